@@ -5,23 +5,23 @@ use halo2_aes::{
         circuit::{Layouter, SimpleFloorPlanner},
         halo2curves::bn256::{Bn256, Fr as Fp, G1Affine},
         plonk::{
-            create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error, ProvingKey,
-            VerifyingKey,
+            create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, Error,
+            ProvingKey, VerifyingKey,
         },
-        poly::{
-            commitment::Params,
-            kzg::{
-                commitment::{KZGCommitmentScheme, ParamsKZG},
-                multiopen::ProverSHPLONK,
-            },
+        poly::kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
         },
-        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
     },
-    table::load_enc_full_table,
-    FixedAes128Config,
+    prover::kzg_params,
+    table::TableLayout,
+    EncryptCursor, FixedAes128Config,
 };
 use rand::rngs::OsRng;
-use std::fs::File;
 
 const SAMPLE_SIZE: usize = 10;
 const K: u32 = 20;
@@ -33,6 +33,23 @@ struct Aes128BenchCircuit {
     pub encrypt_num: usize,
 }
 
+impl Aes128BenchCircuit {
+    fn encrypt_blocks(
+        &self,
+        mut config: FixedAes128Config<K, 5>,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, self.key)?;
+        let mut cursor = EncryptCursor::default();
+        for _ in 0..self.encrypt_num {
+            aes.encrypt(&mut cursor, &mut layouter, self.plaintext)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Circuit<Fp> for Aes128BenchCircuit {
     type Config = FixedAes128Config<K, 5>;
     type FloorPlanner = SimpleFloorPlanner;
@@ -41,18 +58,29 @@ impl Circuit<Fp> for Aes128BenchCircuit {
         FixedAes128Config::configure(meta)
     }
 
-    fn synthesize(
-        &self,
-        mut config: Self::Config,
-        mut layouter: impl Layouter<Fp>,
-    ) -> Result<(), Error> {
-        load_enc_full_table(&mut layouter, config.tables)?;
-        config.schedule_key(&mut layouter, self.key)?;
-        for _ in 0..self.encrypt_num {
-            config.encrypt(&mut layouter, self.plaintext)?;
-        }
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        self.encrypt_blocks(config, layouter)
+    }
 
-        Ok(())
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+// Same workload with the separate lookup tables
+#[derive(Clone, Copy)]
+struct Aes128SeparateTablesBenchCircuit(Aes128BenchCircuit);
+
+impl Circuit<Fp> for Aes128SeparateTablesBenchCircuit {
+    type Config = FixedAes128Config<K, 5>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure_with_layout(meta, TableLayout::Separate)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        self.0.encrypt_blocks(config, layouter)
     }
 
     fn without_witnesses(&self) -> Self {
@@ -69,12 +97,7 @@ fn setup_params<C: Circuit<Fp>>(
     VerifyingKey<G1Affine>,
 ) {
     // load kzg params if available
-    let path = format!("ptau/kzg_bn254_{}.srs", k);
-    let params = if let Ok(mut fs) = File::open(path) {
-        ParamsKZG::<Bn256>::read(&mut fs).expect("Failed to read params")
-    } else {
-        ParamsKZG::<Bn256>::setup(k, OsRng)
-    };
+    let params = kzg_params(k);
     println!("Parameter files loaded");
 
     let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
@@ -82,16 +105,11 @@ fn setup_params<C: Circuit<Fp>>(
     (params, pk, vk)
 }
 
-fn prove_aes128_circuit(_c: &mut Criterion) {
+fn prove_circuit<C: Circuit<Fp> + Copy>(name: &str, circuit: C) {
     let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
-    let circuit = Aes128BenchCircuit {
-        key: [0u8; 16],
-        plaintext: [0u8; 16],
-        encrypt_num: 6000,
-    };
-    let (params, pk, _) = setup_params(K, circuit.clone());
+    let (params, pk, _) = setup_params(K, circuit);
 
-    criterion.bench_function("Prove AES encryption", |b| {
+    criterion.bench_function(name, |b| {
         b.iter(|| {
             let tm = start_timer!(|| "Generating proof");
             let mut transcript =
@@ -104,7 +122,7 @@ fn prove_aes128_circuit(_c: &mut Criterion) {
                 _,
                 _,
                 _,
-            >(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript);
+            >(&params, &pk, &[circuit], &[&[&[]]], OsRng, &mut transcript);
             println!("Error: {:?}", result);
             if result.is_err() {
                 panic!("Create proof fail");
@@ -115,5 +133,76 @@ fn prove_aes128_circuit(_c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, prove_aes128_circuit);
+fn prove_aes128_circuit(_c: &mut Criterion) {
+    let circuit = Aes128BenchCircuit {
+        key: [0u8; 16],
+        plaintext: [0u8; 16],
+        encrypt_num: 6000,
+    };
+    prove_circuit("Prove AES encryption", circuit);
+}
+
+// Compare with `prove_aes128_circuit` to choose the table layout, see `TableLayout`
+fn prove_aes128_separate_tables_circuit(_c: &mut Criterion) {
+    let circuit = Aes128BenchCircuit {
+        key: [0u8; 16],
+        plaintext: [0u8; 16],
+        encrypt_num: 6000,
+    };
+    prove_circuit(
+        "Prove AES encryption with separate tables",
+        Aes128SeparateTablesBenchCircuit(circuit),
+    );
+}
+
+fn verify_aes128_circuit(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+    let circuit = Aes128BenchCircuit {
+        key: [0u8; 16],
+        plaintext: [0u8; 16],
+        encrypt_num: 6000,
+    };
+    let (params, pk, vk) = setup_params(K, circuit.clone());
+
+    let mut transcript = Blake2bWrite::<Vec<u8>, G1Affine, Challenge255<G1Affine>>::init(vec![]);
+    create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        _,
+        _,
+        _,
+    >(&params, &pk, &[circuit], &[&[&[]]], OsRng, &mut transcript)
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    criterion.bench_function("Verify AES encryption", |b| {
+        b.iter(|| {
+            let tm = start_timer!(|| "Verifying proof");
+            let strategy = SingleStrategy::new(&params);
+            let mut transcript =
+                Blake2bRead::<&[u8], G1Affine, Challenge255<G1Affine>>::init(&proof[..]);
+
+            let result = verify_proof::<
+                KZGCommitmentScheme<Bn256>,
+                VerifierSHPLONK<'_, Bn256>,
+                Challenge255<G1Affine>,
+                Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+                SingleStrategy<'_, Bn256>,
+            >(&params, &vk, strategy, &[&[&[]]], &mut transcript);
+            if result.is_err() {
+                panic!("Verify proof fail");
+            }
+
+            end_timer!(tm);
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    prove_aes128_circuit,
+    prove_aes128_separate_tables_circuit,
+    verify_aes128_circuit
+);
 criterion_main!(benches);
@@ -7,14 +7,17 @@ use halo2_aes::{
         circuit::{Layouter, SimpleFloorPlanner},
         halo2curves::bn256::{Bn256, Fr as Fp, G1Affine},
         plonk::{
-            create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error, ProvingKey,
-            TableColumn, VerifyingKey,
+            create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, Error,
+            ProvingKey, TableColumn, VerifyingKey,
         },
         poly::kzg::{
             commitment::{KZGCommitmentScheme, ParamsKZG},
-            multiopen::ProverSHPLONK,
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+        transcript::{
+            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
         },
-        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
     },
     key_schedule::Aes128KeyScheduleConfig,
     table::load_enc_full_table,
@@ -26,6 +29,7 @@ const SAMPLE_SIZE: usize = 10;
 #[derive(Clone, Copy)]
 struct Aes128KeyScheduleBenchCircuit {
     key: [u8; 16],
+    skip_range_check: bool,
 }
 
 impl Circuit<Fp> for Aes128KeyScheduleBenchCircuit {
@@ -74,10 +78,11 @@ impl Circuit<Fp> for Aes128KeyScheduleBenchCircuit {
         config: Self::Config,
         mut layouter: impl Layouter<Fp>,
     ) -> Result<(), Error> {
-        load_enc_full_table(&mut layouter, config.1)?;
+        load_enc_full_table(&mut layouter, config.1, 17)?;
 
-        config
-            .0
+        let mut key_schedule_config = config.0;
+        key_schedule_config.set_skip_range_check(self.skip_range_check);
+        key_schedule_config
             .schedule_keys(&mut layouter.namespace(|| "AES128 schedule key"), self.key)?;
 
         Ok(())
@@ -103,12 +108,60 @@ fn setup_params<C: Circuit<Fp>>(
 }
 
 fn prove_aes128_key_schedule_circuit(_c: &mut Criterion) {
+    bench_prove(false, "prove key scheduling for AES128");
+}
+
+fn prove_aes128_key_schedule_without_range_check_circuit(_c: &mut Criterion) {
+    bench_prove(true, "prove key scheduling for AES128 without range checks");
+}
+
+// Proving with and without the range checks side by side,
+// the difference is the share of the range checks in the key schedule proving time.
+fn prove_aes128_key_schedule_range_check_share(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+    let mut group = criterion.benchmark_group("key scheduling range checks for AES128");
+
+    for skip_range_check in [false, true] {
+        let circuit = Aes128KeyScheduleBenchCircuit {
+            key: [0u8; 16],
+            skip_range_check,
+        };
+        let (params, pk, _) = setup_params(17, circuit);
+        let name = if skip_range_check {
+            "without range checks"
+        } else {
+            "with range checks"
+        };
+
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut transcript =
+                    Blake2bWrite::<Vec<u8>, G1Affine, Challenge255<G1Affine>>::init(vec![]);
+                create_proof::<
+                    KZGCommitmentScheme<Bn256>,
+                    ProverSHPLONK<'_, Bn256>,
+                    Challenge255<G1Affine>,
+                    _,
+                    _,
+                    _,
+                >(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+                .expect("proof generation should not fail");
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_prove(skip_range_check: bool, bench_name: &str) {
     let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
-    let circuit = Aes128KeyScheduleBenchCircuit { key: [0u8; 16] };
+    let circuit = Aes128KeyScheduleBenchCircuit {
+        key: [0u8; 16],
+        skip_range_check,
+    };
     let (params, pk, _) = setup_params(17, circuit.clone());
-    let bench_name = format!("prove key scheduling for AES128");
 
-    criterion.bench_function(&bench_name, |b| {
+    criterion.bench_function(bench_name, |b| {
         b.iter(|| {
             let tm = start_timer!(|| "Generating proof");
             let mut transcript =
@@ -133,5 +186,54 @@ fn prove_aes128_key_schedule_circuit(_c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, prove_aes128_key_schedule_circuit,);
+fn verify_aes128_key_schedule_circuit(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+    let circuit = Aes128KeyScheduleBenchCircuit {
+        key: [0u8; 16],
+        skip_range_check: false,
+    };
+    let (params, pk, vk) = setup_params(17, circuit.clone());
+
+    let mut transcript = Blake2bWrite::<Vec<u8>, G1Affine, Challenge255<G1Affine>>::init(vec![]);
+    create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        _,
+        _,
+        _,
+    >(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    criterion.bench_function("verify key scheduling for AES128", |b| {
+        b.iter(|| {
+            let tm = start_timer!(|| "Verifying proof");
+            let strategy = SingleStrategy::new(&params);
+            let mut transcript =
+                Blake2bRead::<&[u8], G1Affine, Challenge255<G1Affine>>::init(&proof[..]);
+
+            let result = verify_proof::<
+                KZGCommitmentScheme<Bn256>,
+                VerifierSHPLONK<'_, Bn256>,
+                Challenge255<G1Affine>,
+                Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+                SingleStrategy<'_, Bn256>,
+            >(&params, &vk, strategy, &[&[]], &mut transcript);
+            if result.is_err() {
+                panic!("Verify proof fail");
+            }
+
+            end_timer!(tm);
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    prove_aes128_key_schedule_circuit,
+    prove_aes128_key_schedule_without_range_check_circuit,
+    prove_aes128_key_schedule_range_check_share,
+    verify_aes128_key_schedule_circuit,
+);
 criterion_main!(benches);
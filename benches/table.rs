@@ -0,0 +1,129 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use ark_std::{end_timer, start_timer};
+use halo2_aes::{
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        halo2curves::bn256::{Bn256, Fr as Fp, G1Affine},
+        plonk::{create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error},
+        poly::kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
+        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    },
+    prover::kzg_params,
+    table::TableLayout,
+    FixedAes128Config,
+};
+use rand::rngs::OsRng;
+
+const SAMPLE_SIZE: usize = 10;
+// Smallest k holding the table
+const K: u32 = 17;
+
+// Configure the whole AES circuit, i.e. with all its lookups, but only load the table.
+// Its proving time is the share of the table in the proving time of the AES circuit.
+#[derive(Clone, Copy)]
+struct TableBenchCircuit;
+
+impl Circuit<Fp> for TableBenchCircuit {
+    type Config = FixedAes128Config<K, 1>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)
+    }
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+}
+
+// Same with the separate lookup tables
+#[derive(Clone, Copy)]
+struct SeparateTablesBenchCircuit;
+
+impl Circuit<Fp> for SeparateTablesBenchCircuit {
+    type Config = FixedAes128Config<K, 1>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure_with_layout(meta, TableLayout::Separate)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)
+    }
+
+    fn without_witnesses(&self) -> Self {
+        *self
+    }
+}
+
+// The table is fixed, so it's assigned at the keygen.
+fn keygen_table_circuit(_c: &mut Criterion) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+    let params = kzg_params(K);
+    let circuit = TableBenchCircuit;
+
+    criterion.bench_function("keygen lookup table", |b| {
+        b.iter(|| {
+            let tm = start_timer!(|| "Generating keys");
+            let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+            keygen_pk(&params, vk, &circuit).expect("pk generation should not fail");
+            end_timer!(tm);
+        })
+    });
+}
+
+fn prove_table_circuit(_c: &mut Criterion) {
+    bench_prove("prove lookup table", TableBenchCircuit);
+}
+
+// Compare with `prove_table_circuit` for the share of each table layout, see `TableLayout`
+fn prove_separate_tables_circuit(_c: &mut Criterion) {
+    bench_prove("prove separate lookup tables", SeparateTablesBenchCircuit);
+}
+
+fn bench_prove<C: Circuit<Fp> + Copy>(name: &str, circuit: C) {
+    let mut criterion = Criterion::default().sample_size(SAMPLE_SIZE);
+    let params = kzg_params(K);
+    let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("pk generation should not fail");
+
+    criterion.bench_function(name, |b| {
+        b.iter(|| {
+            let tm = start_timer!(|| "Generating proof");
+            let mut transcript =
+                Blake2bWrite::<Vec<u8>, G1Affine, Challenge255<G1Affine>>::init(vec![]);
+            create_proof::<
+                KZGCommitmentScheme<Bn256>,
+                ProverSHPLONK<'_, Bn256>,
+                Challenge255<G1Affine>,
+                _,
+                _,
+                _,
+            >(&params, &pk, &[circuit], &[&[&[]]], OsRng, &mut transcript)
+            .expect("proof generation should not fail");
+            end_timer!(tm);
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    keygen_table_circuit,
+    prove_table_circuit,
+    prove_separate_tables_circuit
+);
+criterion_main!(benches);
@@ -0,0 +1,94 @@
+//! Batch encryption of records under one committed key, e.g. for a rollup.
+//!
+//! The prover encrypts each record with the same private key and proves that the public
+//! ciphertexts are the encryptions of the records under the key of the public commitment.
+//! The instance column is laid out as, see `hash::batch_public_inputs`:
+//!
+//! | rows                 | public input                                   |
+//! |----------------------|------------------------------------------------|
+//! | 0                    | key commitment, the Poseidon hash of the key   |
+//! | 1 + 16i to 16 + 16i  | the 16 bytes of the ciphertext of the record i |
+//!
+//! cargo run --release --example batch_commitment --features poseidon
+
+use aes::{
+    cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit},
+    Aes128,
+};
+use halo2_aes::{
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+    hash::{batch_public_inputs, BlockHashConfig},
+    EncryptCursor, FixedAes128Config,
+};
+
+const K: u32 = 17;
+
+#[derive(Clone)]
+struct BatchCircuit {
+    key: [u8; 16],
+    records: Vec<[u8; 16]>,
+}
+
+impl Circuit<Fp> for BatchCircuit {
+    type Config = (FixedAes128Config<K, 1>, BlockHashConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (
+            FixedAes128Config::configure(meta),
+            BlockHashConfig::configure(meta),
+        )
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (mut config, hash_config) = config;
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, self.key)?;
+        let mut cursor = EncryptCursor::default();
+        aes.batch_encrypt_committed(&mut cursor, &mut layouter, &hash_config, &self.records)?;
+
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+}
+
+fn main() {
+    let key = [0x2b; 16];
+    let records = (0..4u8)
+        .map(|i| std::array::from_fn(|j| 16 * i + j as u8))
+        .collect::<Vec<[u8; 16]>>();
+
+    let cipher = Aes128::new(&GenericArray::from(key));
+    let ciphertexts = records
+        .iter()
+        .map(|&record| {
+            let mut block = GenericArray::from(record);
+            cipher.encrypt_block(&mut block);
+            let mut ciphertext = [0u8; 16];
+            ciphertext.copy_from_slice(&block);
+            ciphertext
+        })
+        .collect::<Vec<_>>();
+    let instance = batch_public_inputs(key, &ciphertexts);
+    assert_eq!(instance.len(), 1 + 16 * records.len());
+
+    let circuit = BatchCircuit { key, records };
+    let mock = MockProver::run(K, &circuit, vec![instance]).unwrap();
+    mock.assert_satisfied();
+    println!(
+        "Batch of {} records under the committed key satisfied",
+        ciphertexts.len()
+    );
+}
@@ -0,0 +1,108 @@
+//! Verifiable encryption with a committed CTR keystream.
+//!
+//! The prover encrypts a message in CTR mode and proves, without revealing the key,
+//! that the public commitments are the Poseidon hashes of the keystream blocks of
+//! the public IV under the key. Later, the key holder reveals the keystream,
+//! and anyone can check it against the commitments and decrypt the ciphertext.
+//!
+//! cargo run --release --example ctr_commitment --features poseidon
+
+use halo2_aes::{
+    ctr_chain::add_counter,
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+    hash::{keystream_commitments, BlockHashConfig},
+    prover::{keygen, kzg_params, prove_kzg, verify_kzg},
+    reference::{encrypt_block_reference, key_schedule_reference},
+    EncryptCursor, FixedAes128Config,
+};
+
+const K: u32 = 17;
+const BLOCKS: usize = 2;
+
+#[derive(Clone)]
+struct KeystreamCircuit {
+    key: [u8; 16],
+    iv: [u8; 16],
+}
+
+impl Circuit<Fp> for KeystreamCircuit {
+    type Config = (FixedAes128Config<K, 1>, BlockHashConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (
+            FixedAes128Config::configure(meta),
+            BlockHashConfig::configure(meta),
+        )
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (mut config, hash_config) = config;
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, self.key)?;
+        let mut cursor = EncryptCursor::default();
+        aes.ctr_commit_keystream(&mut cursor, &mut layouter, &hash_config, self.iv, BLOCKS)?;
+
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+}
+
+fn keystream(key: [u8; 16], iv: [u8; 16]) -> Vec<u8> {
+    let round_keys = key_schedule_reference(key);
+    (0..BLOCKS as u64)
+        .flat_map(|i| encrypt_block_reference(&round_keys, add_counter(iv, i)))
+        .collect()
+}
+
+fn main() {
+    let key = [0x2b; 16];
+    let iv = [0xf0; 16];
+    let message = b"a message of 32 bytes to encrypt";
+
+    // The prover encrypts the message and proves the keystream commitments
+    let ciphertext = message
+        .iter()
+        .zip(keystream(key, iv))
+        .map(|(m, k)| m ^ k)
+        .collect::<Vec<_>>();
+    let commitments = keystream_commitments(&keystream(key, iv));
+    let instance = iv
+        .iter()
+        .map(|&b| Fp::from(b as u64))
+        .chain(commitments.iter().copied())
+        .collect::<Vec<_>>();
+
+    let circuit = KeystreamCircuit { key, iv };
+    let params = kzg_params(K);
+    let (pk, vk) = keygen(&params, &circuit).expect("key generation should not fail");
+    let proof =
+        prove_kzg(&params, &pk, circuit, &[&instance]).expect("proof generation should not fail");
+    verify_kzg(&params, &vk, &proof, &[&instance]).expect("proof should verify");
+    println!(
+        "Keystream commitments of {} bytes proof verified",
+        proof.len()
+    );
+
+    // Later the key holder reveals the keystream, anyone checks and decrypts
+    let revealed = keystream(key, iv);
+    assert_eq!(keystream_commitments(&revealed), commitments);
+    let decrypted = ciphertext
+        .iter()
+        .zip(revealed)
+        .map(|(c, k)| c ^ k)
+        .collect::<Vec<_>>();
+    assert_eq!(decrypted, message);
+    println!("Revealed keystream matches the commitments");
+}
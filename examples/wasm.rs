@@ -0,0 +1,88 @@
+//! Proving without OS randomness or file I/O, as needed in the browser.
+//!
+//! The params are generated in memory and the blinding factors come from a seeded RNG,
+//! so this builds for wasm32-unknown-unknown:
+//!
+//! cargo build --release --example wasm --target wasm32-unknown-unknown
+//!
+//! NOTE: The seeded params are insecure, load a real setup from bytes in production.
+//! Seed the prover RNG from the browser's crypto API rather than a constant.
+
+use halo2_aes::{
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        halo2curves::bn256::{Bn256, Fr as Fp},
+        plonk::{Circuit, ConstraintSystem, Error},
+        poly::kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::ProverSHPLONK,
+        },
+    },
+    prover::{keygen, prove_with_rng, verify_kzg},
+    EncryptCursor, FixedAes128Config,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+const K: u32 = 17;
+
+// FIPS-197 Appendix C.1
+const KEY: [u8; 16] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+];
+const PLAINTEXT: [u8; 16] = [
+    0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+];
+const CIPHERTEXT: [u8; 16] = [
+    0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+];
+
+#[derive(Clone)]
+struct WasmCircuit;
+
+impl Circuit<Fp> for WasmCircuit {
+    type Config = FixedAes128Config<K, 1>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, KEY)?;
+        let mut cursor = EncryptCursor::default();
+        let ciphertext = aes.encrypt(&mut cursor, &mut layouter, PLAINTEXT)?;
+        aes.expose_ciphertext(&mut cursor, &mut layouter, &ciphertext)?;
+
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        Self
+    }
+}
+
+fn main() {
+    let params = ParamsKZG::<Bn256>::setup(K, StdRng::seed_from_u64(0));
+    let (pk, vk) = keygen(&params, &WasmCircuit).expect("key generation should not fail");
+
+    let instance = CIPHERTEXT
+        .iter()
+        .map(|&b| Fp::from(b as u64))
+        .collect::<Vec<_>>();
+    let proof = prove_with_rng::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _>(
+        &params,
+        &pk,
+        WasmCircuit,
+        &[&instance],
+        StdRng::seed_from_u64(1),
+    )
+    .expect("proof generation should not fail");
+
+    verify_kzg(&params, &vk, &proof, &[&instance]).expect("proof should verify");
+    println!("Proof of {} bytes verified", proof.len());
+}
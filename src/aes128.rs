@@ -1,19 +1,168 @@
+use std::ops::{Deref, Range};
+
+#[cfg(feature = "poseidon")]
+use crate::hash::BlockHashConfig;
 use crate::{
+    block::{Ciphertext, Plaintext},
     chips::{
+        byte_pack_chip::{BytePackChip, BytePackConfig, MAX_PACKED_BYTES},
+        counter_chip::{CounterChip, CounterConfig, CounterMode},
+        gf128_mul_chip::{Gf128MulChip, Gf128MulConfig},
         gf_mul_chip::{MulBy2Chip, MulBy2Config, MulBy3Chip, MulBy3Config},
+        inv_cipher_chip::{InvCipherChip, InvCipherConfig},
+        not_equal_chip::{NotEqualChip, NotEqualConfig},
         sbox_chip::{SboxChip, SboxConfig},
+        select_chip::{SelectChip, SelectConfig},
+        u64_decompose_chip::{U64DecomposeChip, U64DecomposeConfig},
         u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
         u8_xor_chip::{U8XorChip, U8XorConfig},
     },
-    constant::{AES_ROWS, KEY_SCHEDULE_ROWS},
+    constant::{
+        AES_ROWS, CBC_BLOCK_ROWS, CTR_BLOCK_ROWS, DECRYPT_ROWS, GHASH_BLOCK_ROWS,
+        INITIAL_ROUND_ROWS, INV_MIX_COLUMNS, KEY_SCHEDULE_ROWS, KEY_SELECT_ROWS,
+        KEY_SELECT_ROWS_PER_KEY, KEY_WRAP_IV, LAST_ROUND_ROWS, MIX_COLUMNS, NOT_EQUAL_ROWS,
+        PACKED_BLOCK_ROWS, ROUND_ROWS,
+    },
+    error::AesError,
     halo2_proofs::{
-        circuit::{AssignedCell, Layouter, Value},
+        circuit::{AssignedCell, Cell, Layouter, Value},
         halo2curves::bn256::Fr as Fp,
-        plonk::{Advice, Column, ConstraintSystem, Error, TableColumn},
+        plonk::{Advice, Column, ConstraintSystem, Error, Instance},
     },
     key_schedule::Aes128KeyScheduleConfig,
+    reference::WitnessTrace,
+    state::State,
+    table::{assert_table_fits, min_table_k, TableColumns, TableLayout, BLINDING_ROWS},
+    utils::{assert_byte_equals, cells_to_bytes, pkcs7_pad},
 };
 
+/// Build the public input vector for the instance column.
+/// The order is ciphertext, nonce and key, skipping the absent ones.
+/// This must match the order of `expose_ciphertext`, `expose_nonce` and `expose_key` calls.
+pub fn build_public_inputs(
+    ciphertext: Option<[u8; 16]>,
+    nonce: Option<[u8; 16]>,
+    key: Option<[u8; 16]>,
+) -> Vec<Fp> {
+    [ciphertext, nonce, key]
+        .iter()
+        .flatten()
+        .flat_map(|bytes| bytes.iter().map(|&b| Fp::from(b as u64)))
+        .collect()
+}
+
+// Name the advice columns by the roles of the chips sharing them.
+// The chips of group i all take their inputs from the first columns.
+#[cfg(feature = "halo2-pse")]
+fn annotate_advice_columns<const N: usize>(
+    meta: &mut ConstraintSystem<Fp>,
+    advices: &[[Column<Advice>; 3]; N],
+) {
+    for (i, group) in advices.iter().enumerate() {
+        meta.annotate_lookup_any_column(group[0], || format!("g{}_range_x/xor_x/sbox_x/mul_x", i));
+        meta.annotate_lookup_any_column(group[1], || format!("g{}_xor_y/sbox_y/mul_y", i));
+        meta.annotate_lookup_any_column(group[2], || format!("g{}_xor_z", i));
+    }
+}
+
+// Number of AES calls fitting in n groups of 2^k rows
+fn max_aes_calls(k: u32, n: usize) -> u64 {
+    let rows = u64::pow(2, k).saturating_sub(BLINDING_ROWS as u64);
+    rows.saturating_sub(KEY_SCHEDULE_ROWS) / AES_ROWS
+        + (n as u64).saturating_sub(1) * (rows / AES_ROWS)
+}
+
+/// Check that `num_blocks` encryptions fit in the circuit of 2^k rows with n groups
+/// of advice columns, before running into the same problems one at a time at the synthesis.
+/// Returns all the violations: no groups, the lookup table or the key schedule
+/// not fitting in 2^k rows, and the blocks not fitting in the rows left.
+pub fn validate_params(k: u32, n: usize, num_blocks: u64) -> Result<(), Vec<AesError>> {
+    let mut errors = vec![];
+
+    if n == 0 {
+        errors.push(AesError::NoGroups);
+    }
+    if let Err(err) = assert_table_fits(k) {
+        errors.push(err);
+    }
+    if u64::pow(2, k).saturating_sub(BLINDING_ROWS as u64) < KEY_SCHEDULE_ROWS {
+        errors.push(AesError::KeyScheduleTooLargeForK {
+            rows: KEY_SCHEDULE_ROWS,
+            k,
+        });
+    }
+    let max = max_aes_calls(k, n);
+    if num_blocks > max {
+        errors.push(AesError::TooManyBlocks { num_blocks, max });
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+// The key data of AES Key Wrap is n >= 2 blocks of 8 bytes.
+fn check_key_data_length(len: usize) -> Result<(), AesError> {
+    if len % 8 == 0 && len >= 16 {
+        Ok(())
+    } else {
+        Err(AesError::InvalidKeyDataLength { len })
+    }
+}
+
+/// Smallest k such that `num_blocks` encryptions, the key schedule and the full table
+/// fit in the circuit with n groups of advice columns.
+/// Never below 17 because of the size of the table.
+pub fn recommended_k(num_blocks: u64, n: usize) -> u32 {
+    assert!(n > 0, "n should be positive");
+
+    let mut k = min_table_k();
+    while max_aes_calls(k, n) < num_blocks {
+        k += 1;
+    }
+    k
+}
+
+// Print the state after the round to stderr, like the `round[ r].k_sch` lines
+// of FIPS-197 Appendix C, e.g. to find where the witness diverges from the expected one.
+// Nothing is printed when the witness is unknown, e.g. during keygen.
+#[cfg(feature = "debug-print")]
+fn debug_print_state(round: usize, state: &[AssignedCell<Fp, Fp>]) {
+    cells_to_bytes(state).map(|bytes| {
+        let hex = bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        eprintln!("round[{:2}].state {}", round, hex);
+    });
+}
+
+// Outputs of the chips of one encryption: computed from their inputs,
+// or taken in order from a `WitnessTrace`.
+enum Outputs<'a> {
+    Computed,
+    Traced { bytes: &'a [u8], next: usize },
+}
+
+impl Outputs<'_> {
+    // The next output of the trace, None if computed.
+    // Fails with `AesError::InvalidTraceLength` once the trace is exhausted.
+    fn next(&mut self) -> Result<Option<Value<Fp>>, AesError> {
+        match self {
+            Outputs::Computed => Ok(None),
+            Outputs::Traced { bytes, next } => {
+                let byte = bytes
+                    .get(*next)
+                    .ok_or(AesError::InvalidTraceLength { len: bytes.len() })?;
+                *next += 1;
+                Ok(Some(Value::known(Fp::from(*byte as u64))))
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 struct Configs(
     Vec<U8RangeCheckConfig>,
@@ -21,17 +170,42 @@ struct Configs(
     Vec<SboxConfig>,
     Vec<MulBy2Config>,
     Vec<MulBy3Config>,
+    Vec<CounterConfig>,
+    Vec<Gf128MulConfig>,
+    Vec<U64DecomposeConfig>,
+    Vec<NotEqualConfig>,
+    Vec<BytePackConfig>,
+    Vec<InvCipherConfig>,
+    // Only in the first group, where the keys are scheduled
+    SelectConfig,
 );
 
+/// AES-128 encryption with a fixed key in a circuit of 2^K rows.
+/// Uses N groups of 3 advice columns; each group holds 2^K / 1360 encryptions
+/// (minus the key schedule in the first group), and moves on to the next group when full.
 #[derive(Clone, Debug)]
 pub struct FixedAes128Config<const K: u32, const N: usize> {
-    keys: Option<Vec<Vec<AssignedCell<Fp, Fp>>>>,
-
     pub key_schedule_config: Aes128KeyScheduleConfig,
 
     configs: Configs,
     pub advices: [[Column<Advice>; 3]; N],
-    pub tables: [TableColumn; 4],
+    pub tables: TableColumns,
+    // The instance columns, only the first one unless configured with more,
+    // see `configure_with_instances`.
+    pub instances: Vec<Column<Instance>>,
+
+    // Set by `load_tables`. The lookups fail on an empty table otherwise.
+    tables_loaded: bool,
+}
+
+/// Progress of the layout of `FixedAes128Config`,
+/// i.e. which group of columns is used, how many rows of it are used and
+/// the next row of each instance column.
+/// It's kept apart from the config, so that the config stays immutable while laying out.
+#[derive(Clone, Debug, Default)]
+pub struct EncryptCursor {
+    // Next row of each instance column to be exposed, missing for the unused ones
+    instance_offsets: Vec<usize>,
 
     // Indicate which columns are currently used.
     // increment this by one once the available cells of advices[i][0]
@@ -40,17 +214,114 @@ pub struct FixedAes128Config<const K: u32, const N: usize> {
 
     // Count number of AES calls
     count: u64,
+
+    // Rows used by gadgets other than AES in the current column
+    extra_rows: u64,
+
+    // First round key cell of each key schedule whose rows are accounted.
+    // The first one is in KEY_SCHEDULE_ROWS, the others in extra_rows.
+    key_schedules: Vec<Cell>,
+}
+
+impl EncryptCursor {
+    /// Reset the progress to the start of the layout.
+    ///
+    /// This is for reusing the Rust-side objects to drive a new synthesis,
+    /// e.g. generating many independent proofs in a loop.
+    /// Together with `Aes128Scheduled::into_config`, the config can schedule a new key.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Layout of the public inputs so far: the number of rows exposed in each instance column,
+    /// i.e. the lengths of the vectors of the instance passed to the prover, in column order.
+    /// Trailing columns without any exposed row are omitted.
+    pub fn instance_rows(&self) -> &[usize] {
+        &self.instance_offsets
+    }
+}
+
+/// Ciphertext of one block with the group of columns its cells are in.
+#[derive(Clone, Debug)]
+pub struct EncryptOutput {
+    pub cells: Vec<AssignedCell<Fp, Fp>>,
+    pub group: usize,
 }
 
+/// Compact layout with a single group of 3 advice columns.
+///
+/// Width (number of columns) is minimal, which makes the circuit cheaper to verify
+/// e.g. in a recursive verifier, at the cost of depth: all encryptions are laid out
+/// in one column so K has to be large enough to hold them, and proving a 2^K row
+/// circuit is slower than N groups of 2^(K - log N) rows.
+/// Use `max_aes_calls` to see how many blocks fit.
+pub type CompactAes128Config<const K: u32> = FixedAes128Config<K, 1>;
+
 impl<const K: u32, const N: usize> FixedAes128Config<K, N> {
+    // Evaluated where the config is used, so that the invalid parameters fail to compile
+    // instead of indexing out of bounds or overflowing the rows at the synthesis.
+    const VALID_PARAMS: () = {
+        assert!(
+            N >= 1,
+            "FixedAes128Config needs at least one group of columns"
+        );
+        assert!(K >= min_table_k(), "2^K rows can't hold the lookup table");
+    };
+
+    /// Check that `num_blocks` encryptions fit in this config, see `validate_params`.
+    /// Call it before the synthesis to get all the violations at once.
+    pub fn validate(&self, num_blocks: u64) -> Result<(), Vec<AesError>> {
+        validate_params(K, N, num_blocks)
+    }
+
+    /// Maximum number of `encrypt` calls that fit in the circuit.
+    pub fn max_aes_calls() -> u64 {
+        let () = Self::VALID_PARAMS;
+        max_aes_calls(K, N)
+    }
+
+    /// Configure N groups of columns with the combined lookup table.
+    ///
+    /// N must be at least 1 and K at least the one holding the lookup table (17),
+    /// otherwise it fails to compile.
     pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
-        // First table_column is used as a tag column
-        let tables = [
-            meta.lookup_table_column(),
-            meta.lookup_table_column(),
-            meta.lookup_table_column(),
-            meta.lookup_table_column(),
-        ];
+        Self::configure_with_layout(meta, TableLayout::Combined)
+    }
+
+    /// Configure N groups of columns with the given layout of the lookup tables.
+    pub fn configure_with_layout(meta: &mut ConstraintSystem<Fp>, layout: TableLayout) -> Self {
+        Self::configure_with_instances(meta, layout, 1)
+    }
+
+    /// Same as `configure_with_layout` with `num_instances` instance columns,
+    /// e.g. to expose the ciphertexts of each group of columns in its own instance column
+    /// with `expose_ciphertext_at`, rather than all of them in the first one.
+    pub fn configure_with_instances(
+        meta: &mut ConstraintSystem<Fp>,
+        layout: TableLayout,
+        num_instances: usize,
+    ) -> Self {
+        Self::configure_with_options(meta, layout, num_instances, 0)
+    }
+
+    /// Same as `configure` with all the tags of the combined table shifted by `tag_offset`,
+    /// e.g. to share the table columns with another tagged table of a larger circuit.
+    /// `load_tables` loads the table with the same offset.
+    pub fn configure_with_tag_offset(meta: &mut ConstraintSystem<Fp>, tag_offset: u64) -> Self {
+        Self::configure_with_options(meta, TableLayout::Combined, 1, tag_offset)
+    }
+
+    fn configure_with_options(
+        meta: &mut ConstraintSystem<Fp>,
+        layout: TableLayout,
+        num_instances: usize,
+        tag_offset: u64,
+    ) -> Self {
+        let () = Self::VALID_PARAMS;
+        assert!(num_instances > 0, "at least one instance column");
+
+        // First table_column of each table is used as a tag column
+        let tables = TableColumns::configure_with_tag_offset(meta, layout, tag_offset);
         let advices = std::array::from_fn(|_| {
             [
                 meta.advice_column(),
@@ -58,59 +329,117 @@ impl<const K: u32, const N: usize> FixedAes128Config<K, N> {
                 meta.advice_column(),
             ]
         });
-        let mut configs = Configs(vec![], vec![], vec![], vec![], vec![]);
+        let select_config = SelectChip::configure(meta, advices[0]);
+        let mut configs = Configs(
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            select_config,
+        );
 
         for i in 0..N {
+            // One complex selector per operation. A single selector with a tag advice cell
+            // selecting the operation wouldn't lower the degree, the lookup inputs being
+            // q * x of degree 2 either way. Nor would it save much by itself: the selectors
+            // are fixed columns committed once in the verifying key, while the proving cost
+            // is in the 6 lookup arguments of each group, each committing 2 permuted columns
+            // and a product column. Merging them into one argument needs every operation to
+            // look up the same (tag, x, y, z) cells with the unused ones zero, i.e. a tag
+            // advice column per group and no other region in the rows of a range check or
+            // an S-box.
             let q_u8_range_check = meta.complex_selector();
             let q_u8_xor = meta.complex_selector();
             let q_sbox = meta.complex_selector();
             let q_mul_by_2 = meta.complex_selector();
             let q_mul_by_3 = meta.complex_selector();
 
-            configs.0.push(U8RangeCheckChip::configure(
+            configs.0.push(U8RangeCheckChip::configure_with_tag_offset(
                 meta,
                 advices[i][0],
                 q_u8_range_check,
-                tables[0],
-                tables[1],
+                tables.u8[0],
+                tables.u8[1],
+                tag_offset,
             ));
-            configs.1.push(U8XorChip::configure(
+            configs.1.push(U8XorChip::configure_with_tag_offset(
                 meta,
                 advices[i][0],
                 advices[i][1],
                 advices[i][2],
                 q_u8_xor,
-                tables[0],
-                tables[1],
-                tables[2],
-                tables[3],
+                tables.xor[0],
+                tables.xor[1],
+                tables.xor[2],
+                tables.xor[3],
+                tag_offset,
             ));
-            configs.2.push(SboxChip::configure(
+            configs.2.push(SboxChip::configure_with_tag_offset(
                 meta,
                 advices[i][0],
                 advices[i][1],
                 q_sbox,
-                tables[0],
-                tables[1],
-                tables[2],
+                tables.sbox[0],
+                tables.sbox[1],
+                tables.sbox[2],
+                tag_offset,
             ));
-            configs.3.push(MulBy2Chip::configure(
+            configs.3.push(MulBy2Chip::configure_with_tag_offset(
                 meta,
                 advices[i][0],
                 advices[i][1],
                 q_mul_by_2,
-                tables[0],
-                tables[1],
-                tables[2],
+                tables.mul2[0],
+                tables.mul2[1],
+                tables.mul2[2],
+                tag_offset,
             ));
-            configs.4.push(MulBy3Chip::configure(
+            configs.4.push(MulBy3Chip::configure_with_tag_offset(
                 meta,
                 advices[i][0],
                 advices[i][1],
                 q_mul_by_3,
-                tables[0],
-                tables[1],
-                tables[2],
+                tables.mul3[0],
+                tables.mul3[1],
+                tables.mul3[2],
+                tag_offset,
+            ));
+            configs
+                .5
+                .push(CounterChip::configure(meta, advices[i], configs.0[i]));
+            configs.6.push(Gf128MulChip::configure(
+                meta,
+                advices[i],
+                configs.0[i],
+                configs.1[i],
+            ));
+            configs.7.push(U64DecomposeChip::configure(
+                meta,
+                [advices[i][0], advices[i][1]],
+                configs.0[i],
+            ));
+            configs.8.push(NotEqualChip::configure(meta, advices[i]));
+            configs.9.push(BytePackChip::configure(
+                meta,
+                [advices[i][0], advices[i][1]],
+                configs.0[i],
+            ));
+            // The inverse S-box and the 4 coefficients of InvMixColumns share one lookup
+            configs.10.push(InvCipherChip::configure_with_tag_offset(
+                meta,
+                advices[i][0],
+                advices[i][1],
+                tables.inv[0],
+                tables.inv[1],
+                tables.inv[2],
+                tag_offset,
             ));
         }
 
@@ -129,350 +458,1966 @@ impl<const K: u32, const N: usize> FixedAes128Config<K, N> {
             })
         });
 
+        let instances = (0..num_instances)
+            .map(|_| {
+                let instance = meta.instance_column();
+                meta.enable_equality(instance);
+                instance
+            })
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "halo2-pse")]
+        {
+            tables.annotate(meta);
+            annotate_advice_columns(meta, &advices);
+            key_schedule_config.annotate_columns(meta);
+            for (i, &instance) in instances.iter().enumerate() {
+                meta.annotate_lookup_any_column(instance, || format!("instance_{}", i));
+            }
+        }
+
         Self {
-            keys: None,
             key_schedule_config,
             advices,
             tables,
+            instances,
             configs,
-            current: 0,
-            count: 0,
+            tables_loaded: false,
         }
     }
 
+    /// Load the lookup tables, which must be done before encrypting.
+    /// Encryption fails with `AesError::TablesNotLoaded`, as `Error::Synthesis`, otherwise.
+    pub fn load_tables(&mut self, layouter: &mut impl Layouter<Fp>) -> Result<(), Error> {
+        self.tables.load(layouter, K)?;
+        self.tables_loaded = true;
+        Ok(())
+    }
+
+    /// Load only the lookup tables of the inverse cipher, see `TableColumns::load_dec`,
+    /// e.g. for a circuit decrypting under precomputed round keys.
+    pub fn load_dec_tables(&mut self, layouter: &mut impl Layouter<Fp>) -> Result<(), Error> {
+        self.tables.load_dec(layouter, K)?;
+        self.tables_loaded = true;
+        Ok(())
+    }
+
+    /// Schedule the key, returning the config which can encrypt under the key.
+    /// Only one key is accounted in the rows, schedule more keys in the same layout
+    /// with `Aes128Scheduled::schedule_another_key`.
     pub fn schedule_key(
-        &mut self,
+        self,
         layouter: &mut impl Layouter<Fp>,
         key: [u8; 16],
-    ) -> Result<(), Error> {
-        let round_keys = self.key_schedule_config.schedule_keys(layouter, key)?;
-        self.keys = Some(round_keys);
+    ) -> Result<Aes128Scheduled<K, N>, Error> {
+        let keys = self.key_schedule_config.schedule_keys(layouter, key)?;
 
-        Ok(())
+        Ok(Aes128Scheduled { config: self, keys })
     }
 
-    pub fn encrypt(
-        &mut self,
+    /// Assign precomputed round keys instead of scheduling them from the key.
+    ///
+    /// NOTE: The round keys are only range checked and the key expansion is NOT constrained.
+    /// A proof using this is not sound, so use it only for testing or cost estimation.
+    pub fn schedule_key_precomputed(
+        self,
         layouter: &mut impl Layouter<Fp>,
-        plaintext: [u8; 16],
-    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
-        // Check if available rows of advice[0] is more than 1360
-        if !self.aes_callable() {
-            panic!("AES calls too many. doesn't fit in the rows")
-        }
-        self.count += 1;
+        round_keys: [[u8; 16]; 11],
+    ) -> Result<Aes128Scheduled<K, N>, Error> {
+        let keys = self
+            .key_schedule_config
+            .assign_precomputed_keys(layouter, round_keys)?;
+
+        Ok(Aes128Scheduled { config: self, keys })
+    }
 
-        // Prepare chips
-        let xor_chip = U8XorChip::construct(self.xor_config());
-        let sbox_chip = SboxChip::construct(self.sbox_config());
-        let _range_chip = U8RangeCheckChip::construct(self.range_config());
+    /// Encrypt under round keys already scheduled in the same circuit, see
+    /// `Aes128Scheduled::take_round_keys`, instead of scheduling the key again.
+    ///
+    /// The round key cells are copied into the encryptions, so no rows are used here.
+    /// They must be assigned by the same layouter in the same synthesis,
+    /// a cell of another circuit or of a previous synthesis doesn't exist in this one.
+    /// Panics if there aren't 11 round keys of 16 bytes.
+    pub fn set_round_keys(
+        self,
+        round_keys: Vec<Vec<AssignedCell<Fp, Fp>>>,
+    ) -> Aes128Scheduled<K, N> {
+        assert!(
+            round_keys.len() == 11 && round_keys.iter().all(|round_key| round_key.len() == 16),
+            "Round keys should be 11 round keys of 16 bytes"
+        );
 
-        let round_keys = self.keys.clone().expect("Keys should be scheduled");
+        Aes128Scheduled {
+            config: self,
+            keys: round_keys,
+        }
+    }
 
-        let advices = self.get_advices();
+    /// Schedule several fixed keys and select one of them by a public index,
+    /// e.g. for key rotation.
+    ///
+    /// The keys are constants of the circuit and `key_index` is exposed to the next row
+    /// of the instance column. The round keys used by the encryption are constrained
+    /// to be those of the key at the index, so a wrong index fails the verification.
+    /// All the keys are scheduled in the first group of columns.
+    /// Fails with `AesError::NoKeys` without any key.
+    pub fn schedule_key_select(
+        self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        keys: &[[u8; 16]],
+        key_index: usize,
+    ) -> Result<Aes128Scheduled<K, N>, Error> {
+        if keys.is_empty() {
+            return Err(AesError::NoKeys.into());
+        }
+        let m = keys.len() as u64;
 
-        // TODO: decide if open the plaintext as instance
-        // Assign 16 bytes in cells
-        let assigned_plaintext = layouter.assign_region(
-            || "Assign plaintext",
-            |mut region| {
-                plaintext
-                    .iter()
-                    .enumerate()
-                    .map(|(i, &p)| {
-                        region.assign_advice(
-                            || "Assign plaintext",
-                            advices[0],
-                            i,
-                            || Value::known(Fp::from(p as u64)),
-                        )
-                    })
-                    .collect::<Result<Vec<_>, Error>>()
-            },
-        )?;
+        // The first key schedule is already subtracted from the rows of the first columns.
+        let rows = (m - 1) * KEY_SCHEDULE_ROWS + m * KEY_SELECT_ROWS_PER_KEY + KEY_SELECT_ROWS;
+        self.add_key_schedule(cursor, rows)?;
 
-        let mut prev_round = assigned_plaintext
+        let select_chip = SelectChip::construct(self.configs.11);
+        let schedules = keys
             .iter()
-            .zip(round_keys[0].clone())
-            .map(|(p, k)| xor_chip.xor(layouter, p, &k))
+            .map(|key| {
+                let key = self.assign_constant_bytes(cursor, layouter, key)?;
+                self.key_schedule_config
+                    .schedule_keys_from_cells(layouter, &key)
+            })
             .collect::<Result<Vec<_>, Error>>()?;
 
-        // we have 4 words in round_out vec.
-        for no_round in 1..11 {
-            // Sub round_out
-            let subbed = prev_round
-                .iter()
-                .map(|byte| sbox_chip.substitute(layouter, byte))
-                .collect::<Result<Vec<_>, Error>>()?
-                .chunks(4)
-                .map(|word| word.to_vec())
-                .collect::<Vec<_>>();
-
-            // Shift rows is just copy constraints.
-            // 1st word (0,0) (1,1) (2,2) (3,3)
-            // 2nd word (0,1) (1,2) (2,3) (3,0)
-            // 3rd word (0,2) (1,3) (2,0) (3,1)
-            // 4th word (0,3) (1,0) (2,1) (3,2)
-            let mut shifted = vec![];
-            for i in 0..4 {
-                let mut inner = vec![];
-                for j in 0..4 {
-                    inner.push(subbed[(i + j) % 4][j].clone());
-                }
-                shifted.push(inner);
-            }
+        let index = select_chip.assign_index(layouter, Value::known(key_index as u64))?;
+        self.expose_bytes(cursor, layouter, std::slice::from_ref(&index))?;
+        let bits = select_chip.select_bits(layouter, &index, keys.len())?;
 
-            // Mixcolumns
-            // do linear transformation to the columns.
-            // for each column(word) multiply by matrix
-            let matrix = vec![
-                vec![2, 3, 1, 1],
-                vec![1, 2, 3, 1],
-                vec![1, 1, 2, 3],
-                vec![3, 1, 1, 2],
-            ];
-
-            // Now e have 4*4 = 16 bytes in the mixed
-            let mixed = if no_round == 10 {
-                shifted.clone()
-            } else {
-                shifted
-                    .iter()
-                    .map(|word| {
-                        matrix
+        let selected = (0..11)
+            .map(|round| {
+                (0..16)
+                    .map(|i| {
+                        let values = schedules
                             .iter()
-                            .map(|col| self.lcon(layouter, word, col))
-                            .collect::<Result<Vec<_>, Error>>()
+                            .map(|schedule| &schedule[round][i])
+                            .collect::<Vec<_>>();
+                        select_chip.select(layouter, &bits, &values)
                     })
-                    .collect::<Result<Vec<Vec<_>>, Error>>()?
-            };
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
 
-            prev_round = mixed
-                .iter()
-                .enumerate()
-                .map(|(i, word)| {
-                    (0..4)
-                        .map(|j| xor_chip.xor(layouter, &word[j], &round_keys[no_round][i * 4 + j]))
-                        .collect::<Result<Vec<_>, Error>>()
-                })
-                .collect::<Result<Vec<Vec<_>>, Error>>()?
-                .into_iter()
-                .flatten()
-                .collect::<Vec<_>>();
-        }
+        Ok(Aes128Scheduled {
+            config: self,
+            keys: selected,
+        })
+    }
 
-        Ok(prev_round)
+    /// Constrain the ciphertext bytes to the next rows of the instance column.
+    pub fn expose_ciphertext(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        ciphertext: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(), Error> {
+        self.expose_bytes(cursor, layouter, ciphertext)
     }
 
-    // Compute linear combination of word and given coefficients
-    fn lcon(
-        &mut self,
+    /// Constrain the ciphertext bytes packed by 31 into field elements to the next rows
+    /// of the instance column, i.e. one row per 31 bytes instead of one per byte,
+    /// see `utils::pack_bytes` for the public inputs. Returns the packed cells.
+    /// Unlike a hash of the ciphertext, the bytes can be recovered from the public inputs.
+    pub fn expose_ciphertext_packed(
+        &self,
+        cursor: &mut EncryptCursor,
         layouter: &mut impl Layouter<Fp>,
-        word: &Vec<AssignedCell<Fp, Fp>>,
-        coeffs: &Vec<u32>,
-    ) -> Result<AssignedCell<Fp, Fp>, Error> {
-        let xor_chip = U8XorChip::construct(self.xor_config());
-        let mul2_chip = MulBy2Chip::construct(self.mul2_config());
-        let mul3_chip = MulBy3Chip::construct(self.mul3_config());
-        let advices = self.get_advices();
+        ciphertext: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        // One row per byte
+        self.add_extra_rows(cursor, ciphertext.len() as u64)?;
 
-        let tmp = word
-            .iter()
-            .zip(coeffs)
-            .map(|(byte, col)| match col {
-                1 => {
-                    layouter.assign_region(
-                        || "",
-                        |mut region| {
-                            // just copy advice from word
-                            byte.copy_advice(|| "Copy mul by 1", &mut region, advices[0], 0)
-                        },
-                    )
-                }
-                2 => mul2_chip.mul(layouter, byte),
-                3 => mul3_chip.mul(layouter, byte),
-                _ => panic!("col should be 1, 2, or 3."),
-            })
+        let pack_chip = BytePackChip::construct(self.byte_pack_config(cursor));
+        let packed = ciphertext
+            .chunks(MAX_PACKED_BYTES)
+            .map(|chunk| pack_chip.pack(layouter, chunk))
             .collect::<Result<Vec<_>, Error>>()?;
+        self.expose_bytes(cursor, layouter, &packed)?;
 
-        let inter_1 = xor_chip.xor(layouter, &tmp[0], &tmp[1])?;
-        let inter_2 = xor_chip.xor(layouter, &tmp[2], &tmp[3])?;
-        xor_chip.xor(layouter, &inter_1, &inter_2)
+        Ok(packed)
     }
 
-    fn aes_callable(&mut self) -> bool {
-        let mut max_row = u64::pow(2, K);
-        if self.current == 0 {
-            // Subtract key scheduling
-            max_row -= KEY_SCHEDULE_ROWS;
-        }
-        // println!(
-        //     "Call: {}, Max_row: {}, self.count*AES_ROWS: {}",
-        //     self.count,
-        //     max_row,
-        //     self.count * AES_ROWS
-        // );
+    /// Constrain the nonce bytes to the next rows of the instance column.
+    pub fn expose_nonce(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        nonce: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(), Error> {
+        self.expose_bytes(cursor, layouter, nonce)
+    }
 
-        if max_row >= self.count * AES_ROWS + AES_ROWS {
-            return true;
-        } else if self.current < N - 1 {
-            self.current += 1;
-            self.count = 0;
-            return true;
-        } else {
-            return false;
+    /// Constrain the ciphertext bytes to the next rows of the given instance column,
+    /// e.g. the group of the block in `EncryptOutput` when configured with an instance column
+    /// per group. Fails with `AesError::InvalidInstanceColumn` if the column doesn't exist.
+    pub fn expose_ciphertext_at(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        column: usize,
+        ciphertext: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(), AesError> {
+        if column >= self.instances.len() {
+            return Err(AesError::InvalidInstanceColumn {
+                column,
+                n: self.instances.len(),
+            });
         }
-    }
 
-    // Config getters
-    fn range_config(&self) -> U8RangeCheckConfig {
-        assert!(self.current < N);
-        self.configs.0[self.current]
+        Ok(self.expose_bytes_at(cursor, layouter, column, ciphertext)?)
     }
 
-    fn xor_config(&self) -> U8XorConfig {
-        assert!(self.current < N);
-        self.configs.1[self.current]
+    fn expose_bytes(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(), Error> {
+        self.expose_bytes_at(cursor, layouter, 0, bytes)
     }
 
-    fn sbox_config(&self) -> SboxConfig {
-        assert!(self.current < N);
-        self.configs.2[self.current]
+    fn expose_bytes_at(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        column: usize,
+        bytes: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(), Error> {
+        if cursor.instance_offsets.len() <= column {
+            cursor.instance_offsets.resize(column + 1, 0);
+        }
+
+        let offset = &mut cursor.instance_offsets[column];
+        for byte in bytes {
+            layouter.constrain_instance(byte.cell(), self.instances[column], *offset)?;
+            *offset += 1;
+        }
+        Ok(())
     }
 
-    fn mul2_config(&self) -> MulBy2Config {
-        assert!(self.current < N);
-        self.configs.3[self.current]
+    /// GHASH of the blocks under the hash subkey h, as in NIST SP 800-38D.
+    /// Y_i = (Y_{i-1} XOR X_i) * H in GF(2^128) with Y_0 = 0, returns the last Y_i.
+    /// Each block should be 16 bytes constrained to be u8.
+    /// GHASH of no blocks is Y_0, i.e. the zero block fixed to constants.
+    pub fn ghash(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        h: &[AssignedCell<Fp, Fp>],
+        blocks: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        if blocks.is_empty() {
+            return self.assign_constant_bytes(cursor, layouter, &[0u8; 16]);
+        }
+
+        let mut y: Option<Vec<AssignedCell<Fp, Fp>>> = None;
+        for block in blocks {
+            self.check_rows_callable(cursor, GHASH_BLOCK_ROWS)?;
+            cursor.extra_rows += GHASH_BLOCK_ROWS;
+
+            let xor_chip = U8XorChip::construct(self.xor_config(cursor));
+            let mul_chip = Gf128MulChip::construct(self.gf128_mul_config(cursor));
+
+            let x = match y.take() {
+                None => block.clone(),
+                Some(y) => xor_chip.xor_blocks(layouter, &y, block)?,
+            };
+            y = Some(mul_chip.mul(layouter, &x, h)?);
+        }
+
+        Ok(y.unwrap())
     }
 
-    fn mul3_config(&self) -> MulBy3Config {
-        assert!(self.current < N);
-        self.configs.4[self.current]
+    // Assign bytes in range checked cells
+    fn assign_bytes(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let range_chip = U8RangeCheckChip::construct(self.range_config(cursor));
+
+        layouter.assign_region(
+            || "Assign bytes",
+            |mut region| {
+                bytes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &b)| range_chip.assign_byte(&mut region, i, Value::known(b as u64)))
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )
     }
 
-    fn get_advices(&self) -> &[Column<Advice>] {
-        assert!(self.current < N);
-        &self.advices[self.current]
+    // Assign bytes padded with zeros to 16 bytes.
+    // The padding is fixed to constants.
+    fn assign_padded_block(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let range_chip = U8RangeCheckChip::construct(self.range_config(cursor));
+        let advices = self.get_advices(cursor);
+
+        layouter.assign_region(
+            || "Assign padded block",
+            |mut region| {
+                (0..16)
+                    .map(|i| match bytes.get(i) {
+                        Some(&b) => range_chip.assign_byte(&mut region, i, Value::known(b as u64)),
+                        None => region.assign_advice_from_constant(
+                            || "Assign padding",
+                            advices[0],
+                            i,
+                            Fp::from(0),
+                        ),
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    use crate::{
-        halo2_proofs::{
-            circuit::{Layouter, SimpleFloorPlanner},
-            dev::MockProver,
-            halo2curves::bn256::Fr as Fp,
-            plonk::{Circuit, ConstraintSystem, Error},
-        },
-        table::load_enc_full_table,
-    };
+    // Assign bytes fixed to constants
+    fn assign_constant_bytes(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let advices = self.get_advices(cursor);
+
+        layouter.assign_region(
+            || "Assign constant bytes",
+            |mut region| {
+                bytes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &b)| {
+                        region.assign_advice_from_constant(
+                            || "Assign constant byte",
+                            advices[0],
+                            i,
+                            Fp::from(b as u64),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )
+    }
 
-    const K: u32 = 20;
+    // XOR the bytes with the constant, skipping its zero bytes.
+    fn xor_constant(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[AssignedCell<Fp, Fp>],
+        constant: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        // A constant cell and a XOR for each non zero byte
+        let nonzero = constant.iter().filter(|&&c| c != 0).count() as u64;
+        self.add_extra_rows(cursor, 2 * nonzero)?;
 
-    #[derive(Clone)]
-    struct TestAesCircuit {
-        key: [u8; 16],
-        plaintext: [u8; 16],
+        let xor_chip = U8XorChip::construct(self.xor_config(cursor));
+        bytes
+            .iter()
+            .zip(constant)
+            .map(|(byte, &c)| {
+                if c == 0 {
+                    return Ok(byte.clone());
+                }
+                let c = self.assign_constant_bytes(cursor, layouter, &[c])?;
+                xor_chip.xor(layouter, byte, &c[0])
+            })
+            .collect()
     }
 
-    impl Circuit<Fp> for TestAesCircuit {
-        type Config = FixedAes128Config<K, 3>;
-        type FloorPlanner = SimpleFloorPlanner;
+    // Encrypt one block given as bytes under the round keys.
+    fn encrypt_bytes(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[u8],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, AesError> {
+        self.check_tables_loaded()?;
+        self.check_key_schedule(cursor, round_keys)?;
+        // Check if available rows of advice[0] is more than 1360
+        self.aes_callable(cursor)?;
+        cursor.count += 1;
+
+        let assigned_plaintext = self.assign_bytes(cursor, layouter, plaintext)?;
+        let state = self.initial_round(
+            cursor,
+            layouter,
+            &mut Outputs::Computed,
+            &assigned_plaintext,
+            &round_keys[0],
+        )?;
+        Ok(self.encrypt_rounds(cursor, layouter, &mut Outputs::Computed, state, round_keys)?)
+    }
 
-        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
-            FixedAes128Config::configure(meta)
+    // Encrypt one block given as bytes n times under the round keys, sharing the plaintext
+    // and the initial AddRoundKey. The other encryptions take AES_ROWS - INITIAL_ROUND_ROWS rows.
+    fn encrypt_repeated_bytes(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[u8],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+        n: usize,
+    ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, AesError> {
+        if n == 0 {
+            return Ok(vec![]);
         }
 
-        fn synthesize(
-            &self,
-            mut config: Self::Config,
-            mut layouter: impl Layouter<Fp>,
-        ) -> Result<(), Error> {
-            load_enc_full_table(&mut layouter, config.tables)?;
-            config.schedule_key(&mut layouter, self.key)?;
+        self.check_tables_loaded()?;
+        self.check_key_schedule(cursor, round_keys)?;
+        self.aes_callable(cursor)?;
+        cursor.count += 1;
 
-            for _ in 0..1000 {
-                config.encrypt(&mut layouter, self.plaintext)?;
-            }
+        let assigned_plaintext = self.assign_bytes(cursor, layouter, plaintext)?;
+        let state = self.initial_round(
+            cursor,
+            layouter,
+            &mut Outputs::Computed,
+            &assigned_plaintext,
+            &round_keys[0],
+        )?;
 
-            Ok(())
+        let mut ciphertexts = vec![];
+        for i in 0..n {
+            if i > 0 {
+                self.add_extra_rows(cursor, AES_ROWS - INITIAL_ROUND_ROWS)?;
+            }
+            ciphertexts.push(self.encrypt_rounds(
+                cursor,
+                layouter,
+                &mut Outputs::Computed,
+                state.clone(),
+                round_keys,
+            )?);
         }
+        Ok(ciphertexts)
+    }
 
-        fn without_witnesses(&self) -> Self {
-            unimplemented!()
+    // Encrypt one block given as bytes under the round keys, assigning the outputs of the trace.
+    fn encrypt_traced(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[u8],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+        trace: &WitnessTrace,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        if trace.bytes().len() != WitnessTrace::LEN {
+            return Err(AesError::InvalidTraceLength {
+                len: trace.bytes().len(),
+            }
+            .into());
         }
-    }
+        self.check_tables_loaded()?;
+        self.check_key_schedule(cursor, round_keys)?;
+        self.aes_callable(cursor)?;
+        cursor.count += 1;
 
-    #[test]
-    #[cfg(feature = "halo2-pse")]
-    fn test_correct_encryption() {
-        let circuit = TestAesCircuit {
-            key: [0u8; 16],
-            plaintext: [0u8; 16],
+        let mut outputs = Outputs::Traced {
+            bytes: trace.bytes(),
+            next: 0,
         };
+        let assigned_plaintext = self.assign_bytes(cursor, layouter, plaintext)?;
+        let state = self.initial_round(
+            cursor,
+            layouter,
+            &mut outputs,
+            &assigned_plaintext,
+            &round_keys[0],
+        )?;
+        self.encrypt_rounds(cursor, layouter, &mut outputs, state, round_keys)
+    }
 
-        let mock = MockProver::run(K, &circuit, vec![]).unwrap();
-        mock.assert_satisfied();
-
-        // Print expected ciphertext
-        // {
-        //     use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
-        //     use aes::Aes128;
-
-        //     let key = GenericArray::from([0u8; 16]);
-        //     let mut block = GenericArray::from([0u8; 16]);
-
-        //     // Initialize cipher
-        //     let cipher = Aes128::new(&key);
-        //     cipher.encrypt_block(&mut block);
-        //     block.iter().for_each(|v| {
-        //         println!("{:02X?}", v);
-        //     });
-        // }
-    }
-
-    #[cfg(feature = "dev-graph")]
-    #[test]
-    fn print_aes_encrypt() {
-        use plotters::prelude::*;
-        let circuit = TestAesCircuit {
-            key: [0u8; 16],
-            plaintext: [0u8; 16],
-        };
+    // Encrypt one block given as range checked cells under the round keys.
+    fn encrypt_assigned(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[AssignedCell<Fp, Fp>],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.check_tables_loaded()?;
+        self.check_key_schedule(cursor, round_keys)?;
+        self.aes_callable(cursor)?;
+        cursor.count += 1;
 
-        let root =
-            BitMapBackend::new("prints/aes128-layout.png", (2048, 32768)).into_drawing_area();
-        root.fill(&WHITE).unwrap();
-        let root = root
-            .titled("AES128 Key schedule circuit", ("sans-serif", 60))
-            .unwrap();
-
-        halo2_proofs::dev::CircuitLayout::default()
-            .render(K, &circuit, &root)
-            .unwrap();
-    }
-
-    #[cfg(feature = "cost-estimator")]
-    #[test]
-    fn cost_estimate_aes_encrypt() {
-        use halo2_proofs::dev::cost_model::{from_circuit_to_model_circuit, CommitmentScheme};
-        let circuit = TestAesCircuit {
-            key: [0u8; 16],
-            plaintext: [0u8; 16],
-        };
+        self.encrypt_block(cursor, layouter, plaintext, round_keys)
+    }
 
-        let model = from_circuit_to_model_circuit::<_, _, 56, 56>(
-            K,
-            &circuit,
-            vec![],
-            CommitmentScheme::KZGGWC,
-        );
-        println!(
-            "Cost of AES128 Encryption: \n{}",
-            serde_json::to_string_pretty(&model).unwrap()
-        );
+    // Encrypt one block. Capacity should be checked by the caller.
+    fn encrypt_block(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[AssignedCell<Fp, Fp>],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let outputs = &mut Outputs::Computed;
+        let state = self.initial_round(cursor, layouter, outputs, plaintext, &round_keys[0])?;
+        self.encrypt_rounds(cursor, layouter, outputs, state, round_keys)
     }
-}
+
+    // The initial AddRoundKey
+    fn initial_round(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        plaintext: &[AssignedCell<Fp, Fp>],
+        round_key: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let xor_chip = U8XorChip::construct(self.xor_config(cursor));
+
+        match outputs {
+            Outputs::Computed => xor_chip.xor_blocks(layouter, plaintext, round_key),
+            Outputs::Traced { .. } => plaintext
+                .iter()
+                .zip(round_key.iter())
+                .map(|(p, k)| self.xor(cursor, layouter, outputs, p, k))
+                .collect(),
+        }
+    }
+
+    // Rounds 1 to 10 from the state after the initial AddRoundKey
+    fn encrypt_rounds(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        state: Vec<AssignedCell<Fp, Fp>>,
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        #[cfg(feature = "debug-print")]
+        debug_print_state(0, &state);
+
+        let mut prev_round = state;
+        for (no_round, round_key) in round_keys.iter().enumerate().skip(1) {
+            prev_round = self.round(
+                cursor,
+                layouter,
+                outputs,
+                &prev_round,
+                round_key,
+                no_round < 10,
+            )?;
+
+            #[cfg(feature = "debug-print")]
+            debug_print_state(no_round, &prev_round);
+        }
+
+        Ok(prev_round)
+    }
+
+    /// One round of AES: SubBytes, ShiftRows, MixColumns if `mix`, and AddRoundKey,
+    /// returning the new state.
+    ///
+    /// This is the building block of `encrypt`, exposed to build custom round sequences,
+    /// e.g. for ciphers reusing the AES round with other constants or number of rounds.
+    /// The state and the round key are 16 range checked cells.
+    /// The rows of the round are accounted in the cursor.
+    pub fn aes_round(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        state: &[AssignedCell<Fp, Fp>],
+        round_key: &[AssignedCell<Fp, Fp>],
+        mix: bool,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.check_tables_loaded()?;
+        let rows = if mix { ROUND_ROWS } else { LAST_ROUND_ROWS };
+        self.check_rows_callable(cursor, rows)?;
+        cursor.extra_rows += rows;
+
+        self.round(
+            cursor,
+            layouter,
+            &mut Outputs::Computed,
+            state,
+            round_key,
+            mix,
+        )
+    }
+
+    // One round. Capacity should be checked by the caller.
+    fn round(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        state: &[AssignedCell<Fp, Fp>],
+        round_key: &[AssignedCell<Fp, Fp>],
+        mix: bool,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        if mix {
+            self.middle_round(cursor, layouter, outputs, state, round_key)
+        } else {
+            self.last_round(cursor, layouter, outputs, state, round_key)
+        }
+    }
+
+    // SubBytes, ShiftRows, MixColumns and AddRoundKey of the rounds 1 to 9
+    fn middle_round(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        state: &[AssignedCell<Fp, Fp>],
+        round_key: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let subbed = State::from_bytes_column_major(state)
+            .try_map(|byte| self.substitute(cursor, layouter, outputs, byte))?;
+
+        // Shift rows is just copy constraints.
+        // MixColumns multiplies each column by the matrix.
+        let mixed = subbed.shift_rows().try_map_columns(|word| {
+            MIX_COLUMNS
+                .iter()
+                .map(|col| self.lcon_with(cursor, layouter, outputs, word, col))
+                .collect::<Result<Vec<_>, Error>>()
+        })?;
+
+        self.add_round_key(cursor, layouter, outputs, &mixed, round_key)
+    }
+
+    // The last round is SubBytes, ShiftRows and AddRoundKey without MixColumns.
+    fn last_round(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        state: &[AssignedCell<Fp, Fp>],
+        round_key: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let subbed = State::from_bytes_column_major(state)
+            .try_map(|byte| self.substitute(cursor, layouter, outputs, byte))?;
+
+        self.add_round_key(cursor, layouter, outputs, &subbed.shift_rows(), round_key)
+    }
+
+    // Decrypt one block given as bytes under the round keys of the encryption,
+    // with the inverse cipher of FIPS-197 5.3, taking the round keys in reverse order.
+    fn decrypt_bytes(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        ciphertext: &[u8],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.check_tables_loaded()?;
+        self.check_key_schedule(cursor, round_keys)?;
+        self.add_extra_rows(cursor, DECRYPT_ROWS)?;
+
+        let assigned = self.assign_bytes(cursor, layouter, ciphertext)?;
+        self.decrypt_block(cursor, layouter, &assigned, round_keys)
+    }
+
+    // Decrypt one block given as cells. As with `encrypt_assigned`, the rows of the
+    // ciphertext assignment are accounted in DECRYPT_ROWS though left unused.
+    fn decrypt_assigned(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        ciphertext: &[AssignedCell<Fp, Fp>],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.check_tables_loaded()?;
+        self.check_key_schedule(cursor, round_keys)?;
+        self.add_extra_rows(cursor, DECRYPT_ROWS)?;
+
+        self.decrypt_block(cursor, layouter, ciphertext, round_keys)
+    }
+
+    // Decrypt one block. Capacity should be checked by the caller.
+    fn decrypt_block(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        ciphertext: &[AssignedCell<Fp, Fp>],
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let xor_chip = U8XorChip::construct(self.xor_config(cursor));
+        let inv_chip = InvCipherChip::construct(self.inv_cipher_config(cursor));
+
+        let mut state = xor_chip.xor_blocks(layouter, ciphertext, &round_keys[10])?;
+        for (no_round, round_key) in round_keys[..10].iter().enumerate().rev() {
+            // InvShiftRows is just copy constraints, and commutes with InvSubBytes
+            let subbed = State::from_bytes_column_major(&state)
+                .inv_shift_rows()
+                .try_map(|byte| inv_chip.inv_substitute(layouter, byte))?;
+            let added =
+                self.add_round_key(cursor, layouter, &mut Outputs::Computed, &subbed, round_key)?;
+
+            state = if no_round > 0 {
+                self.inv_mix_columns(cursor, layouter, &added)?
+            } else {
+                added
+            };
+        }
+
+        Ok(state)
+    }
+
+    // InvMixColumns with the coefficients 9, 11, 13 and 14 looked up in their tables
+    fn inv_mix_columns(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        state: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let mixed = State::from_bytes_column_major(state).try_map_columns(|word| {
+            INV_MIX_COLUMNS
+                .iter()
+                .map(|col| self.lcon(cursor, layouter, word, col))
+                .collect::<Result<Vec<_>, Error>>()
+        })?;
+
+        Ok(mixed.to_flat())
+    }
+
+    // S-box of the byte, with the output of the trace if any
+    fn substitute(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        byte: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let sbox_chip = SboxChip::construct(self.sbox_config(cursor));
+
+        match outputs.next()? {
+            Some(output) => sbox_chip.substitute_with(layouter, byte, output),
+            None => sbox_chip.substitute(layouter, byte),
+        }
+    }
+
+    // XOR of the bytes, with the output of the trace if any
+    fn xor(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        x: &AssignedCell<Fp, Fp>,
+        y: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let xor_chip = U8XorChip::construct(self.xor_config(cursor));
+
+        match outputs.next()? {
+            Some(output) => xor_chip.xor_with(layouter, x, y, output),
+            None => xor_chip.xor(layouter, x, y),
+        }
+    }
+
+    // XOR the state with the round key
+    //
+    // The key bytes are copied from the key schedule into every XOR row.
+    // Caching the round keys in each group of columns doesn't save any of these copies,
+    // as each XOR row still needs its own copy of the key byte, and only adds 176 per group.
+    // Nor would fewer copies make the proof cheaper: the cost of the permutation argument
+    // depends on the number of equality enabled columns, not on the number of copies.
+    fn add_round_key(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        state: &State<AssignedCell<Fp, Fp>>,
+        round_key: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        state
+            .try_zip_map(&State::from_bytes_column_major(round_key), |byte, key| {
+                self.xor(cursor, layouter, outputs, byte, key)
+            })
+            .map(|state| state.to_flat())
+    }
+
+    // Compute linear combination of word and given coefficients
+    pub(crate) fn lcon(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        word: &[AssignedCell<Fp, Fp>],
+        coeffs: &[u32],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        self.lcon_with(cursor, layouter, &mut Outputs::Computed, word, coeffs)
+    }
+
+    // `lcon` with the outputs of the trace if any
+    fn lcon_with(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        word: &[AssignedCell<Fp, Fp>],
+        coeffs: &[u32],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let xor_chip = U8XorChip::construct(self.xor_config(cursor));
+
+        let tmp = word
+            .iter()
+            .zip(coeffs)
+            .map(|(byte, &coeff)| self.mul_by_coeff(cursor, layouter, outputs, byte, coeff))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        match outputs {
+            Outputs::Computed => xor_chip.xor_many(layouter, &tmp),
+            // The same tree as `xor_many`, in the order of the trace
+            Outputs::Traced { .. } => {
+                let left = self.xor(cursor, layouter, outputs, &tmp[0], &tmp[1])?;
+                let right = self.xor(cursor, layouter, outputs, &tmp[2], &tmp[3])?;
+                self.xor(cursor, layouter, outputs, &left, &right)
+            }
+        }
+    }
+
+    // Multiply the byte by the coefficient of the (Inv)MixColumns matrix.
+    // 2 and 3 are looked up in their tables, and so are 9, 11, 13 and 14 of InvMixColumns
+    // in the inverse cipher tables, always computed as they are not traced.
+    // Other coefficients fail with `AesError::UnsupportedCoefficient`.
+    fn mul_by_coeff(
+        &self,
+        cursor: &EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        outputs: &mut Outputs,
+        byte: &AssignedCell<Fp, Fp>,
+        coeff: u32,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let mul2_chip = MulBy2Chip::construct(self.mul2_config(cursor));
+        let mul3_chip = MulBy3Chip::construct(self.mul3_config(cursor));
+
+        match coeff {
+            1 => {
+                let advices = self.get_advices(cursor);
+                layouter.assign_region(
+                    || "",
+                    |mut region| {
+                        // just copy advice from word
+                        byte.copy_advice(|| "Copy mul by 1", &mut region, advices[0], 0)
+                    },
+                )
+            }
+            2 => match outputs.next()? {
+                Some(output) => mul2_chip.mul_with(layouter, byte, output),
+                None => mul2_chip.mul(layouter, byte),
+            },
+            3 => match outputs.next()? {
+                Some(output) => mul3_chip.mul_with(layouter, byte, output),
+                None => mul3_chip.mul(layouter, byte),
+            },
+            9 | 11 | 13 | 14 => {
+                InvCipherChip::construct(self.inv_cipher_config(cursor)).mul(layouter, byte, coeff)
+            }
+            _ => Err(AesError::UnsupportedCoefficient { coeff }.into()),
+        }
+    }
+
+    /// Lay out the next blocks in the given group of columns,
+    /// e.g. to keep related blocks in the same group.
+    ///
+    /// Only the usage of the current group is tracked, so the group can't go back
+    /// to the current or an earlier one, and the rest of the current group is left unused.
+    /// When the selected group is full, the next blocks still move on
+    /// to the following group automatically.
+    pub fn set_active_group(
+        &self,
+        cursor: &mut EncryptCursor,
+        group: usize,
+    ) -> Result<(), AesError> {
+        if group <= cursor.current || group >= N {
+            return Err(AesError::InvalidGroup {
+                group,
+                current: cursor.current,
+                n: N,
+            });
+        }
+
+        cursor.current = group;
+        cursor.count = 0;
+        cursor.extra_rows = 0;
+        Ok(())
+    }
+
+    pub(crate) fn check_tables_loaded(&self) -> Result<(), AesError> {
+        if self.tables_loaded {
+            Ok(())
+        } else {
+            Err(AesError::TablesNotLoaded)
+        }
+    }
+
+    // Check that the rows of the key schedule of the round keys are accounted.
+    // The first key schedule encrypted under is the one in KEY_SCHEDULE_ROWS,
+    // any other should be added by `Aes128Scheduled::schedule_another_key`.
+    fn check_key_schedule(
+        &self,
+        cursor: &mut EncryptCursor,
+        round_keys: &[Vec<AssignedCell<Fp, Fp>>],
+    ) -> Result<(), AesError> {
+        let first_key = round_keys[0][0].cell();
+        if cursor.key_schedules.is_empty() {
+            cursor.key_schedules.push(first_key);
+        }
+
+        if cursor.key_schedules.contains(&first_key) {
+            Ok(())
+        } else {
+            Err(AesError::KeyScheduleNotAccounted)
+        }
+    }
+
+    // Account the rows of one more key schedule in the first group of columns.
+    pub(crate) fn add_key_schedule(
+        &self,
+        cursor: &mut EncryptCursor,
+        rows: u64,
+    ) -> Result<(), AesError> {
+        // The key schedule chip uses the first group of columns,
+        // the first key schedule is already subtracted from its rows.
+        let available = if cursor.current == 0 {
+            self.available_rows(cursor)
+        } else {
+            0
+        };
+        if available < rows {
+            return Err(AesError::CapacityExceeded {
+                requested: rows,
+                available,
+            });
+        }
+        cursor.extra_rows += rows;
+        Ok(())
+    }
+
+    // Account `rows` rows used besides AES in the current group of columns,
+    // moving on to the next group if they don't fit.
+    pub(crate) fn add_extra_rows(
+        &self,
+        cursor: &mut EncryptCursor,
+        rows: u64,
+    ) -> Result<(), AesError> {
+        self.check_rows_callable(cursor, rows)?;
+        cursor.extra_rows += rows;
+        Ok(())
+    }
+
+    fn aes_callable(&self, cursor: &mut EncryptCursor) -> Result<(), AesError> {
+        self.check_rows_callable(cursor, AES_ROWS)
+    }
+
+    // `rows_callable` failing with the rows left in the last group of columns.
+    fn check_rows_callable(&self, cursor: &mut EncryptCursor, rows: u64) -> Result<(), AesError> {
+        if self.rows_callable(cursor, rows) {
+            Ok(())
+        } else {
+            Err(AesError::CapacityExceeded {
+                requested: rows,
+                available: self.available_rows(cursor),
+            })
+        }
+    }
+
+    // Rows of the current group of columns not used yet.
+    fn available_rows(&self, cursor: &EncryptCursor) -> u64 {
+        self.max_rows(cursor)
+            .saturating_sub(cursor.count * AES_ROWS + cursor.extra_rows)
+    }
+
+    // Rows of the current group of columns for the encryptions and the extra rows.
+    fn max_rows(&self, cursor: &EncryptCursor) -> u64 {
+        let mut max_row = u64::pow(2, K) - BLINDING_ROWS as u64;
+        if cursor.current == 0 {
+            // Subtract key scheduling
+            max_row -= KEY_SCHEDULE_ROWS;
+        }
+        max_row
+    }
+
+    // Check if `rows` more rows fit in the current column,
+    // and move on to the next column if not.
+    //
+    // The encryptions in the first column start right after the key schedule:
+    // every region of both touches the first column of the group, so the floor planner
+    // can't place them beside each other, and KEY_SCHEDULE_ROWS is the exact
+    // rows of the key schedule, so there are no rows left to share between them.
+    fn rows_callable(&self, cursor: &mut EncryptCursor, rows: u64) -> bool {
+        if self.available_rows(cursor) >= rows {
+            return true;
+        } else if cursor.current < N - 1 {
+            cursor.current += 1;
+            cursor.count = 0;
+            cursor.extra_rows = 0;
+            return true;
+        } else {
+            return false;
+        }
+    }
+
+    // Config getters
+    pub(crate) fn range_config(&self, cursor: &EncryptCursor) -> U8RangeCheckConfig {
+        assert!(cursor.current < N);
+        self.configs.0[cursor.current]
+    }
+
+    pub(crate) fn xor_config(&self, cursor: &EncryptCursor) -> U8XorConfig {
+        assert!(cursor.current < N);
+        self.configs.1[cursor.current]
+    }
+
+    pub(crate) fn sbox_config(&self, cursor: &EncryptCursor) -> SboxConfig {
+        assert!(cursor.current < N);
+        self.configs.2[cursor.current]
+    }
+
+    fn mul2_config(&self, cursor: &EncryptCursor) -> MulBy2Config {
+        assert!(cursor.current < N);
+        self.configs.3[cursor.current]
+    }
+
+    fn mul3_config(&self, cursor: &EncryptCursor) -> MulBy3Config {
+        assert!(cursor.current < N);
+        self.configs.4[cursor.current]
+    }
+
+    fn counter_config(&self, cursor: &EncryptCursor) -> CounterConfig {
+        assert!(cursor.current < N);
+        self.configs.5[cursor.current]
+    }
+
+    fn gf128_mul_config(&self, cursor: &EncryptCursor) -> Gf128MulConfig {
+        assert!(cursor.current < N);
+        self.configs.6[cursor.current]
+    }
+
+    fn u64_decompose_config(&self, cursor: &EncryptCursor) -> U64DecomposeConfig {
+        assert!(cursor.current < N);
+        self.configs.7[cursor.current]
+    }
+
+    fn not_equal_config(&self, cursor: &EncryptCursor) -> NotEqualConfig {
+        assert!(cursor.current < N);
+        self.configs.8[cursor.current]
+    }
+
+    fn byte_pack_config(&self, cursor: &EncryptCursor) -> BytePackConfig {
+        assert!(cursor.current < N);
+        self.configs.9[cursor.current]
+    }
+
+    fn inv_cipher_config(&self, cursor: &EncryptCursor) -> InvCipherConfig {
+        assert!(cursor.current < N);
+        self.configs.10[cursor.current]
+    }
+
+    fn get_advices(&self, cursor: &EncryptCursor) -> &[Column<Advice>] {
+        assert!(cursor.current < N);
+        &self.advices[cursor.current]
+    }
+}
+
+// Evaluated in `cbc_mac` as `FixedAes128Config::VALID_PARAMS`,
+// so that the CBC-MAC of no blocks fails to compile instead of panicking at the synthesis.
+struct CbcMacBlocks<const BLOCKS: usize>;
+
+impl<const BLOCKS: usize> CbcMacBlocks<BLOCKS> {
+    const NONEMPTY: () = assert!(BLOCKS > 0, "CBC-MAC of at least one block");
+}
+
+/// `FixedAes128Config` with the scheduled key, returned by `schedule_key`.
+/// Encryption is only available on this type, so it can't be called before scheduling the key.
+/// Derefs to the config for the other methods, e.g. `expose_ciphertext`.
+#[derive(Clone, Debug)]
+pub struct Aes128Scheduled<const K: u32, const N: usize> {
+    config: FixedAes128Config<K, N>,
+    keys: Vec<Vec<AssignedCell<Fp, Fp>>>,
+}
+
+impl<const K: u32, const N: usize> Deref for Aes128Scheduled<K, N> {
+    type Target = FixedAes128Config<K, N>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.config
+    }
+}
+
+impl<const K: u32, const N: usize> Aes128Scheduled<K, N> {
+    /// Drop the scheduled round keys and return the config,
+    /// so that it can schedule another key for a new synthesis.
+    /// Reset the `EncryptCursor` as well when reusing it.
+    pub fn into_config(self) -> FixedAes128Config<K, N> {
+        self.config
+    }
+
+    /// Export the values of the scheduled round keys,
+    /// e.g. to feed them to `schedule_key_precomputed` of another circuit.
+    /// Returns unknown value when the witness is not known.
+    pub fn exported_round_keys(&self) -> Value<[[u8; 16]; 11]> {
+        self.keys
+            .iter()
+            .fold(Value::known(vec![]), |acc, round_key| {
+                acc.zip(cells_to_bytes(round_key)).map(|(mut keys, bytes)| {
+                    keys.push(bytes);
+                    keys
+                })
+            })
+            .map(|keys| std::array::from_fn(|i| keys[i].clone().try_into().unwrap()))
+    }
+
+    /// The scheduled round key cells, e.g. to encrypt under them with another config
+    /// of the same circuit by `FixedAes128Config::set_round_keys`.
+    pub fn take_round_keys(&self) -> Vec<Vec<AssignedCell<Fp, Fp>>> {
+        self.keys.clone()
+    }
+
+    /// The 16 key cells the round keys are derived from, i.e. the first round key.
+    pub fn key_input_cells(&self) -> &[AssignedCell<Fp, Fp>] {
+        &self.keys[0]
+    }
+
+    /// Constrain the key of this schedule and the one of `other` to be equal,
+    /// e.g. to prove that two encryptions used the same key without revealing it.
+    pub fn constrain_same_key(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        other: &Aes128Scheduled<K, N>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "Constrain same key",
+            |mut region| {
+                for (a, b) in self.key_input_cells().iter().zip(other.key_input_cells()) {
+                    region.constrain_equal(a.cell(), b.cell())?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Schedule another key in the same layout, e.g. to encrypt under several keys.
+    ///
+    /// The rows of the new key schedule are accounted in the cursor, so it must be called
+    /// while the cursor is in the first group of columns.
+    /// Encrypting under a key scheduled otherwise, e.g. by `schedule_key` of a cloned config,
+    /// after encrypting under this one fails with `AesError::KeyScheduleNotAccounted`,
+    /// as its rows would overflow the circuit unnoticed.
+    pub fn schedule_another_key(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        key: [u8; 16],
+    ) -> Result<Aes128Scheduled<K, N>, Error> {
+        self.config.check_key_schedule(cursor, &self.keys)?;
+        self.config.add_key_schedule(cursor, KEY_SCHEDULE_ROWS)?;
+
+        let keys = self
+            .config
+            .key_schedule_config
+            .schedule_keys(layouter, key)?;
+        cursor.key_schedules.push(keys[0][0].cell());
+
+        Ok(Aes128Scheduled {
+            config: self.config.clone(),
+            keys,
+        })
+    }
+
+    /// Encrypt the plaintext block under the scheduled key.
+    /// The ciphertext bytes are in the order of the block, see `block`.
+    /// Fails with `AesError::CapacityExceeded` if the block doesn't fit in the rows left.
+    pub fn encrypt(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 16],
+    ) -> Result<Ciphertext, AesError> {
+        self.config
+            .encrypt_bytes(cursor, layouter, &plaintext, &self.keys)
+            .map(Ciphertext::new)
+    }
+
+    /// Encrypt the plaintext block n times under the scheduled key, e.g. for benchmarks.
+    /// The plaintext and the initial AddRoundKey are assigned once and shared by the encryptions,
+    /// saving INITIAL_ROUND_ROWS (32) rows for each but the first.
+    /// The layout depends on n only, not on the plaintext.
+    pub fn encrypt_repeated(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 16],
+        n: usize,
+    ) -> Result<Vec<Ciphertext>, AesError> {
+        Ok(self
+            .config
+            .encrypt_repeated_bytes(cursor, layouter, &plaintext, &self.keys, n)?
+            .into_iter()
+            .map(Ciphertext::new)
+            .collect())
+    }
+
+    /// Encrypt the plaintext and constrain the ciphertext to the next 16 rows of the instance
+    /// column, preceded by the plaintext if `public_plaintext`, so that the proof is bound to them.
+    /// Returns the ciphertext and the rows of the instance column it is constrained to.
+    /// `encrypt` keeps both private unless exposed, e.g. with `expose_ciphertext`.
+    pub fn encrypt_with_public_io(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 16],
+        public_plaintext: bool,
+    ) -> Result<(Ciphertext, Range<usize>), Error> {
+        let ciphertext = if public_plaintext {
+            Ciphertext::new(self.encrypt_public_plaintext(cursor, layouter, plaintext)?)
+        } else {
+            self.encrypt(cursor, layouter, plaintext)?
+        };
+
+        let start = cursor.instance_rows().first().copied().unwrap_or(0);
+        self.config.expose_bytes(cursor, layouter, &ciphertext)?;
+        Ok((ciphertext, start..start + 16))
+    }
+
+    /// Decrypt the ciphertext block under the scheduled key with the inverse cipher,
+    /// returning the plaintext in the order of the block, e.g. to prove the decryption of a ciphertext
+    /// to a plaintext committed to elsewhere in the circuit.
+    /// The inverse S-box and the coefficients of InvMixColumns are looked up in their own tables,
+    /// loaded by `load_tables` with the ones of the cipher.
+    /// Takes DECRYPT_ROWS (1360) rows of the current group of columns.
+    pub fn decrypt(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        ciphertext: [u8; 16],
+    ) -> Result<Plaintext, Error> {
+        self.config
+            .decrypt_bytes(cursor, layouter, &ciphertext, &self.keys)
+            .map(Plaintext::new)
+    }
+
+    /// Same as `encrypt`, assigning the outputs of the chips from the trace as they are
+    /// instead of computing them, e.g. to iterate faster on the shape of a circuit
+    /// with MockProver. The trace is of this key and plaintext, see
+    /// `reference::witness_trace_reference`. The outputs are still constrained
+    /// by the lookups, so a wrong trace fails the verification.
+    /// A trace of another length than `WitnessTrace::LEN` fails with `AesError::InvalidTraceLength`.
+    pub fn encrypt_with_trace(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 16],
+        trace: &WitnessTrace,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.config
+            .encrypt_traced(cursor, layouter, &plaintext, &self.keys, trace)
+    }
+
+    /// Same as `encrypt`, also returning the group of columns the block is laid out in.
+    /// The group differs from the one of the previous block when it was full,
+    /// which matters e.g. for the copy constraints between the blocks.
+    pub fn encrypt_with_group(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 16],
+    ) -> Result<EncryptOutput, Error> {
+        let cells = self.encrypt(cursor, layouter, plaintext)?.into_cells();
+
+        Ok(EncryptOutput {
+            cells,
+            group: cursor.current,
+        })
+    }
+
+    /// Encrypt the plaintext made public, i.e. the plaintext bytes are constrained to
+    /// the next 16 rows of the instance column before the encryption.
+    pub fn encrypt_public_plaintext(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 16],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let assigned = self.config.assign_bytes(cursor, layouter, &plaintext)?;
+        self.config.expose_bytes(cursor, layouter, &assigned)?;
+        self.encrypt_cells(cursor, layouter, &assigned)
+    }
+
+    /// Encrypt the plaintext and constrain the ciphertext to differ from `target`
+    /// in at least one byte, i.e. prove the key doesn't encrypt the plaintext to the target.
+    /// The target is exposed to the next 16 rows of the instance column.
+    /// Unsatisfiable if the ciphertext equals the target.
+    pub fn encrypt_not_equal(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 16],
+        target: [u8; 16],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let ciphertext = self.encrypt(cursor, layouter, plaintext)?.into_cells();
+
+        self.config.add_extra_rows(cursor, NOT_EQUAL_ROWS)?;
+        let target = self.config.assign_bytes(cursor, layouter, &target)?;
+        self.config.expose_bytes(cursor, layouter, &target)?;
+        NotEqualChip::construct(self.config.not_equal_config(cursor)).assert_not_equal(
+            layouter,
+            &ciphertext,
+            &target,
+        )?;
+
+        Ok(ciphertext)
+    }
+
+    /// Encrypt the plaintext and hash the ciphertext with Poseidon,
+    /// returning the ciphertext and the digest.
+    /// Only the digest is exposed to the next row of the instance column,
+    /// see `hash::hash_block` to compute it out of the circuit.
+    #[cfg(feature = "poseidon")]
+    pub fn encrypt_and_hash(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        hash_config: &BlockHashConfig,
+        plaintext: [u8; 16],
+    ) -> Result<(Vec<AssignedCell<Fp, Fp>>, AssignedCell<Fp, Fp>), Error> {
+        let ciphertext = self.encrypt(cursor, layouter, plaintext)?.into_cells();
+        let digest = hash_config.hash_block(layouter, &ciphertext)?;
+        self.config
+            .expose_bytes(cursor, layouter, std::slice::from_ref(&digest))?;
+
+        Ok((ciphertext, digest))
+    }
+
+    /// Encrypt a batch of blocks under the scheduled key committed to by its Poseidon hash,
+    /// e.g. the records of a rollup batch, returning the ciphertexts.
+    /// The next rows of the instance column are the key commitment, i.e. `hash::hash_block`
+    /// of the key, then the 16 bytes of each ciphertext, see `hash::batch_public_inputs`.
+    /// The key is scheduled once and stays private, only its hash is public.
+    #[cfg(feature = "poseidon")]
+    pub fn batch_encrypt_committed(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        hash_config: &BlockHashConfig,
+        plaintexts: &[[u8; 16]],
+    ) -> Result<Vec<Ciphertext>, Error> {
+        let commitment = hash_config.hash_block(layouter, &self.keys[0])?;
+        self.config
+            .expose_bytes(cursor, layouter, std::slice::from_ref(&commitment))?;
+
+        let mut ciphertexts = vec![];
+        for plaintext in plaintexts {
+            let ciphertext = self.encrypt(cursor, layouter, *plaintext)?;
+            self.config.expose_bytes(cursor, layouter, &ciphertext)?;
+            ciphertexts.push(ciphertext);
+        }
+        Ok(ciphertexts)
+    }
+
+    /// Encrypt 16 bytes given as range checked cells.
+    pub fn encrypt_cells(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.config
+            .encrypt_assigned(cursor, layouter, plaintext, &self.keys)
+    }
+
+    /// Encrypt 16 bytes packed upstream into 2 cells of 8 bytes each, see `utils::pack_block`.
+    /// Each cell is decomposed into its range checked little-endian bytes,
+    /// so a cell of 2^64 or more is unsatisfiable.
+    pub fn encrypt_packed(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        packed: &[AssignedCell<Fp, Fp>; 2],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        // Decompose in the same group of columns as the AES call
+        self.config
+            .check_rows_callable(cursor, PACKED_BLOCK_ROWS + AES_ROWS)?;
+        cursor.extra_rows += PACKED_BLOCK_ROWS;
+
+        let decompose_chip = U64DecomposeChip::construct(self.config.u64_decompose_config(cursor));
+        let mut plaintext = decompose_chip.decompose(layouter, &packed[0])?;
+        plaintext.extend(decompose_chip.decompose(layouter, &packed[1])?);
+
+        self.encrypt_cells(cursor, layouter, &plaintext)
+    }
+
+    /// Encrypt the plaintext in ECB mode, i.e. each 16 bytes block independently.
+    ///
+    /// The plaintext must be a multiple of 16 bytes, otherwise it fails with
+    /// `AesError::InvalidLength` before assigning any cells.
+    /// Use `ecb_encrypt_padded` for a plaintext of any length.
+    pub fn ecb_encrypt(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, AesError> {
+        if plaintext.len() % 16 != 0 {
+            return Err(AesError::InvalidLength {
+                len: plaintext.len(),
+            });
+        }
+
+        let mut ciphertext = vec![];
+        for block in plaintext.chunks(16) {
+            ciphertext.extend(
+                self.config
+                    .encrypt_bytes(cursor, layouter, block, &self.keys)?,
+            );
+        }
+        Ok(ciphertext)
+    }
+
+    /// Encrypt the plaintext in ECB mode after PKCS#7 padding, see `utils::pkcs7_pad`.
+    /// The ciphertext is the plaintext length rounded up to the next multiple of 16 bytes.
+    /// NOTE: The padding is a part of the plaintext witness, it's not constrained.
+    pub fn ecb_encrypt_padded(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, AesError> {
+        self.ecb_encrypt(cursor, layouter, &pkcs7_pad(plaintext))
+    }
+
+    /// Encrypt the plaintext in CBC mode, i.e. each block is XORed with the previous
+    /// ciphertext block, or `iv` for the first one, before the encryption.
+    ///
+    /// The plaintext must be a multiple of 16 bytes, otherwise it fails with
+    /// `AesError::InvalidLength` before assigning any cells.
+    pub fn cbc_encrypt(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        iv: [u8; 16],
+        plaintext: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, AesError> {
+        if plaintext.len() % 16 != 0 {
+            return Err(AesError::InvalidLength {
+                len: plaintext.len(),
+            });
+        }
+
+        self.config.add_extra_rows(cursor, 16)?;
+        let iv = self.config.assign_bytes(cursor, layouter, &iv)?;
+        Ok(self.cbc_blocks(cursor, layouter, iv, plaintext)?)
+    }
+
+    /// Encrypt the blocks in CBC mode as `cbc_encrypt`, returning the ciphertext by block.
+    /// The IV is a private witness, or constrained to the next 16 rows of the instance column
+    /// if `public_iv`. No blocks give no ciphertext blocks without assigning any cells,
+    /// nor exposing the IV.
+    pub fn encrypt_cbc(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        iv: [u8; 16],
+        blocks: &[[u8; 16]],
+        public_iv: bool,
+    ) -> Result<Vec<Ciphertext>, Error> {
+        if blocks.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.config.add_extra_rows(cursor, 16)?;
+        let iv = self.config.assign_bytes(cursor, layouter, &iv)?;
+        if public_iv {
+            self.config.expose_bytes(cursor, layouter, &iv)?;
+        }
+
+        let ciphertext = self.cbc_blocks(cursor, layouter, iv, &blocks.concat())?;
+        Ok(ciphertext
+            .chunks(16)
+            .map(|block| Ciphertext::new(block.to_vec()))
+            .collect())
+    }
+
+    /// CBC-MAC of a message of `BLOCKS` blocks, i.e. the last block of the CBC encryption
+    /// with the zero IV. Returns the 16 bytes tag.
+    ///
+    /// NOTE: CBC-MAC is only secure for messages of one fixed length under a key.
+    /// Given the tags of two messages of different lengths, the tag of their extension
+    /// can be forged, so the length is fixed by the type and a key must only ever
+    /// authenticate messages of the same number of blocks. Use CMAC otherwise.
+    /// A message of no blocks fails to compile.
+    pub fn cbc_mac<const BLOCKS: usize>(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        message: &[[u8; 16]; BLOCKS],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let () = CbcMacBlocks::<BLOCKS>::NONEMPTY;
+
+        self.config.add_extra_rows(cursor, 16)?;
+        let iv = self
+            .config
+            .assign_constant_bytes(cursor, layouter, &[0u8; 16])?;
+        let ciphertext = self.cbc_blocks(cursor, layouter, iv, &message.concat())?;
+
+        Ok(ciphertext[ciphertext.len() - 16..].to_vec())
+    }
+
+    // CBC encryption of the block aligned plaintext chained from the assigned iv.
+    // The rows of the plaintext and the XOR of each block are accounted
+    // in the same group of columns as the AES call.
+    fn cbc_blocks(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        iv: Vec<AssignedCell<Fp, Fp>>,
+        plaintext: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let mut prev = iv;
+        let mut ciphertext = vec![];
+
+        for block in plaintext.chunks(16) {
+            self.config
+                .check_rows_callable(cursor, CBC_BLOCK_ROWS + AES_ROWS)?;
+            cursor.extra_rows += CBC_BLOCK_ROWS;
+
+            let xor_chip = U8XorChip::construct(self.config.xor_config(cursor));
+            let assigned = self.config.assign_bytes(cursor, layouter, block)?;
+            let chained = xor_chip.xor_blocks(layouter, &assigned, &prev)?;
+
+            prev = self.encrypt_cells(cursor, layouter, &chained)?;
+            ciphertext.extend(prev.iter().cloned());
+        }
+
+        Ok(ciphertext)
+    }
+
+    /// Encrypt the plaintext in CTR mode starting from the counter block `iv`.
+    /// The counter block of each following block is constrained to be
+    /// the previous one plus 1, so that no counter block is reused within a call.
+    /// The last block can be partial; ciphertext has the same length as the plaintext,
+    /// so an empty plaintext gives an empty ciphertext without assigning any cells.
+    /// Only the used bytes of the last keystream block are XORed with the plaintext.
+    /// The others are still outputs of the constrained AES call, but aren't returned
+    /// nor exposed, so they don't leak the keystream beyond the message.
+    pub fn ctr_encrypt(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        iv: [u8; 16],
+        plaintext: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.ctr_blocks(cursor, layouter, iv, plaintext, CounterMode::Full128)
+            .map(|(ciphertext, _)| ciphertext)
+    }
+
+    /// Encrypt the plaintext in CTR mode with the counter blocks `nonce || counter`,
+    /// the 32 bit big-endian counter starting from `initial_counter`, as `ctr_encrypt`.
+    /// Only the counter is incremented, wrapping around at 2^32 (inc32 of GCM),
+    /// and the nonce cells are the same in every counter block.
+    pub fn encrypt_ctr(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        nonce: [u8; 12],
+        initial_counter: u32,
+        plaintext: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let mut iv = [0u8; 16];
+        iv[..12].copy_from_slice(&nonce);
+        iv[12..].copy_from_slice(&initial_counter.to_be_bytes());
+
+        self.ctr_blocks(cursor, layouter, iv, plaintext, CounterMode::Low32)
+            .map(|(ciphertext, _)| ciphertext)
+    }
+
+    /// Encrypt one chunk of a message split across circuits in CTR mode,
+    /// see `ctr_chain` for the chaining protocol.
+    /// The next rows of the instance column are the input counter block, the ciphertext
+    /// and the output counter block, i.e. the counter block following the last used one.
+    pub fn ctr_encrypt_chunk(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        counter: [u8; 16],
+        plaintext: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let (ciphertext, blocks) =
+            self.ctr_blocks(cursor, layouter, counter, plaintext, CounterMode::Full128)?;
+
+        self.config.check_rows_callable(cursor, CTR_BLOCK_ROWS)?;
+        cursor.extra_rows += CTR_BLOCK_ROWS;
+
+        let (counter_in, counter_out) = match (blocks.first(), blocks.last()) {
+            (Some(first), Some(last)) => {
+                let counter_chip = CounterChip::construct(self.config.counter_config(cursor));
+                (first.clone(), counter_chip.increment(layouter, last)?)
+            }
+            // Empty chunk passes the counter through
+            _ => {
+                let counter = self.config.assign_bytes(cursor, layouter, &counter)?;
+                (counter.clone(), counter)
+            }
+        };
+
+        self.config.expose_bytes(cursor, layouter, &counter_in)?;
+        self.config.expose_bytes(cursor, layouter, &ciphertext)?;
+        self.config.expose_bytes(cursor, layouter, &counter_out)?;
+
+        Ok(ciphertext)
+    }
+
+    // CTR mode returning the ciphertext and the counter blocks used
+    fn ctr_blocks(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        iv: [u8; 16],
+        plaintext: &[u8],
+        mode: CounterMode,
+    ) -> Result<(Vec<AssignedCell<Fp, Fp>>, Vec<Vec<AssignedCell<Fp, Fp>>>), Error> {
+        if plaintext.is_empty() {
+            return Ok((vec![], vec![]));
+        }
+
+        let mut blocks: Vec<Vec<AssignedCell<Fp, Fp>>> = vec![];
+        let mut ciphertext = vec![];
+
+        for chunk in plaintext.chunks(16) {
+            let (block, keystream) =
+                self.ctr_keystream_block(cursor, layouter, iv, blocks.last(), mode)?;
+            let xor_chip = U8XorChip::construct(self.config.xor_config(cursor));
+            let assigned_chunk = self.config.assign_bytes(cursor, layouter, chunk)?;
+
+            // The last chunk may be short of a block, the rest of the keystream is unused
+            ciphertext.extend(xor_chip.xor_blocks(
+                layouter,
+                &assigned_chunk,
+                &keystream[..assigned_chunk.len()],
+            )?);
+            blocks.push(block);
+        }
+
+        Ok((ciphertext, blocks))
+    }
+
+    // Counter block following `prev` in the mode, or `iv` for the first one, and its keystream block.
+    // The rows of the counter increment and of the use of the keystream
+    // are accounted in the same group of columns as the AES call.
+    fn ctr_keystream_block(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        iv: [u8; 16],
+        prev: Option<&Vec<AssignedCell<Fp, Fp>>>,
+        mode: CounterMode,
+    ) -> Result<(Vec<AssignedCell<Fp, Fp>>, Vec<AssignedCell<Fp, Fp>>), Error> {
+        self.config
+            .check_rows_callable(cursor, CTR_BLOCK_ROWS + AES_ROWS)?;
+        cursor.extra_rows += CTR_BLOCK_ROWS;
+
+        let counter_chip = CounterChip::construct(self.config.counter_config(cursor));
+        let block = match prev {
+            None => self.config.assign_bytes(cursor, layouter, &iv)?,
+            Some(prev) => counter_chip.increment_with_mode(layouter, prev, mode)?,
+        };
+        let keystream = self.encrypt_cells(cursor, layouter, &block)?;
+
+        Ok((block, keystream))
+    }
+
+    /// CTR keystream of `num_blocks` blocks from the counter block `iv`,
+    /// committed to instead of exposing a ciphertext, e.g. for verifiable encryption.
+    ///
+    /// The next rows of the instance column are the iv and the Poseidon hash
+    /// of each keystream block, see `hash::keystream_commitments`.
+    /// Whoever holds the key can later reveal the keystream,
+    /// and anyone can check it against the commitments and decrypt the ciphertext with it.
+    #[cfg(feature = "poseidon")]
+    pub fn ctr_commit_keystream(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        hash_config: &BlockHashConfig,
+        iv: [u8; 16],
+        num_blocks: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let mut blocks: Vec<Vec<AssignedCell<Fp, Fp>>> = vec![];
+        let mut keystream = vec![];
+        let mut digests = vec![];
+
+        for _ in 0..num_blocks {
+            let (block, keystream_block) = self.ctr_keystream_block(
+                cursor,
+                layouter,
+                iv,
+                blocks.last(),
+                CounterMode::Full128,
+            )?;
+            digests.push(hash_config.hash_block(layouter, &keystream_block)?);
+            keystream.extend(keystream_block);
+            blocks.push(block);
+        }
+
+        let iv = match blocks.first() {
+            Some(first) => first.clone(),
+            None => self.config.assign_bytes(cursor, layouter, &iv)?,
+        };
+        self.config.expose_bytes(cursor, layouter, &iv)?;
+        self.config.expose_bytes(cursor, layouter, &digests)?;
+
+        Ok(keystream)
+    }
+
+    /// Encrypt the plaintext under the scheduled key and the related key `key XOR mask`,
+    /// returning both ciphertexts.
+    /// The related key is derived from the scheduled key bytes with the XOR chip,
+    /// and the mask is exposed to the next 16 rows of the instance column.
+    pub fn encrypt_related_keys(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        mask: [u8; 16],
+        plaintext: [u8; 16],
+    ) -> Result<(Vec<AssignedCell<Fp, Fp>>, Vec<AssignedCell<Fp, Fp>>), Error> {
+        self.config.check_key_schedule(cursor, &self.keys)?;
+        self.config
+            .add_key_schedule(cursor, KEY_SCHEDULE_ROWS + 32)?;
+
+        let xor_chip = U8XorChip::construct(self.config.xor_config(cursor));
+        let assigned_mask = self.config.assign_bytes(cursor, layouter, &mask)?;
+        self.config.expose_bytes(cursor, layouter, &assigned_mask)?;
+        let related_key = xor_chip.xor_blocks(layouter, &self.keys[0], &assigned_mask)?;
+        let related_keys = self
+            .config
+            .key_schedule_config
+            .schedule_keys_from_cells(layouter, &related_key)?;
+        cursor.key_schedules.push(related_keys[0][0].cell());
+
+        let ciphertext = self.encrypt(cursor, layouter, plaintext)?.into_cells();
+        let related_ciphertext =
+            self.config
+                .encrypt_bytes(cursor, layouter, &plaintext, &related_keys)?;
+
+        Ok((ciphertext, related_ciphertext))
+    }
+
+    /// Encrypt one private plaintext under this key and `other_key`, i.e. prove that
+    /// the two ciphertexts are of the same plaintext without revealing it.
+    /// The other key is scheduled with `schedule_another_key`, and the plaintext cells
+    /// are assigned once and fed to both encryptions.
+    /// Both ciphertexts are exposed to the next 32 rows of the instance column,
+    /// the one under this key first. Returns both ciphertexts in the same order.
+    pub fn encrypt_same_plaintext(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        other_key: [u8; 16],
+        plaintext: [u8; 16],
+    ) -> Result<(Vec<AssignedCell<Fp, Fp>>, Vec<AssignedCell<Fp, Fp>>), Error> {
+        let other = self.schedule_another_key(cursor, layouter, other_key)?;
+
+        let assigned = self.config.assign_bytes(cursor, layouter, &plaintext)?;
+        let ciphertext = self.encrypt_cells(cursor, layouter, &assigned)?;
+        let other_ciphertext = other.encrypt_cells(cursor, layouter, &assigned)?;
+        self.expose_ciphertext(cursor, layouter, &ciphertext)?;
+        self.expose_ciphertext(cursor, layouter, &other_ciphertext)?;
+
+        Ok((ciphertext, other_ciphertext))
+    }
+
+    /// AES Key Wrap of RFC 3394 of the key data under this key as the KEK,
+    /// returning the wrapped key, 8 bytes longer than the key data.
+    /// The key data must be at least 2 blocks of 8 bytes, otherwise it fails with
+    /// `AesError::InvalidKeyDataLength` before assigning any cells.
+    /// See `reference::key_wrap_reference` to compute it out of the circuit.
+    pub fn key_wrap(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        key_data: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        check_key_data_length(key_data.len())?;
+
+        self.config.add_extra_rows(cursor, key_data.len() as u64)?;
+        let assigned = self.config.assign_bytes(cursor, layouter, key_data)?;
+        self.key_wrap_cells(cursor, layouter, &assigned)
+    }
+
+    /// Same as `key_wrap` with the key data given as range checked cells,
+    /// e.g. the key of another schedule from `key_input_cells`.
+    pub fn key_wrap_cells(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        key_data: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        check_key_data_length(key_data.len())?;
+
+        let n = key_data.len() / 8;
+        self.config
+            .add_extra_rows(cursor, KEY_WRAP_IV.len() as u64)?;
+        let mut a = self
+            .config
+            .assign_constant_bytes(cursor, layouter, &KEY_WRAP_IV)?;
+        let mut r = key_data
+            .chunks(8)
+            .map(|block| block.to_vec())
+            .collect::<Vec<_>>();
+
+        // B = AES(K, A | R[i]), A = MSB(64, B) ^ t, R[i] = LSB(64, B) for t = n * j + i
+        for j in 0..6 {
+            for (i, r_i) in r.iter_mut().enumerate() {
+                let block = [a.as_slice(), r_i.as_slice()].concat();
+                let b = self.encrypt_cells(cursor, layouter, &block)?;
+                let t = ((n * j + i + 1) as u64).to_be_bytes();
+                a = self.config.xor_constant(cursor, layouter, &b[..8], &t)?;
+                *r_i = b[8..].to_vec();
+            }
+        }
+
+        Ok(a.into_iter().chain(r.into_iter().flatten()).collect())
+    }
+
+    /// AES Key Unwrap of RFC 3394 of the wrapped key under this key as the KEK,
+    /// returning the key data, 8 bytes shorter than the wrapped key.
+    /// The integrity check is constrained: the first 8 bytes unwrapped must be the IV of
+    /// `KEY_WRAP_IV`, so a wrapped key of another KEK or tampered with is unsatisfiable.
+    /// The key data must be at least 2 blocks of 8 bytes, otherwise it fails with
+    /// `AesError::InvalidKeyDataLength` before assigning any cells.
+    pub fn key_unwrap(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        wrapped: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        check_key_data_length(wrapped.len().saturating_sub(8))?;
+
+        self.config.add_extra_rows(cursor, wrapped.len() as u64)?;
+        let assigned = self.config.assign_bytes(cursor, layouter, wrapped)?;
+        self.key_unwrap_cells(cursor, layouter, &assigned)
+    }
+
+    /// Same as `key_unwrap` with the wrapped key given as range checked cells,
+    /// e.g. the output of `key_wrap`.
+    pub fn key_unwrap_cells(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        wrapped: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        check_key_data_length(wrapped.len().saturating_sub(8))?;
+
+        let n = wrapped.len() / 8 - 1;
+        let mut a = wrapped[..8].to_vec();
+        let mut r = wrapped[8..]
+            .chunks(8)
+            .map(|block| block.to_vec())
+            .collect::<Vec<_>>();
+
+        // B = AES-1(K, (A ^ t) | R[i]), A = MSB(64, B), R[i] = LSB(64, B) for t = n * j + i
+        for j in (0..6).rev() {
+            for (i, r_i) in r.iter_mut().enumerate().rev() {
+                let t = ((n * j + i + 1) as u64).to_be_bytes();
+                let a_t = self.config.xor_constant(cursor, layouter, &a, &t)?;
+                let block = [a_t.as_slice(), r_i.as_slice()].concat();
+                let b = self
+                    .config
+                    .decrypt_assigned(cursor, layouter, &block, &self.keys)?;
+                a = b[..8].to_vec();
+                *r_i = b[8..].to_vec();
+            }
+        }
+
+        for (byte, iv) in a.iter().zip(KEY_WRAP_IV) {
+            assert_byte_equals(layouter, byte, iv)?;
+        }
+
+        Ok(r.into_iter().flatten().collect())
+    }
+
+    /// GMAC, i.e. GCM with the empty plaintext, authenticating only the AAD.
+    /// The AAD is zero padded to 16 bytes blocks, followed by the length block
+    /// of len(A) || len(C) in bits. Returns the 16 bytes tag.
+    pub fn gmac(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        nonce: [u8; 12],
+        aad: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        // Hash subkey H = E_K(0^128)
+        self.config.add_extra_rows(cursor, 16)?;
+        let zero = self
+            .config
+            .assign_constant_bytes(cursor, layouter, &[0u8; 16])?;
+        let h = self.encrypt_cells(cursor, layouter, &zero)?;
+
+        // Pre-counter block J0 = nonce || 0^31 || 1
+        self.config.add_extra_rows(cursor, 16)?;
+        let mut j0 = self.config.assign_bytes(cursor, layouter, &nonce)?;
+        j0.append(
+            &mut self
+                .config
+                .assign_constant_bytes(cursor, layouter, &[0, 0, 0, 1])?,
+        );
+        let encrypted_j0 = self.encrypt_cells(cursor, layouter, &j0)?;
+
+        // AAD blocks and the length block
+        self.config
+            .add_extra_rows(cursor, 16 * ((aad.len() + 15) / 16 + 1) as u64)?;
+        let mut blocks = aad
+            .chunks(16)
+            .map(|chunk| self.config.assign_padded_block(cursor, layouter, chunk))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut length_block = [0u8; 16];
+        length_block[..8].copy_from_slice(&(aad.len() as u64 * 8).to_be_bytes());
+        blocks.push(
+            self.config
+                .assign_constant_bytes(cursor, layouter, &length_block)?,
+        );
+
+        let s = self.config.ghash(cursor, layouter, &h, &blocks)?;
+
+        self.config.add_extra_rows(cursor, 16)?;
+        let xor_chip = U8XorChip::construct(self.config.xor_config(cursor));
+        xor_chip.xor_blocks(layouter, &encrypted_j0, &s)
+    }
+
+    /// Pseudo random function on a block with domain separation.
+    /// Computes AES_k(input XOR (domain || 0^120)) of the input given as 16 range checked
+    /// cells. The domain is a constant of the circuit, XORed into the first input byte
+    /// in the circuit, so an output can't be proven under another domain.
+    pub fn prf(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        input: &[AssignedCell<Fp, Fp>],
+        domain: u8,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let mut tweak = [0u8; 16];
+        tweak[0] = domain;
+        let block = self.config.xor_constant(cursor, layouter, input, &tweak)?;
+        self.encrypt_cells(cursor, layouter, &block)
+    }
+
+    /// Constrain the scheduled key bytes to the next rows of the instance column.
+    pub fn expose_key(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let key = self.keys[0].clone();
+        self.config.expose_bytes(cursor, layouter, &key)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,1662 @@
+use super::*;
+
+use crate::{
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+    reference::{
+        encrypt_block_reference, encrypt_trace_reference, key_schedule_reference,
+        witness_trace_reference,
+    },
+    table::load_enc_full_table,
+    utils::{cells_to_bytes, pack_block, pack_bytes},
+};
+
+#[cfg(feature = "halo2-pse")]
+use harness::{keyed_circuit, test_circuit, Synthesize, TestCircuit};
+
+#[cfg(feature = "halo2-pse")]
+mod cbc;
+#[cfg(feature = "halo2-pse")]
+mod ctr;
+#[cfg(feature = "halo2-pse")]
+mod gmac;
+#[cfg(feature = "halo2-pse")]
+mod harness;
+#[cfg(feature = "halo2-pse")]
+mod key_wrap;
+
+const K: u32 = 20;
+
+// Encrypts the plaintext 1000 times
+#[cfg(feature = "halo2-pse")]
+fn aes_circuit(key: [u8; 16], plaintext: [u8; 16]) -> TestCircuit<K, 3, impl Synthesize<K, 3>> {
+    TestCircuit::new(move |config, layouter| {
+        let aes = config.schedule_key(layouter, key)?;
+        let mut cursor = EncryptCursor::default();
+
+        for _ in 0..1000 {
+            aes.encrypt(&mut cursor, layouter, plaintext)?;
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_correct_encryption() {
+    let circuit = aes_circuit([0u8; 16], [0u8; 16]);
+
+    let mock = MockProver::run(K, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+// Encrypts with the public ciphertext, and the public plaintext if set
+#[cfg(feature = "halo2-pse")]
+fn ciphertext_circuit(
+    key: [u8; 16],
+    plaintext: [u8; 16],
+    expected: [u8; 16],
+    public_plaintext: bool,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(key, move |aes, cursor, layouter| {
+        let (ciphertext, rows) =
+            aes.encrypt_with_public_io(cursor, layouter, plaintext, public_plaintext)?;
+
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == expected);
+        // The words are the columns of the state, i.e. consecutive bytes
+        cells_to_bytes(ciphertext.word(1)).assert_if_known(|bytes| bytes[..] == expected[4..8]);
+        let start = if public_plaintext { 16 } else { 0 };
+        assert_eq!(rows, start..start + 16);
+        assert_eq!(cursor.instance_rows(), &[start + 16]);
+        Ok(())
+    })
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_fips_197_ciphertext() {
+    let circuit = ciphertext_circuit(FIPS_KEY, FIPS_PLAINTEXT, FIPS_CIPHERTEXT, false);
+
+    let instance = build_public_inputs(Some(FIPS_CIPHERTEXT), None, None);
+    let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+    mock.assert_satisfied();
+
+    // The proof is bound to the ciphertext
+    let instance = build_public_inputs(Some([0u8; 16]), None, None);
+    let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_with_public_io() {
+    let circuit = ciphertext_circuit(FIPS_KEY, FIPS_PLAINTEXT, FIPS_CIPHERTEXT, true);
+
+    let instance = [FIPS_PLAINTEXT, FIPS_CIPHERTEXT]
+        .concat()
+        .iter()
+        .map(|&b| Fp::from(b as u64))
+        .collect::<Vec<_>>();
+    let mock = MockProver::run(17, &circuit, vec![instance.clone()]).unwrap();
+    mock.assert_satisfied();
+
+    // Another plaintext with the same ciphertext fails
+    let mut wrong = instance;
+    wrong[0] += Fp::from(1);
+    let mock = MockProver::run(17, &circuit, vec![wrong]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+#[cfg(all(feature = "halo2-pse", feature = "test-vectors"))]
+fn test_encryption_matches_aes_crate() {
+    use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+    use aes::Aes128;
+
+    for _ in 0..3 {
+        let key: [u8; 16] = rand::random();
+        let plaintext: [u8; 16] = rand::random();
+
+        let cipher = Aes128::new(&GenericArray::from(key));
+        let mut block = GenericArray::from(plaintext);
+        cipher.encrypt_block(&mut block);
+        let mut expected = [0u8; 16];
+        expected.copy_from_slice(&block);
+
+        let circuit = ciphertext_circuit(key, plaintext, expected, false);
+        let instance = build_public_inputs(Some(expected), None, None);
+        let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+        mock.assert_satisfied();
+    }
+}
+
+// FIPS-197 Appendix C.1
+const FIPS_KEY: [u8; 16] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+];
+const FIPS_PLAINTEXT: [u8; 16] = [
+    0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+];
+const FIPS_CIPHERTEXT: [u8; 16] = [
+    0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+];
+
+// NIST SP 800-38A, the key of the CBC and CTR examples
+const NIST_KEY: [u8; 16] = [
+    0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c,
+];
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_public_inputs() {
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        let ciphertext = aes.encrypt(cursor, layouter, FIPS_PLAINTEXT)?;
+
+        aes.expose_ciphertext(cursor, layouter, &ciphertext)?;
+        aes.expose_key(cursor, layouter)?;
+
+        Ok(())
+    });
+
+    let instance = build_public_inputs(Some(FIPS_CIPHERTEXT), None, Some(FIPS_KEY));
+    let mock = MockProver::run(17, &circuit, vec![instance.clone()]).unwrap();
+    mock.assert_satisfied();
+
+    // Swapping ciphertext and key must break the proof
+    let mut permuted = instance[16..].to_vec();
+    permuted.extend_from_slice(&instance[..16]);
+    let mock = MockProver::run(17, &circuit, vec![permuted]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+const ZERO_KEY_SCHEDULE: [&str; 11] = [
+    "00000000000000000000000000000000",
+    "62636363626363636263636362636363",
+    "9b9898c9f9fbfbaa9b9898c9f9fbfbaa",
+    "90973450696ccffaf2f457330b0fac99",
+    "ee06da7b876a1581759e42b27e91ee2b",
+    "7f2e2b88f8443e098dda7cbbf34b9290",
+    "ec614b851425758c99ff09376ab49ba7",
+    "217517873550620bacaf6b3cc61bf09b",
+    "0ef903333ba9613897060a04511dfa9f",
+    "b1d4d8e28a7db9da1d7bb3de4c664941",
+    "b4ef5bcb3e92e21123e951cf6f8f188e",
+];
+const ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT: &str = "66e94bd4ef8a2c3b884cfa59ca342b2e";
+
+fn hex_to_block(hex: &str) -> [u8; 16] {
+    std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+}
+
+fn zero_key_schedule() -> [[u8; 16]; 11] {
+    std::array::from_fn(|i| hex_to_block(ZERO_KEY_SCHEDULE[i]))
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_exported_round_keys() {
+    let circuit = keyed_circuit([0u8; 16], |aes, _, _| {
+        aes.exported_round_keys()
+            .assert_if_known(|round_keys| *round_keys == zero_key_schedule());
+
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_schedule_key_precomputed() {
+    let circuit = test_circuit(|config, layouter| {
+        let aes = config.schedule_key_precomputed(layouter, zero_key_schedule())?;
+        let mut cursor = EncryptCursor::default();
+        let ciphertext = aes.encrypt(&mut cursor, layouter, [0u8; 16])?;
+
+        cells_to_bytes(&ciphertext)
+            .assert_if_known(|bytes| bytes[..] == hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT));
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+// FIPS-197 Appendix C.1, round[10].start and round[10].k_sch
+const FIPS_ROUND_10_START: &str = "bd6e7c3df2b5779e0b61216e8b10b689";
+const FIPS_ROUND_10_KEY: &str = "13111d7fe3944a17f307a78b4d2b30c5";
+
+// The last round must be SubBytes -> ShiftRows -> AddRoundKey with no MixColumns
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_last_round() {
+    let circuit = test_circuit(|config, layouter| {
+        let cursor = EncryptCursor::default();
+        let state = config.assign_bytes(&cursor, layouter, &hex_to_block(FIPS_ROUND_10_START))?;
+        let round_key = config.assign_bytes(&cursor, layouter, &hex_to_block(FIPS_ROUND_10_KEY))?;
+        let output = config.last_round(
+            &cursor,
+            layouter,
+            &mut Outputs::Computed,
+            &state,
+            &round_key,
+        )?;
+
+        cells_to_bytes(&output).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_round_trace() {
+    assert_eq!(
+        encrypt_block_reference(&key_schedule_reference(FIPS_KEY), FIPS_PLAINTEXT),
+        FIPS_CIPHERTEXT
+    );
+
+    let circuit = test_circuit(|config, layouter| {
+        let cursor = EncryptCursor::default();
+
+        let round_keys = key_schedule_reference(FIPS_KEY);
+        let trace = encrypt_trace_reference(&round_keys, FIPS_PLAINTEXT);
+
+        // Each round from the reference input state gives the reference output state
+        for round in 1..=10 {
+            let state = config.assign_bytes(&cursor, layouter, &trace[round - 1])?;
+            let round_key = config.assign_bytes(&cursor, layouter, &round_keys[round])?;
+            let outputs = &mut Outputs::Computed;
+            let output = if round < 10 {
+                config.middle_round(&cursor, layouter, outputs, &state, &round_key)?
+            } else {
+                config.last_round(&cursor, layouter, outputs, &state, &round_key)?
+            };
+
+            cells_to_bytes(&output).assert_if_known(|bytes| bytes[..] == trace[round]);
+        }
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_aes_round() {
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        let expected = aes.encrypt(cursor, layouter, FIPS_PLAINTEXT)?;
+
+        // The initial AddRoundKey followed by 10 rounds, the last one without MixColumns
+        let plaintext = aes.assign_bytes(cursor, layouter, &FIPS_PLAINTEXT)?;
+        let mut state = aes.initial_round(
+            cursor,
+            layouter,
+            &mut Outputs::Computed,
+            &plaintext,
+            &aes.keys[0],
+        )?;
+        for no_round in 1..=10 {
+            state = aes.aes_round(cursor, layouter, &state, &aes.keys[no_round], no_round < 10)?;
+        }
+
+        cells_to_bytes(&state)
+            .zip(cells_to_bytes(&expected))
+            .assert_if_known(|(state, expected)| state == expected && state[..] == FIPS_CIPHERTEXT);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+// Columns before and after MixColumns, not symmetric under the transposition
+// https://en.wikipedia.org/wiki/Rijndael_MixColumns#Test_vectors_for_MixColumn()
+const MIX_COLUMNS_VECTORS: [([u8; 4], [u8; 4]); 3] = [
+    ([0xdb, 0x13, 0x53, 0x45], [0x8e, 0x4d, 0xa1, 0xbc]),
+    ([0xf2, 0x0a, 0x22, 0x5c], [0x9f, 0xdc, 0x58, 0x9d]),
+    ([0x2d, 0x26, 0x31, 0x4c], [0x4d, 0x7e, 0xbd, 0xf8]),
+];
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_mix_columns_orientation() {
+    let circuit = test_circuit(|config, layouter| {
+        let cursor = EncryptCursor::default();
+
+        for (column, expected) in MIX_COLUMNS_VECTORS {
+            let word = config.assign_bytes(&cursor, layouter, &column)?;
+            let mixed = MIX_COLUMNS
+                .iter()
+                .map(|coeffs| config.lcon(&cursor, layouter, &word, coeffs))
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            cells_to_bytes(&mixed).assert_if_known(|bytes| bytes[..] == expected);
+        }
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_inv_mix_columns_coefficients() {
+    let circuit = test_circuit(|config, layouter| {
+        let cursor = EncryptCursor::default();
+
+        // InvMixColumns undoes MixColumns
+        for (expected, column) in MIX_COLUMNS_VECTORS {
+            let word = config.assign_bytes(&cursor, layouter, &column)?;
+            let unmixed = INV_MIX_COLUMNS
+                .iter()
+                .map(|coeffs| config.lcon(&cursor, layouter, &word, coeffs))
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            cells_to_bytes(&unmixed).assert_if_known(|bytes| bytes[..] == expected);
+        }
+
+        let word = config.assign_bytes(&cursor, layouter, &[1, 2, 3, 4])?;
+        assert!(matches!(
+            config.lcon(&cursor, layouter, &word, &[5, 1, 1, 1]),
+            Err(Error::Synthesis)
+        ));
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_ecb_encrypt() {
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        let plaintext = [FIPS_PLAINTEXT, FIPS_PLAINTEXT].concat();
+        let ciphertext = aes.ecb_encrypt(cursor, layouter, &plaintext)?;
+        cells_to_bytes(&ciphertext)
+            .assert_if_known(|bytes| *bytes == [FIPS_CIPHERTEXT, FIPS_CIPHERTEXT].concat());
+
+        // A partial block is rejected before assigning anything
+        let count = cursor.count;
+        let result = aes.ecb_encrypt(cursor, layouter, &plaintext[..17]);
+        assert!(matches!(result, Err(AesError::InvalidLength { len: 17 })));
+        assert_eq!(cursor.count, count);
+
+        // Block aligned input gets a whole block of padding
+        let padding_block = encrypt_block_reference(&key_schedule_reference(FIPS_KEY), [16; 16]);
+        let ciphertext = aes.ecb_encrypt_padded(cursor, layouter, &FIPS_PLAINTEXT)?;
+        cells_to_bytes(&ciphertext)
+            .assert_if_known(|bytes| *bytes == [FIPS_CIPHERTEXT, padding_block].concat());
+
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[derive(Clone)]
+struct TestSeparateTablesCircuit;
+
+impl Circuit<Fp> for TestSeparateTablesCircuit {
+    type Config = FixedAes128Config<17, 1>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure_with_layout(meta, TableLayout::Separate)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, FIPS_KEY)?;
+        let mut cursor = EncryptCursor::default();
+        let ciphertext = aes.encrypt(&mut cursor, &mut layouter, FIPS_PLAINTEXT)?;
+
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_separate_tables() {
+    let mock = MockProver::run(17, &TestSeparateTablesCircuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    // The table columns are fixed columns
+    let fixed_columns = |layout| {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        FixedAes128Config::<17, 1>::configure_with_layout(&mut meta, layout);
+        meta.num_fixed_columns()
+    };
+    assert_eq!(
+        fixed_columns(TableLayout::Separate) - fixed_columns(TableLayout::Combined),
+        18 - 4
+    );
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_reset() {
+    let circuit = test_circuit(|config, layouter| {
+        let aes = config.schedule_key(layouter, [0u8; 16])?;
+        let mut cursor = EncryptCursor::default();
+        let ciphertext = aes.encrypt(&mut cursor, layouter, [0u8; 16])?;
+        cells_to_bytes(&ciphertext)
+            .assert_if_known(|bytes| bytes[..] == hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT));
+
+        // Reuse the config and the cursor for another key
+        cursor.reset();
+        assert_eq!(cursor.count, 0);
+        let aes = aes.into_config().schedule_key(layouter, FIPS_KEY)?;
+        let ciphertext = aes.encrypt(&mut cursor, layouter, FIPS_PLAINTEXT)?;
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+        assert_eq!(cursor.count, 1);
+
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_schedule_another_key() {
+    // Schedule the second key with `schedule_key` of a cloned config if unaccounted
+    let circuit = |unaccounted: bool| {
+        test_circuit(move |config, layouter| {
+            let mut cursor = EncryptCursor::default();
+            let aes = config.clone().schedule_key(layouter, FIPS_KEY)?;
+            let ciphertext = aes.encrypt(&mut cursor, layouter, FIPS_PLAINTEXT)?;
+            cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+
+            let other = if unaccounted {
+                config.schedule_key(layouter, [0u8; 16])?
+            } else {
+                aes.schedule_another_key(&mut cursor, layouter, [0u8; 16])?
+            };
+            let ciphertext = other.encrypt(&mut cursor, layouter, [0u8; 16])?;
+            cells_to_bytes(&ciphertext).assert_if_known(|bytes| {
+                bytes[..] == hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT)
+            });
+
+            // The first key is still usable
+            aes.encrypt(&mut cursor, layouter, [0u8; 16])?;
+
+            Ok(())
+        })
+    };
+
+    let mock = MockProver::run(17, &circuit(false), vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    let result = MockProver::run(17, &circuit(true), vec![vec![]]);
+    assert!(matches!(result, Err(Error::Synthesis)));
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_constrain_same_key() {
+    let circuit = |keys: [[u8; 16]; 2]| {
+        test_circuit(move |config, layouter| {
+            let mut cursor = EncryptCursor::default();
+            let aes = config.schedule_key(layouter, keys[0])?;
+            let other = aes.schedule_another_key(&mut cursor, layouter, keys[1])?;
+
+            let c0 = aes.encrypt(&mut cursor, layouter, FIPS_PLAINTEXT)?;
+            let c1 = other.encrypt(&mut cursor, layouter, [0u8; 16])?;
+            aes.expose_ciphertext(&mut cursor, layouter, &c0)?;
+            aes.expose_ciphertext(&mut cursor, layouter, &c1)?;
+
+            aes.constrain_same_key(layouter, &other)
+        })
+    };
+    // The ciphertexts are right, only the keys differ
+    let instance = |keys: [[u8; 16]; 2]| {
+        let c0 = encrypt_block_reference(&key_schedule_reference(keys[0]), FIPS_PLAINTEXT);
+        let c1 = encrypt_block_reference(&key_schedule_reference(keys[1]), [0u8; 16]);
+        [
+            build_public_inputs(Some(c0), None, None),
+            build_public_inputs(Some(c1), None, None),
+        ]
+        .concat()
+    };
+
+    let keys = [FIPS_KEY, FIPS_KEY];
+    let mock = MockProver::run(17, &circuit(keys), vec![instance(keys)]).unwrap();
+    mock.assert_satisfied();
+
+    let mut other_key = FIPS_KEY;
+    other_key[15] ^= 1;
+    let keys = [FIPS_KEY, other_key];
+    let mock = MockProver::run(17, &circuit(keys), vec![instance(keys)]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+// Encrypts the FIPS-197 plaintext n times, with `encrypt_repeated` if repeated
+#[cfg(feature = "halo2-pse")]
+fn repeated_plaintext_circuit(
+    n: usize,
+    repeated: bool,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+        let ciphertexts = if repeated {
+            aes.encrypt_repeated(cursor, layouter, FIPS_PLAINTEXT, n)?
+        } else {
+            (0..n)
+                .map(|_| aes.encrypt(cursor, layouter, FIPS_PLAINTEXT))
+                .collect::<Result<Vec<_>, AesError>>()?
+        };
+
+        assert_eq!(ciphertexts.len(), n);
+        for ciphertext in ciphertexts.iter() {
+            cells_to_bytes(ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_repeated() {
+    use crate::dev::used_rows_per_advice;
+
+    let n = 3;
+    let mock = MockProver::run(17, &repeated_plaintext_circuit(n, false), vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+    let separate_rows = used_rows_per_advice(&mock)[0] as u64;
+    assert_eq!(separate_rows, KEY_SCHEDULE_ROWS + n as u64 * AES_ROWS);
+
+    let mock = MockProver::run(17, &repeated_plaintext_circuit(n, true), vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+    let repeated_rows = used_rows_per_advice(&mock)[0] as u64;
+    assert_eq!(
+        separate_rows - repeated_rows,
+        (n as u64 - 1) * INITIAL_ROUND_ROWS
+    );
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_repeated_none() {
+    let mock = MockProver::run(17, &repeated_plaintext_circuit(0, true), vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+    assert_eq!(
+        crate::dev::used_rows_per_advice(&mock)[0] as u64,
+        KEY_SCHEDULE_ROWS
+    );
+}
+
+// Flip one witness cell of each kind and check that the proof fails.
+// The cells following the flipped one are computed from it,
+// so only the constraints on the flipped cell itself can catch it.
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_faults_are_caught() {
+    use crate::{
+        fault::{with_fault, FaultSite},
+        halo2_proofs::dev::VerifyFailure,
+    };
+
+    // Encrypts without asserting the values, so that faulted witnesses can be synthesized
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        aes.encrypt(cursor, layouter, FIPS_PLAINTEXT)?;
+
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    // (site, index of the witness at the site, caught by a lookup)
+    let faults = [
+        // The key schedule comes first with 40 S-boxes and 200 XORs
+        (FaultSite::Sbox, 0, true),
+        (FaultSite::Xor, 0, true),
+        (FaultSite::Xor, 199, true),
+        // Encryption
+        (FaultSite::Sbox, 40, true),
+        (FaultSite::Xor, 200, true),
+        (FaultSite::Xor, 250, true),
+        (FaultSite::Mul2, 0, true),
+        (FaultSite::Mul3, 0, true),
+        // Round constant by the gate, and its zero padding by the constants
+        (FaultSite::RoundConstant, 0, false),
+        (FaultSite::RoundConstant, 1, false),
+    ];
+
+    for (site, index, by_lookup) in faults {
+        let mock = with_fault(site, index, || {
+            MockProver::run(17, &circuit, vec![vec![]]).unwrap()
+        });
+        let failures = match mock.verify() {
+            Ok(()) => panic!("{:?} {} is not constrained", site, index),
+            Err(failures) => failures,
+        };
+
+        if by_lookup {
+            assert!(
+                failures
+                    .iter()
+                    .any(|failure| matches!(failure, VerifyFailure::Lookup { .. })),
+                "{:?} {} should fail a lookup",
+                site,
+                index
+            );
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_schedule_key_select() {
+    let circuit =
+        |keys: Vec<[u8; 16]>, key_index: usize, plaintext: [u8; 16], expected: [u8; 16]| {
+            test_circuit(move |config, layouter| {
+                let mut cursor = EncryptCursor::default();
+                let aes = config.schedule_key_select(&mut cursor, layouter, &keys, key_index)?;
+                let ciphertext = aes.encrypt(&mut cursor, layouter, plaintext)?;
+
+                cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == expected);
+                Ok(())
+            })
+        };
+    let keys = vec![FIPS_KEY, [0u8; 16]];
+
+    let mock = MockProver::run(
+        17,
+        &circuit(keys.clone(), 0, FIPS_PLAINTEXT, FIPS_CIPHERTEXT),
+        vec![vec![Fp::from(0)]],
+    )
+    .unwrap();
+    mock.assert_satisfied();
+    let rows = crate::dev::used_rows_per_advice(&mock);
+    assert_eq!(
+        rows[0] as u64,
+        2 * KEY_SCHEDULE_ROWS + 2 * KEY_SELECT_ROWS_PER_KEY + KEY_SELECT_ROWS + AES_ROWS
+    );
+
+    let circuit_1 = circuit(
+        keys,
+        1,
+        [0u8; 16],
+        hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT),
+    );
+    let mock = MockProver::run(17, &circuit_1, vec![vec![Fp::from(1)]]).unwrap();
+    mock.assert_satisfied();
+
+    // The public index doesn't match the key used
+    let mock = MockProver::run(17, &circuit_1, vec![vec![Fp::from(0)]]).unwrap();
+    assert!(mock.verify().is_err());
+
+    let result = MockProver::run(
+        17,
+        &circuit(vec![], 0, FIPS_PLAINTEXT, FIPS_CIPHERTEXT),
+        vec![vec![]],
+    );
+    assert!(matches!(result, Err(Error::Synthesis)));
+}
+
+#[derive(Clone)]
+struct TestTablesNotLoadedCircuit;
+
+impl Circuit<Fp> for TestTablesNotLoadedCircuit {
+    type Config = FixedAes128Config<17, 1>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let aes = config.schedule_key(&mut layouter, FIPS_KEY)?;
+        let mut cursor = EncryptCursor::default();
+        aes.encrypt(&mut cursor, &mut layouter, FIPS_PLAINTEXT)?;
+
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_before_load_tables() {
+    let result = MockProver::run(17, &TestTablesNotLoadedCircuit, vec![vec![]]);
+    assert!(matches!(result, Err(Error::Synthesis)));
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_capacity_exceeded() {
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        // One row short of a block in the only group of columns
+        cursor.extra_rows = aes.available_rows(cursor) - AES_ROWS + 1;
+        assert!(matches!(
+            aes.encrypt(cursor, layouter, FIPS_PLAINTEXT),
+            Err(AesError::CapacityExceeded { requested, available })
+                if requested == AES_ROWS && available == AES_ROWS - 1
+        ));
+        assert!(matches!(
+            aes.schedule_another_key(cursor, layouter, FIPS_KEY),
+            Err(Error::Synthesis)
+        ));
+
+        // Surfaced to the prover
+        aes.encrypt(cursor, layouter, FIPS_PLAINTEXT)?;
+        Ok(())
+    });
+
+    let result = MockProver::run(17, &circuit, vec![vec![]]);
+    assert!(matches!(result, Err(Error::Synthesis)));
+}
+
+// Not a proof of correctness, but catches gross bugs like a stuck round
+// or an identity MixColumns, which would flip only a few bits.
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_avalanche() {
+    for (byte, bit) in [(15, 0x01), (0, 0x80), (7, 0x10)] {
+        let mut flipped = FIPS_PLAINTEXT;
+        flipped[byte] ^= bit;
+        let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+            let c0 = aes.encrypt(cursor, layouter, FIPS_PLAINTEXT)?;
+            let c1 = aes.encrypt(cursor, layouter, flipped)?;
+
+            cells_to_bytes(&c0)
+                .zip(cells_to_bytes(&c1))
+                .assert_if_known(|(c0, c1)| {
+                    let flipped: u32 = c0.iter().zip(c1).map(|(a, b)| (a ^ b).count_ones()).sum();
+                    // Roughly half of 128 bits should flip
+                    (32..=96).contains(&flipped)
+                });
+            Ok(())
+        });
+
+        let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+        mock.assert_satisfied();
+    }
+}
+
+// AES is a permutation under a fixed key, so distinct plaintexts can't collide.
+// A collision would mean e.g. a round collapsing the state.
+// The round trip through decryption is checked by `test_decrypt`.
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encryption_is_permutation() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut plaintexts = std::collections::HashSet::new();
+    while plaintexts.len() < 32 {
+        plaintexts.insert(rng.gen::<[u8; 16]>());
+    }
+    let circuit = keyed_circuit(rng.gen(), |aes, cursor, layouter| {
+        let ciphertexts = plaintexts
+            .iter()
+            .map(|&plaintext| {
+                aes.encrypt(cursor, layouter, plaintext)
+                    .map(|ciphertext| cells_to_bytes(&ciphertext))
+            })
+            .collect::<Result<Vec<_>, AesError>>()?;
+
+        ciphertexts
+            .into_iter()
+            .collect::<Value<Vec<_>>>()
+            .assert_if_known(|ciphertexts| {
+                let distinct = ciphertexts.iter().collect::<std::collections::HashSet<_>>();
+                distinct.len() == plaintexts.len()
+            });
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_packed() {
+    let circuit = |packed: [Fp; 2]| {
+        keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+            // The packed cells as given by an upstream circuit
+            let packed = layouter.assign_region(
+                || "assign packed plaintext",
+                |mut region| {
+                    let cells = packed
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &packed)| {
+                            region.assign_advice(
+                                || "assign packed",
+                                aes.advices[0][2],
+                                i,
+                                || Value::known(packed),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    Ok([cells[0].clone(), cells[1].clone()])
+                },
+            )?;
+            let ciphertext = aes.encrypt_packed(cursor, layouter, &packed)?;
+            aes.expose_ciphertext(cursor, layouter, &ciphertext)?;
+
+            Ok(())
+        })
+    };
+
+    let instance = build_public_inputs(Some(FIPS_CIPHERTEXT), None, None);
+    let mock = MockProver::run(
+        17,
+        &circuit(pack_block(&FIPS_PLAINTEXT)),
+        vec![instance.clone()],
+    )
+    .unwrap();
+    mock.assert_satisfied();
+
+    // The high half packs more than 8 bytes: the same low 8 bytes plus 2^64
+    let [low, high] = pack_block(&FIPS_PLAINTEXT);
+    let circuit = circuit([low, high + Fp::from(u64::MAX) + Fp::from(1)]);
+    let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_related_keys() {
+    // Zero key and the FIPS-197 key 000102..0f as its related key
+    let circuit = keyed_circuit([0u8; 16], |aes, cursor, layouter| {
+        let (ciphertext, related_ciphertext) =
+            aes.encrypt_related_keys(cursor, layouter, FIPS_KEY, [0u8; 16])?;
+
+        cells_to_bytes(&ciphertext)
+            .assert_if_known(|bytes| bytes[..] == hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT));
+        cells_to_bytes(&related_ciphertext)
+            .assert_if_known(|bytes| bytes[..] == hex_to_block("c6a13b37878f5b826f4f8162a1c8d879"));
+        Ok(())
+    });
+    let mask = FIPS_KEY.iter().map(|&b| Fp::from(b as u64)).collect();
+
+    let mock = MockProver::run(17, &circuit, vec![mask]).unwrap();
+    mock.assert_satisfied();
+
+    // The mask should match the public one
+    let mock = MockProver::run(17, &circuit, vec![vec![Fp::from(0); 16]]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_same_plaintext() {
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        aes.encrypt_same_plaintext(cursor, layouter, [0u8; 16], FIPS_PLAINTEXT)?;
+
+        Ok(())
+    });
+    let other_ciphertext =
+        encrypt_block_reference(&key_schedule_reference([0u8; 16]), FIPS_PLAINTEXT);
+    let instance = [FIPS_CIPHERTEXT, other_ciphertext]
+        .iter()
+        .flat_map(|&ciphertext| build_public_inputs(Some(ciphertext), None, None))
+        .collect();
+    let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+    mock.assert_satisfied();
+
+    // The second ciphertext is of a different plaintext (zero), which the shared
+    // plaintext cells can't encrypt to
+    let instance = [
+        FIPS_CIPHERTEXT,
+        hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT),
+    ]
+    .iter()
+    .flat_map(|&ciphertext| build_public_inputs(Some(ciphertext), None, None))
+    .collect();
+    let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[derive(Clone)]
+struct TestInstanceColumnsCircuit {
+    column: usize,
+}
+
+impl Circuit<Fp> for TestInstanceColumnsCircuit {
+    type Config = FixedAes128Config<17, 2>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure_with_instances(meta, TableLayout::Combined, 2)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, FIPS_KEY)?;
+        let mut cursor = EncryptCursor::default();
+
+        // The first block in the first column, the next two in the other one
+        let output = aes.encrypt_with_group(&mut cursor, &mut layouter, FIPS_PLAINTEXT)?;
+        aes.expose_ciphertext_at(&mut cursor, &mut layouter, output.group, &output.cells)?;
+        for _ in 0..2 {
+            let ciphertext = aes.encrypt(&mut cursor, &mut layouter, [0u8; 16])?;
+            aes.expose_ciphertext_at(&mut cursor, &mut layouter, self.column, &ciphertext)?;
+        }
+        assert_eq!(cursor.instance_rows(), &[16, 32]);
+
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_instance_columns() {
+    let zero_ciphertext = encrypt_block_reference(&key_schedule_reference(FIPS_KEY), [0u8; 16]);
+    let instances = vec![
+        build_public_inputs(Some(FIPS_CIPHERTEXT), None, None),
+        [zero_ciphertext, zero_ciphertext]
+            .iter()
+            .flat_map(|block| build_public_inputs(Some(*block), None, None))
+            .collect(),
+    ];
+
+    let circuit = TestInstanceColumnsCircuit { column: 1 };
+    let mock = MockProver::run(17, &circuit, instances).unwrap();
+    mock.assert_satisfied();
+
+    // Only 2 instance columns
+    let result = MockProver::run(
+        17,
+        &TestInstanceColumnsCircuit { column: 2 },
+        vec![vec![], vec![]],
+    );
+    assert!(matches!(result, Err(Error::Synthesis)));
+}
+
+#[derive(Clone)]
+struct TestTagOffsetCircuit {
+    // Load the table without the offset of the chips
+    unshifted_table: bool,
+}
+
+impl Circuit<Fp> for TestTagOffsetCircuit {
+    type Config = FixedAes128Config<17, 1>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure_with_tag_offset(meta, 100)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        if self.unshifted_table {
+            load_enc_full_table(&mut layouter, config.tables.xor, 17)?;
+            config.tables_loaded = true;
+        } else {
+            config.load_tables(&mut layouter)?;
+        }
+        let aes = config.schedule_key(&mut layouter, FIPS_KEY)?;
+        let mut cursor = EncryptCursor::default();
+        let ciphertext = aes.encrypt(&mut cursor, &mut layouter, FIPS_PLAINTEXT)?;
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_tag_offset() {
+    let circuit = TestTagOffsetCircuit {
+        unshifted_table: false,
+    };
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    // The chips look up the shifted tags, which aren't in the unshifted table
+    let circuit = TestTagOffsetCircuit {
+        unshifted_table: true,
+    };
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_with_trace() {
+    let circuit = |trace: WitnessTrace| {
+        keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+            let ciphertext = aes.encrypt_with_trace(cursor, layouter, FIPS_PLAINTEXT, &trace)?;
+            aes.expose_ciphertext(cursor, layouter, &ciphertext)?;
+
+            Ok(())
+        })
+    };
+    let round_keys = key_schedule_reference(FIPS_KEY);
+    let trace = witness_trace_reference(&round_keys, FIPS_PLAINTEXT);
+    let instance = build_public_inputs(Some(FIPS_CIPHERTEXT), None, None);
+
+    let mock = MockProver::run(17, &circuit(trace.clone()), vec![instance.clone()]).unwrap();
+    mock.assert_satisfied();
+
+    // A wrong output in the middle of the trace isn't in the tables
+    let mut wrong = trace.clone();
+    wrong.0[WitnessTrace::LEN / 2] ^= 1;
+    let mock = MockProver::run(17, &circuit(wrong), vec![instance.clone()]).unwrap();
+    assert!(mock.verify().is_err());
+
+    // A trace of another length fails the synthesis
+    let mut short = trace;
+    short.0.pop();
+    let result = MockProver::run(17, &circuit(short), vec![instance]);
+    assert!(matches!(result, Err(Error::Synthesis)));
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_not_equal() {
+    let circuit = |target: [u8; 16]| {
+        keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+            aes.encrypt_not_equal(cursor, layouter, FIPS_PLAINTEXT, target)?;
+
+            Ok(())
+        })
+    };
+
+    let mut target = FIPS_CIPHERTEXT;
+    target[15] ^= 1;
+    let instance = build_public_inputs(Some(target), None, None);
+    let mock = MockProver::run(17, &circuit(target), vec![instance]).unwrap();
+    mock.assert_satisfied();
+
+    // The key does encrypt the plaintext to the ciphertext
+    let instance = build_public_inputs(Some(FIPS_CIPHERTEXT), None, None);
+    let mock = MockProver::run(17, &circuit(FIPS_CIPHERTEXT), vec![instance]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+// Computes the PRF of the input under the FIPS-197 key and exposes the output
+#[cfg(feature = "halo2-pse")]
+fn prf_circuit(input: [u8; 16], domain: u8) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+        let input = aes.assign_bytes(cursor, layouter, &input)?;
+        let output = aes.prf(cursor, layouter, &input, domain)?;
+        aes.expose_ciphertext(cursor, layouter, &output)
+    })
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_prf() {
+    // AES_k(input XOR (domain || 0^120))
+    let domain = 0x5a;
+    let mut tweaked = FIPS_PLAINTEXT;
+    tweaked[0] ^= domain;
+    let output = encrypt_block_reference(&key_schedule_reference(FIPS_KEY), tweaked);
+    let instance = build_public_inputs(Some(output), None, None);
+
+    let mock = MockProver::run(
+        17,
+        &prf_circuit(FIPS_PLAINTEXT, domain),
+        vec![instance.clone()],
+    )
+    .unwrap();
+    mock.assert_satisfied();
+
+    // The output isn't the PRF of the same input under another domain
+    let mock =
+        MockProver::run(17, &prf_circuit(FIPS_PLAINTEXT, domain ^ 1), vec![instance]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_expose_ciphertext_packed() {
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        let ciphertext = aes.encrypt(cursor, layouter, FIPS_PLAINTEXT)?;
+        let packed = aes.expose_ciphertext_packed(cursor, layouter, &ciphertext)?;
+
+        // A block is a single public input
+        assert_eq!(packed.len(), 1);
+        assert_eq!(cursor.instance_rows(), [1]);
+        Ok(())
+    });
+
+    let instance = pack_bytes(&FIPS_CIPHERTEXT);
+    // Unpacks to the ciphertext
+    assert_eq!(instance[0].to_bytes()[..16], FIPS_CIPHERTEXT);
+
+    let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+    mock.assert_satisfied();
+
+    let mut wrong = FIPS_CIPHERTEXT;
+    wrong[15] ^= 1;
+    let mock = MockProver::run(17, &circuit, vec![pack_bytes(&wrong)]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[cfg(feature = "poseidon")]
+#[derive(Clone)]
+struct TestHashCircuit {
+    key: [u8; 16],
+    plaintext: [u8; 16],
+}
+
+#[cfg(feature = "poseidon")]
+impl Circuit<Fp> for TestHashCircuit {
+    type Config = (FixedAes128Config<17, 1>, BlockHashConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (
+            FixedAes128Config::configure(meta),
+            BlockHashConfig::configure(meta),
+        )
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (mut config, hash_config) = config;
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, self.key)?;
+        let mut cursor = EncryptCursor::default();
+        let (_, d0) =
+            aes.encrypt_and_hash(&mut cursor, &mut layouter, &hash_config, self.plaintext)?;
+        let (_, d1) =
+            aes.encrypt_and_hash(&mut cursor, &mut layouter, &hash_config, self.plaintext)?;
+
+        d0.value()
+            .zip(d1.value())
+            .assert_if_known(|(d0, d1)| d0 == d1);
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+#[test]
+#[cfg(feature = "poseidon")]
+fn test_encrypt_and_hash() {
+    use crate::hash::hash_block;
+
+    let digest = hash_block(FIPS_CIPHERTEXT);
+    assert_eq!(digest, hash_block(FIPS_CIPHERTEXT));
+    assert_ne!(digest, hash_block(FIPS_PLAINTEXT));
+
+    let circuit = TestHashCircuit {
+        key: FIPS_KEY,
+        plaintext: FIPS_PLAINTEXT,
+    };
+    let mock = MockProver::run(17, &circuit, vec![vec![digest, digest]]).unwrap();
+    mock.assert_satisfied();
+
+    let wrong = hash_block(FIPS_PLAINTEXT);
+    let mock = MockProver::run(17, &circuit, vec![vec![digest, wrong]]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+// Decrypts the ciphertexts and exposes the plaintexts
+#[cfg(feature = "halo2-pse")]
+fn decrypt_circuit(
+    ciphertexts: Vec<[u8; 16]>,
+    plaintexts: Vec<[u8; 16]>,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+        for (&ciphertext, expected) in ciphertexts.iter().zip(&plaintexts) {
+            let plaintext = aes.decrypt(cursor, layouter, ciphertext)?;
+            cells_to_bytes(&plaintext).assert_if_known(|bytes| bytes[..] == expected[..]);
+            aes.expose_ciphertext(cursor, layouter, &plaintext)?;
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_decrypt() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    // FIPS-197 Appendix C.1, and the round trip of random blocks
+    let mut rng = StdRng::seed_from_u64(0);
+    let round_keys = key_schedule_reference(FIPS_KEY);
+    let mut plaintexts = vec![FIPS_PLAINTEXT];
+    plaintexts.extend((0..3).map(|_| rng.gen::<[u8; 16]>()));
+    let ciphertexts = plaintexts
+        .iter()
+        .map(|&plaintext| encrypt_block_reference(&round_keys, plaintext))
+        .collect::<Vec<_>>();
+    assert_eq!(ciphertexts[0], FIPS_CIPHERTEXT);
+
+    let instance = plaintexts
+        .concat()
+        .iter()
+        .map(|&b| Fp::from(b as u64))
+        .collect::<Vec<_>>();
+    let circuit = decrypt_circuit(ciphertexts, plaintexts);
+    let mock = MockProver::run(17, &circuit, vec![instance.clone()]).unwrap();
+    mock.assert_satisfied();
+
+    // A plaintext byte off by one fails
+    let mut wrong = instance.clone();
+    wrong[16] += Fp::from(1);
+    let mock = MockProver::run(17, &circuit, vec![wrong]).unwrap();
+    assert!(mock.verify().is_err());
+
+    // So does a wrong inverse S-box, the first 16 outputs, or InvMixColumns product
+    for index in [0, 16] {
+        let mock = crate::fault::with_fault(crate::fault::FaultSite::InvCipher, index, || {
+            MockProver::run(17, &circuit, vec![instance.clone()]).unwrap()
+        });
+        assert!(mock.verify().is_err());
+    }
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_decrypt_rows() {
+    let circuit = decrypt_circuit(vec![FIPS_CIPHERTEXT], vec![FIPS_PLAINTEXT]);
+    let instance = FIPS_PLAINTEXT.iter().map(|&b| Fp::from(b as u64)).collect();
+    let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+    mock.assert_satisfied();
+
+    // The capacity of the group relies on this
+    let rows = crate::dev::used_rows_per_advice(&mock);
+    assert_eq!(rows[0] as u64, KEY_SCHEDULE_ROWS + DECRYPT_ROWS);
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_first_column_rows() {
+    let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+        let ciphertext = aes.encrypt(cursor, layouter, FIPS_PLAINTEXT)?;
+
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    // The block directly follows the key schedule, no row is left unused in between
+    let rows = crate::dev::used_rows_per_advice(&mock);
+    assert_eq!(rows[0] as u64, KEY_SCHEDULE_ROWS + AES_ROWS);
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_shared_round_keys() {
+    let circuit = test_circuit(|config, layouter| {
+        let aes = config.clone().schedule_key(layouter, FIPS_KEY)?;
+        let shared = config.set_round_keys(aes.take_round_keys());
+
+        let mut cursor = EncryptCursor::default();
+        for aes in [&aes, &shared] {
+            let ciphertext = aes.encrypt(&mut cursor, layouter, FIPS_PLAINTEXT)?;
+            cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+        }
+        // Same cells, so the key schedule is accounted once
+        assert_eq!(cursor.key_schedules.len(), 1);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    // The key isn't scheduled again for the second encryption
+    let rows = crate::dev::used_rows_per_advice(&mock);
+    assert_eq!(rows[0] as u64, KEY_SCHEDULE_ROWS + 2 * AES_ROWS);
+}
+
+// Key: 1, each of the 10 words with RotWord: rotation 1, SubWord 4, round constant 1
+// and 8 XORs, each of the 30 other words: 4 XORs, and the range check of the words: 1
+const KEY_SCHEDULE_REGIONS: usize = 1 + 10 * (1 + 4 + 1 + 8) + 30 * 4 + 1;
+// Plaintext: 1, initial AddRoundKey: 1, each of the 9 middle rounds: 16 S-boxes,
+// 16 MixColumns bytes of 4 products and 3 XORs and 16 XORs of AddRoundKey,
+// and the last round: 16 S-boxes and 16 XORs
+const ENCRYPT_REGIONS: usize = 1 + 1 + 9 * (16 + 16 * 7 + 16) + 16 + 16;
+
+// Flags a change of the number of regions, e.g. a chip assigning a region per byte
+// instead of one for all. Update the baselines if the change is intended.
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_region_counts() {
+    let circuit = test_circuit(|config, layouter| {
+        let mut counter = crate::dev::RegionCounter::new(layouter);
+
+        let aes = config.schedule_key(&mut counter, FIPS_KEY)?;
+        assert_eq!(counter.regions(), KEY_SCHEDULE_REGIONS);
+
+        counter.reset();
+        let mut cursor = EncryptCursor::default();
+        aes.encrypt(&mut cursor, &mut counter, FIPS_PLAINTEXT)?;
+        assert_eq!(counter.regions(), ENCRYPT_REGIONS);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_set_active_group() {
+    let circuit = TestCircuit::<17, 3, _>::new(|config, layouter| {
+        let aes = config.schedule_key(layouter, FIPS_KEY)?;
+        let mut cursor = EncryptCursor::default();
+
+        aes.set_active_group(&mut cursor, 2)?;
+        let ciphertext = aes.encrypt(&mut cursor, layouter, FIPS_PLAINTEXT)?;
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+
+        // Neither back nor out of the groups
+        for group in [0, 2, 3] {
+            assert!(matches!(
+                aes.set_active_group(&mut cursor, group),
+                Err(AesError::InvalidGroup {
+                    current: 2,
+                    n: 3,
+                    ..
+                })
+            ));
+        }
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    // The block is in the last group, the second one is unused
+    let rows = crate::dev::used_rows_per_advice(&mock);
+    assert_eq!(rows[0] as u64, KEY_SCHEDULE_ROWS);
+    assert_eq!(rows[3], 0);
+    assert_eq!(rows[6] as u64, AES_ROWS);
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_encrypt_with_group() {
+    let circuit = TestCircuit::<17, 2, _>::new(|config, layouter| {
+        let aes = config.schedule_key(layouter, FIPS_KEY)?;
+        let mut cursor = EncryptCursor::default();
+
+        // The first group is full after these blocks
+        let first_group = CompactAes128Config::<17>::max_aes_calls();
+        for _ in 0..first_group {
+            let output = aes.encrypt_with_group(&mut cursor, layouter, FIPS_PLAINTEXT)?;
+            assert_eq!(output.group, 0);
+        }
+
+        let output = aes.encrypt_with_group(&mut cursor, layouter, FIPS_PLAINTEXT)?;
+        assert_eq!(output.group, 1);
+        cells_to_bytes(&output.cells).assert_if_known(|bytes| bytes[..] == FIPS_CIPHERTEXT);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+fn test_max_aes_calls() {
+    assert_eq!(CompactAes128Config::<17>::max_aes_calls(), 96);
+    assert_eq!(CompactAes128Config::<18>::max_aes_calls(), 192);
+    assert_eq!(FixedAes128Config::<18, 3>::max_aes_calls(), 192 + 2 * 192);
+}
+
+#[test]
+fn test_validate_params() {
+    assert!(validate_params(17, 1, 96).is_ok());
+    assert!(validate_params(18, 3, 576).is_ok());
+
+    let errors = validate_params(17, 1, 97).unwrap_err();
+    assert!(matches!(
+        errors[..],
+        [AesError::TooManyBlocks {
+            num_blocks: 97,
+            max: 96
+        }]
+    ));
+
+    // All the violations at once
+    let errors = validate_params(8, 0, 1).unwrap_err();
+    assert!(matches!(
+        errors[..],
+        [
+            AesError::NoGroups,
+            AesError::TableTooLargeForK { needed: 17, k: 8 },
+            AesError::KeyScheduleTooLargeForK { k: 8, .. },
+            AesError::TooManyBlocks {
+                num_blocks: 1,
+                max: 0
+            },
+        ]
+    ));
+
+    // Same as the config
+    let mut meta = ConstraintSystem::<Fp>::default();
+    let config = FixedAes128Config::<17, 2>::configure(&mut meta);
+    assert!(config.validate(192).is_ok());
+    assert!(config.validate(193).is_err());
+}
+
+#[test]
+fn test_recommended_k() {
+    // Table alone needs 2^17 rows
+    assert_eq!(recommended_k(0, 1), 17);
+    assert_eq!(recommended_k(1, 1), 17);
+    assert_eq!(recommended_k(96, 1), 17);
+    assert_eq!(recommended_k(97, 1), 18);
+    assert_eq!(recommended_k(192, 1), 18);
+    assert_eq!(recommended_k(193, 1), 19);
+    assert_eq!(recommended_k(96, 2), 17);
+    assert_eq!(recommended_k(1000, 3), 19);
+    assert_eq!(recommended_k(1000, 1), 21);
+
+    for (num_blocks, n) in [(1, 1), (500, 2), (2000, 4)] {
+        let k = recommended_k(num_blocks, n);
+        assert!(max_aes_calls(k, n) >= num_blocks);
+        assert!(k == 17 || max_aes_calls(k - 1, n) < num_blocks);
+    }
+}
+
+#[test]
+#[cfg(feature = "halo2-pse")]
+fn test_compact_layout() {
+    let circuit = TestCircuit::<18, 1, _>::new(|config, layouter| {
+        let aes = config.schedule_key(layouter, [0u8; 16])?;
+        let mut cursor = EncryptCursor::default();
+        // Fill the single column up to the capacity
+        for _ in 0..CompactAes128Config::<18>::max_aes_calls() {
+            let ciphertext = aes.encrypt(&mut cursor, layouter, [0u8; 16])?;
+            cells_to_bytes(&ciphertext).assert_if_known(|bytes| {
+                bytes[..] == hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT)
+            });
+        }
+        Ok(())
+    });
+
+    let mock = MockProver::run(18, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[cfg(all(feature = "halo2-pse", feature = "dev-graph"))]
+#[test]
+fn print_aes_encrypt() {
+    use plotters::prelude::*;
+    let circuit = aes_circuit([0u8; 16], [0u8; 16]);
+
+    let root = BitMapBackend::new("prints/aes128-layout.png", (2048, 32768)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+    let root = root
+        .titled("AES128 Key schedule circuit", ("sans-serif", 60))
+        .unwrap();
+
+    halo2_proofs::dev::CircuitLayout::default()
+        .render(K, &circuit, &root)
+        .unwrap();
+}
+
+#[cfg(all(feature = "halo2-pse", feature = "cost-estimator"))]
+#[test]
+fn cost_estimate_aes_encrypt() {
+    use halo2_proofs::dev::cost_model::{from_circuit_to_model_circuit, CommitmentScheme};
+    let circuit = aes_circuit([0u8; 16], [0u8; 16]);
+
+    let model = from_circuit_to_model_circuit::<_, _, 56, 56>(
+        K,
+        &circuit,
+        vec![vec![]],
+        CommitmentScheme::KZGGWC,
+    );
+    println!(
+        "Cost of AES128 Encryption: \n{}",
+        serde_json::to_string_pretty(&model).unwrap()
+    );
+
+    let mock = MockProver::run(K, &circuit, vec![vec![]]).unwrap();
+    println!(
+        "Used rows per advice column: {:?} (AES_ROWS: {}, KEY_SCHEDULE_ROWS: {})",
+        crate::dev::used_rows_per_advice(&mock),
+        AES_ROWS,
+        KEY_SCHEDULE_ROWS
+    );
+}
+
+#[cfg(feature = "poseidon")]
+#[derive(Clone)]
+struct TestBatchCommittedCircuit {
+    key: [u8; 16],
+    plaintexts: Vec<[u8; 16]>,
+    expected: Vec<[u8; 16]>,
+}
+
+#[cfg(feature = "poseidon")]
+impl Circuit<Fp> for TestBatchCommittedCircuit {
+    type Config = (FixedAes128Config<17, 1>, BlockHashConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (
+            FixedAes128Config::configure(meta),
+            BlockHashConfig::configure(meta),
+        )
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (mut config, hash_config) = config;
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, self.key)?;
+        let mut cursor = EncryptCursor::default();
+        let ciphertexts = aes.batch_encrypt_committed(
+            &mut cursor,
+            &mut layouter,
+            &hash_config,
+            &self.plaintexts,
+        )?;
+
+        assert_eq!(ciphertexts.len(), self.plaintexts.len());
+        for (ciphertext, expected) in ciphertexts.iter().zip(&self.expected) {
+            cells_to_bytes(ciphertext).assert_if_known(|bytes| bytes[..] == expected[..]);
+        }
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+#[test]
+#[cfg(feature = "poseidon")]
+fn test_batch_encrypt_committed() {
+    use crate::hash::batch_public_inputs;
+    use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+    use aes::Aes128;
+
+    let plaintexts = vec![FIPS_PLAINTEXT, [0u8; 16], [0xffu8; 16]];
+    let cipher = Aes128::new(&GenericArray::from(FIPS_KEY));
+    let expected = plaintexts
+        .iter()
+        .map(|&plaintext| {
+            let mut block = GenericArray::from(plaintext);
+            cipher.encrypt_block(&mut block);
+            let mut ciphertext = [0u8; 16];
+            ciphertext.copy_from_slice(&block);
+            ciphertext
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(expected[0], FIPS_CIPHERTEXT);
+
+    // The key commitment, then the 16 bytes of each ciphertext
+    let instance = batch_public_inputs(FIPS_KEY, &expected);
+    assert_eq!(instance.len(), 1 + 16 * plaintexts.len());
+
+    let circuit = TestBatchCommittedCircuit {
+        key: FIPS_KEY,
+        plaintexts,
+        expected: expected.clone(),
+    };
+    let mock = MockProver::run(17, &circuit, vec![instance.clone()]).unwrap();
+    mock.assert_satisfied();
+
+    // Committed to another key
+    let wrong = batch_public_inputs([0u8; 16], &expected);
+    let mock = MockProver::run(17, &circuit, vec![wrong]).unwrap();
+    assert!(mock.verify().is_err());
+
+    // A ciphertext byte off by one
+    let mut wrong = instance;
+    wrong[1 + 16 + 5] += Fp::from(1);
+    let mock = MockProver::run(17, &circuit, vec![wrong]).unwrap();
+    assert!(mock.verify().is_err());
+}
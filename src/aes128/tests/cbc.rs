@@ -0,0 +1,143 @@
+use super::{
+    build_public_inputs,
+    harness::{keyed_circuit, Synthesize, TestCircuit},
+    hex_to_block, FIPS_KEY, FIPS_PLAINTEXT, NIST_KEY, ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT,
+};
+use crate::{
+    constant::KEY_SCHEDULE_ROWS,
+    halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr as Fp},
+    reference::{encrypt_block_reference, key_schedule_reference},
+    utils::cells_to_bytes,
+};
+
+#[test]
+fn test_cbc_encrypt() {
+    // NIST SP 800-38A F.2.1 CBC-AES128.Encrypt, the first 2 blocks
+    let plaintext = [
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .collect::<Vec<_>>();
+    let expected = [
+        "7649abac8119b246cee98e9b12e9197d",
+        "5086cb9b507219ee95db113a917678b2",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .collect::<Vec<_>>();
+    let iv = hex_to_block("000102030405060708090a0b0c0d0e0f");
+
+    let circuit = keyed_circuit(NIST_KEY, |aes, cursor, layouter| {
+        let ciphertext = aes.cbc_encrypt(cursor, layouter, iv, &plaintext)?;
+
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| *bytes == expected);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+// Encrypts the blocks under the NIST key, exposing the IV if public
+fn encrypt_cbc_circuit(
+    iv: [u8; 16],
+    blocks: Vec<[u8; 16]>,
+    expected: Vec<[u8; 16]>,
+    public_iv: bool,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(NIST_KEY, move |aes, cursor, layouter| {
+        let ciphertext = aes.encrypt_cbc(cursor, layouter, iv, &blocks, public_iv)?;
+
+        assert_eq!(ciphertext.len(), expected.len());
+        for (block, expected) in ciphertext.iter().zip(&expected) {
+            cells_to_bytes(block).assert_if_known(|bytes| bytes[..] == expected[..]);
+        }
+        let exposed: &[usize] = if public_iv && !blocks.is_empty() {
+            &[16]
+        } else {
+            &[]
+        };
+        assert_eq!(cursor.instance_rows(), exposed);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_encrypt_cbc() {
+    // NIST SP 800-38A F.2.1 CBC-AES128.Encrypt, the first 2 blocks
+    let iv = hex_to_block("000102030405060708090a0b0c0d0e0f");
+    let blocks = [
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+    ]
+    .map(hex_to_block);
+    let expected = [
+        "7649abac8119b246cee98e9b12e9197d",
+        "5086cb9b507219ee95db113a917678b2",
+    ]
+    .map(hex_to_block);
+    let circuit =
+        |public_iv| encrypt_cbc_circuit(iv, blocks.to_vec(), expected.to_vec(), public_iv);
+
+    let mock = MockProver::run(17, &circuit(false), vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    let instance = iv.iter().map(|&b| Fp::from(b as u64)).collect::<Vec<_>>();
+    let mock = MockProver::run(17, &circuit(true), vec![instance.clone()]).unwrap();
+    mock.assert_satisfied();
+
+    // The proof is bound to the public IV
+    let mut wrong = instance;
+    wrong[15] += Fp::from(1);
+    let mock = MockProver::run(17, &circuit(true), vec![wrong]).unwrap();
+    assert!(mock.verify().is_err());
+}
+
+#[test]
+fn test_encrypt_cbc_empty() {
+    let circuit = encrypt_cbc_circuit([0u8; 16], vec![], vec![], true);
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+
+    // Nothing is assigned after the key schedule, not even the IV
+    let rows = crate::dev::used_rows_per_advice(&mock);
+    assert_eq!(rows[0] as u64, KEY_SCHEDULE_ROWS);
+}
+
+#[test]
+fn test_cbc_mac() {
+    let circuit = |message: [[u8; 16]; 3]| {
+        keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+            let tag = aes.cbc_mac(cursor, layouter, &message)?;
+            aes.expose_ciphertext(cursor, layouter, &tag)?;
+
+            Ok(())
+        })
+    };
+    let message = [
+        FIPS_PLAINTEXT,
+        [0xa5; 16],
+        hex_to_block(ZERO_KEY_ZERO_PLAINTEXT_CIPHERTEXT),
+    ];
+
+    // Reference CBC-MAC: chain the blocks from the zero IV
+    let round_keys = key_schedule_reference(FIPS_KEY);
+    let tag = message.iter().fold([0u8; 16], |prev, block| {
+        let chained = std::array::from_fn(|i| prev[i] ^ block[i]);
+        encrypt_block_reference(&round_keys, chained)
+    });
+
+    let instance = build_public_inputs(Some(tag), None, None);
+    let mock = MockProver::run(17, &circuit(message), vec![instance]).unwrap();
+    mock.assert_satisfied();
+
+    // The tag of a different message doesn't verify
+    let mut forged = message;
+    forged[1][0] ^= 1;
+    let instance = build_public_inputs(Some(tag), None, None);
+    let mock = MockProver::run(17, &circuit(forged), vec![instance]).unwrap();
+    assert!(mock.verify().is_err());
+}
@@ -0,0 +1,279 @@
+use super::{
+    harness::{keyed_circuit, Synthesize, TestCircuit},
+    hex_to_block, FIPS_KEY, NIST_KEY,
+};
+#[cfg(feature = "poseidon")]
+use super::{EncryptCursor, FixedAes128Config};
+use crate::{
+    halo2_proofs::dev::MockProver,
+    reference::{encrypt_block_reference, key_schedule_reference},
+    utils::cells_to_bytes,
+};
+#[cfg(feature = "poseidon")]
+use crate::{
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+    hash::BlockHashConfig,
+};
+
+fn ctr_circuit(
+    key: [u8; 16],
+    iv: [u8; 16],
+    plaintext: Vec<u8>,
+    expected: Vec<u8>,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(key, move |aes, cursor, layouter| {
+        let ciphertext = aes.ctr_encrypt(cursor, layouter, iv, &plaintext)?;
+
+        // One cell per byte of the message, and no keystream is exposed
+        assert_eq!(ciphertext.len(), plaintext.len());
+        assert!(cursor.instance_rows().is_empty());
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| *bytes == expected);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_ctr_encrypt() {
+    // NIST SP 800-38A F.5.1 CTR-AES128.Encrypt, the last block truncated to 4 bytes
+    let plaintext = [
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+        "30c81c46a35ce411e5fbc1191a0a52ef",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .take(36)
+    .collect::<Vec<_>>();
+    let expected = [
+        "874d6191b620e3261bef6864990db6ce",
+        "9806f66b7970fdff8617187bb9fffdff",
+        "5ae4df3edbd5d35e5b4f09020db03eab",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .take(36)
+    .collect::<Vec<_>>();
+
+    let circuit = ctr_circuit(
+        NIST_KEY,
+        hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"),
+        plaintext,
+        expected,
+    );
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+fn test_ctr_encrypt_partial_block() {
+    // NIST SP 800-38A F.5.1 CTR-AES128.Encrypt, one full block and 4 bytes
+    let plaintext = [
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .take(20)
+    .collect::<Vec<_>>();
+    let expected = [
+        "874d6191b620e3261bef6864990db6ce",
+        "9806f66b7970fdff8617187bb9fffdff",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .take(20)
+    .collect::<Vec<_>>();
+    assert_eq!(expected.len(), 20);
+
+    let circuit = ctr_circuit(
+        NIST_KEY,
+        hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff"),
+        plaintext,
+        expected,
+    );
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+fn test_ctr_encrypt_empty() {
+    let circuit = ctr_circuit(FIPS_KEY, [0u8; 16], vec![], vec![]);
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+// Encrypts under the NIST key with the 32 bit counter
+fn encrypt_ctr_circuit(
+    nonce: [u8; 12],
+    initial_counter: u32,
+    plaintext: Vec<u8>,
+    expected: Vec<u8>,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(NIST_KEY, move |aes, cursor, layouter| {
+        let ciphertext = aes.encrypt_ctr(cursor, layouter, nonce, initial_counter, &plaintext)?;
+
+        assert_eq!(ciphertext.len(), plaintext.len());
+        cells_to_bytes(&ciphertext).assert_if_known(|bytes| *bytes == expected);
+        Ok(())
+    })
+}
+
+// CTR reference with the 32 bit counter, independent of `CounterMode`
+fn ctr32_reference(key: [u8; 16], nonce: [u8; 12], counter: u32, plaintext: &[u8]) -> Vec<u8> {
+    let round_keys = key_schedule_reference(key);
+    plaintext
+        .chunks(16)
+        .enumerate()
+        .flat_map(|(i, chunk)| {
+            let mut block = [0u8; 16];
+            block[..12].copy_from_slice(&nonce);
+            block[12..].copy_from_slice(&counter.wrapping_add(i as u32).to_be_bytes());
+            let keystream = encrypt_block_reference(&round_keys, block);
+            chunk
+                .iter()
+                .zip(keystream)
+                .map(|(p, k)| p ^ k)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[test]
+fn test_encrypt_ctr() {
+    // NIST SP 800-38A F.5.1 CTR-AES128.Encrypt, the last block truncated to 4 bytes,
+    // with the initial counter block f0f1..fb || fcfdfeff
+    let plaintext = [
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+        "30c81c46a35ce411e5fbc1191a0a52ef",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .take(36)
+    .collect::<Vec<_>>();
+    let expected = [
+        "874d6191b620e3261bef6864990db6ce",
+        "9806f66b7970fdff8617187bb9fffdff",
+        "5ae4df3edbd5d35e5b4f09020db03eab",
+    ]
+    .iter()
+    .flat_map(|hex| hex_to_block(hex))
+    .take(36)
+    .collect::<Vec<_>>();
+    let nonce: [u8; 12] = hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff")[..12]
+        .try_into()
+        .unwrap();
+    assert_eq!(
+        ctr32_reference(NIST_KEY, nonce, 0xfcfdfeff, &plaintext),
+        expected
+    );
+
+    let circuit = encrypt_ctr_circuit(nonce, 0xfcfdfeff, plaintext, expected);
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+fn test_encrypt_ctr_counter_wraps() {
+    // The counter wraps around to 0 without carrying into the nonce
+    let nonce = [0xffu8; 12];
+    let plaintext = (0..40).collect::<Vec<u8>>();
+    let expected = ctr32_reference(NIST_KEY, nonce, u32::MAX, &plaintext);
+    // The full 128 bit increment would carry into the nonce, giving another keystream
+    let carried = ctr32_reference(NIST_KEY, [0u8; 12], 0, &plaintext[16..]);
+    assert_ne!(expected[16..], carried[..]);
+
+    let circuit = encrypt_ctr_circuit(nonce, u32::MAX, plaintext, expected);
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[test]
+fn test_encrypt_ctr_empty() {
+    let circuit = encrypt_ctr_circuit([0u8; 12], 0, vec![], vec![]);
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
+
+#[cfg(feature = "poseidon")]
+#[derive(Clone)]
+struct TestKeystreamCommitmentCircuit {
+    iv: [u8; 16],
+    num_blocks: usize,
+}
+
+#[cfg(feature = "poseidon")]
+impl Circuit<Fp> for TestKeystreamCommitmentCircuit {
+    type Config = (FixedAes128Config<17, 1>, BlockHashConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        (
+            FixedAes128Config::configure(meta),
+            BlockHashConfig::configure(meta),
+        )
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let (mut config, hash_config) = config;
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, FIPS_KEY)?;
+        let mut cursor = EncryptCursor::default();
+        let keystream = aes.ctr_commit_keystream(
+            &mut cursor,
+            &mut layouter,
+            &hash_config,
+            self.iv,
+            self.num_blocks,
+        )?;
+
+        cells_to_bytes(&keystream).assert_if_known(|bytes| bytes.len() == self.num_blocks * 16);
+        Ok(())
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+#[test]
+#[cfg(feature = "poseidon")]
+fn test_ctr_commit_keystream() {
+    use crate::{ctr_chain::add_counter, hash::keystream_commitments};
+
+    // Keystream computed out of the circuit
+    let iv = hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+    let round_keys = key_schedule_reference(FIPS_KEY);
+    let keystream = (0..2)
+        .flat_map(|i| encrypt_block_reference(&round_keys, add_counter(iv, i)))
+        .collect::<Vec<_>>();
+
+    let public_inputs = |keystream: &[u8]| {
+        iv.iter()
+            .map(|&b| Fp::from(b as u64))
+            .chain(keystream_commitments(keystream))
+            .collect::<Vec<_>>()
+    };
+    let circuit = TestKeystreamCommitmentCircuit { iv, num_blocks: 2 };
+
+    let mock = MockProver::run(17, &circuit, vec![public_inputs(&keystream)]).unwrap();
+    mock.assert_satisfied();
+
+    // A revealed keystream differing from the committed one is rejected
+    let mut wrong = keystream.clone();
+    wrong[17] ^= 1;
+    let mock = MockProver::run(17, &circuit, vec![public_inputs(&wrong)]).unwrap();
+    assert!(mock.verify().is_err());
+}
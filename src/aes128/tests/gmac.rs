@@ -0,0 +1,53 @@
+use super::{
+    harness::{keyed_circuit, test_circuit},
+    hex_to_block, EncryptCursor, FIPS_KEY,
+};
+use crate::{halo2_proofs::dev::MockProver, utils::cells_to_bytes};
+
+#[test]
+fn test_gmac() {
+    // Tags of AES-128-GCM with the empty plaintext, computed by an independent GCM implementation
+    let nonce = [
+        0xca, 0xfe, 0xba, 0xbe, 0xfa, 0xce, 0xdb, 0xad, 0xde, 0xca, 0xf8, 0x88,
+    ];
+    let cases = [
+        (vec![], "a945054aec8b8f4e4bdfe17f0557f09a"),
+        // Not a multiple of 16 bytes
+        (
+            vec![
+                0xfe, 0xed, 0xfa, 0xce, 0xde, 0xad, 0xbe, 0xef, 0xfe, 0xed, 0xfa, 0xce, 0xde, 0xad,
+                0xbe, 0xef, 0xab, 0xad, 0xda, 0xd2,
+            ],
+            "c80ee24815fc8229a2c0ccc3200e473d",
+        ),
+        ((0..32).collect(), "ce729053f8727ccfc80d15dc273b4925"),
+    ];
+
+    for (aad, tag) in cases {
+        let expected = hex_to_block(tag);
+        let circuit = keyed_circuit(FIPS_KEY, |aes, cursor, layouter| {
+            let tag = aes.gmac(cursor, layouter, nonce, &aad)?;
+
+            cells_to_bytes(&tag).assert_if_known(|bytes| bytes[..] == expected);
+            Ok(())
+        });
+
+        let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+        mock.assert_satisfied();
+    }
+}
+
+#[test]
+fn test_ghash_empty() {
+    let circuit = test_circuit(|config, layouter| {
+        let mut cursor = EncryptCursor::default();
+        let h = config.assign_bytes(&cursor, layouter, &FIPS_KEY)?;
+        let y = config.ghash(&mut cursor, layouter, &h, &[])?;
+
+        cells_to_bytes(&y).assert_if_known(|bytes| bytes[..] == [0u8; 16]);
+        Ok(())
+    });
+
+    let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+    mock.assert_satisfied();
+}
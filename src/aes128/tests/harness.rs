@@ -0,0 +1,219 @@
+//! Circuit shared by the tests of `FixedAes128Config`, laying out whatever the closure
+//! of the test does, instead of a `Circuit` implementation per test.
+
+use crate::{
+    aes128::{Aes128Scheduled, EncryptCursor, FixedAes128Config},
+    halo2_proofs::{
+        circuit::{Cell, Layouter, Region, SimpleFloorPlanner, Table, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Challenge, Circuit, Column, ConstraintSystem, Error, Instance},
+    },
+};
+
+/// Synthesis of a `TestCircuit`, given the config with the tables loaded.
+pub(super) trait Synthesize<const K: u32, const N: usize>:
+    Fn(FixedAes128Config<K, N>, &mut DynLayouter<'_>) -> Result<(), Error>
+{
+}
+
+impl<const K: u32, const N: usize, F> Synthesize<K, N> for F where
+    F: Fn(FixedAes128Config<K, N>, &mut DynLayouter<'_>) -> Result<(), Error>
+{
+}
+
+/// `FixedAes128Config<K, N>` with the combined lookup table, loaded before calling
+/// the closure. The configs that differ, e.g. in the table layout, have their own circuit.
+pub(super) struct TestCircuit<const K: u32, const N: usize, F> {
+    synthesize: F,
+}
+
+impl<const K: u32, const N: usize, F> TestCircuit<K, N, F> {
+    pub(super) fn new(synthesize: F) -> Self
+    where
+        F: Fn(FixedAes128Config<K, N>, &mut DynLayouter<'_>) -> Result<(), Error>,
+    {
+        Self { synthesize }
+    }
+}
+
+impl<const K: u32, const N: usize, F: Synthesize<K, N>> Circuit<Fp> for TestCircuit<K, N, F> {
+    type Config = FixedAes128Config<K, N>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)?;
+        (self.synthesize)(config, &mut DynLayouter::new(&mut layouter))
+    }
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+}
+
+/// `TestCircuit` of the config used by most tests, `CompactAes128Config<17>`.
+pub(super) fn test_circuit<F>(synthesize: F) -> TestCircuit<17, 1, F>
+where
+    F: Fn(FixedAes128Config<17, 1>, &mut DynLayouter<'_>) -> Result<(), Error>,
+{
+    TestCircuit::new(synthesize)
+}
+
+/// `test_circuit` calling the closure with the key scheduled and a new cursor.
+pub(super) fn keyed_circuit<F>(
+    key: [u8; 16],
+    synthesize: F,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>>
+where
+    F: Fn(&Aes128Scheduled<17, 1>, &mut EncryptCursor, &mut DynLayouter<'_>) -> Result<(), Error>,
+{
+    test_circuit(move |config, layouter| {
+        let aes = config.schedule_key(layouter, key)?;
+        let mut cursor = EncryptCursor::default();
+        synthesize(&aes, &mut cursor, layouter)
+    })
+}
+
+// The object safe part of `Layouter`, so that `DynLayouter` can hold any layouter
+trait ErasedLayouter {
+    fn assign_region(
+        &mut self,
+        name: String,
+        assignment: &mut dyn FnMut(Region<'_, Fp>) -> Result<(), Error>,
+    ) -> Result<(), Error>;
+
+    fn assign_table(
+        &mut self,
+        name: String,
+        assignment: &mut dyn FnMut(Table<'_, Fp>) -> Result<(), Error>,
+    ) -> Result<(), Error>;
+
+    fn constrain_instance(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error>;
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<Fp>;
+
+    fn push_namespace(&mut self, name: String);
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>);
+}
+
+impl<L: Layouter<Fp>> ErasedLayouter for L {
+    fn assign_region(
+        &mut self,
+        name: String,
+        assignment: &mut dyn FnMut(Region<'_, Fp>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        Layouter::assign_region(self, || name.clone(), assignment)
+    }
+
+    fn assign_table(
+        &mut self,
+        name: String,
+        assignment: &mut dyn FnMut(Table<'_, Fp>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        Layouter::assign_table(self, || name.clone(), assignment)
+    }
+
+    fn constrain_instance(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error> {
+        Layouter::constrain_instance(self, cell, column, row)
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<Fp> {
+        Layouter::get_challenge(self, challenge)
+    }
+
+    fn push_namespace(&mut self, name: String) {
+        Layouter::push_namespace(self.get_root(), || name)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        Layouter::pop_namespace(self.get_root(), gadget_name)
+    }
+}
+
+/// Layouter passed to the closure of `TestCircuit`, forwarding to the layouter
+/// of the floor planner. Closures can't be generic over `impl Layouter<Fp>`,
+/// so they take this single type instead.
+pub(super) struct DynLayouter<'a> {
+    inner: &'a mut dyn ErasedLayouter,
+}
+
+impl<'a> DynLayouter<'a> {
+    fn new(inner: &'a mut impl Layouter<Fp>) -> Self {
+        Self { inner }
+    }
+}
+
+impl Layouter<Fp> for DynLayouter<'_> {
+    type Root = Self;
+
+    fn assign_region<A, AR, N, NR>(&mut self, name: N, mut assignment: A) -> Result<AR, Error>
+    where
+        A: FnMut(Region<'_, Fp>) -> Result<AR, Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        // The floor planner may run the assignment more than once, the last result is kept
+        let mut result = None;
+        self.inner.assign_region(name().into(), &mut |region| {
+            result = Some(assignment(region)?);
+            Ok(())
+        })?;
+        Ok(result.expect("the region is assigned"))
+    }
+
+    fn assign_table<A, N, NR>(&mut self, name: N, mut assignment: A) -> Result<(), Error>
+    where
+        A: FnMut(Table<'_, Fp>) -> Result<(), Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        self.inner.assign_table(name().into(), &mut assignment)
+    }
+
+    fn constrain_instance(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error> {
+        self.inner.constrain_instance(cell, column, row)
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<Fp> {
+        self.inner.get_challenge(challenge)
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.push_namespace(name_fn().into())
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.inner.pop_namespace(gadget_name)
+    }
+}
@@ -0,0 +1,122 @@
+use super::{
+    harness::{keyed_circuit, Synthesize, TestCircuit},
+    FIPS_KEY,
+};
+use crate::{
+    constant::{AES_ROWS, DECRYPT_ROWS, KEY_SCHEDULE_ROWS},
+    halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr as Fp, plonk::Error},
+    reference::{key_schedule_reference, key_wrap_reference},
+    utils::cells_to_bytes,
+};
+
+fn hex_to_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len() / 2)
+        .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+        .collect()
+}
+
+// Wraps the key data, then unwraps the wrapped key with the byte `tampered` flipped
+// instead if set, leaving the key data unexposed
+fn key_wrap_circuit(
+    key_data: Vec<u8>,
+    expected: Vec<u8>,
+    tampered: Option<usize>,
+) -> TestCircuit<17, 1, impl Synthesize<17, 1>> {
+    keyed_circuit(FIPS_KEY, move |aes, cursor, layouter| {
+        let wrapped = aes.key_wrap(cursor, layouter, &key_data)?;
+        cells_to_bytes(&wrapped).assert_if_known(|bytes| *bytes == expected);
+        aes.expose_ciphertext(cursor, layouter, &wrapped)?;
+
+        match tampered {
+            None => {
+                let unwrapped = aes.key_unwrap_cells(cursor, layouter, &wrapped)?;
+                cells_to_bytes(&unwrapped).assert_if_known(|bytes| *bytes == key_data);
+                aes.expose_ciphertext(cursor, layouter, &unwrapped)?;
+            }
+            Some(i) => {
+                let mut tampered = expected.clone();
+                tampered[i] ^= 1;
+                aes.key_unwrap(cursor, layouter, &tampered)?;
+            }
+        }
+
+        // Key data of a single block is rejected before assigning anything
+        let count = cursor.count;
+        assert!(matches!(
+            aes.key_wrap(cursor, layouter, &key_data[..8]),
+            Err(Error::Synthesis)
+        ));
+        assert!(matches!(
+            aes.key_unwrap(cursor, layouter, &expected[..16]),
+            Err(Error::Synthesis)
+        ));
+        assert_eq!(cursor.count, count);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_key_wrap() {
+    // RFC 3394 Section 4.1 with the KEK 000102..0f, and 192 bits of key data under it
+    let vectors = [
+        (
+            "00112233445566778899aabbccddeeff",
+            "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5",
+        ),
+        (
+            "00112233445566778899aabbccddeeff0001020304050607",
+            "889671106535a9f86d9f9a262f674569efa38d7535aac77527cab92855bddd6e",
+        ),
+    ];
+    let round_keys = key_schedule_reference(FIPS_KEY);
+    let instance = |bytes: &[u8]| vec![bytes.iter().map(|&b| Fp::from(b as u64)).collect()];
+
+    for (key_data, expected) in vectors {
+        let key_data = hex_to_bytes(key_data);
+        let expected = hex_to_bytes(expected);
+        assert_eq!(key_wrap_reference(&round_keys, &key_data), expected);
+
+        // Wrapped, then unwrapped back to the key data
+        let circuit = key_wrap_circuit(key_data.clone(), expected.clone(), None);
+        let public = [expected.as_slice(), key_data.as_slice()].concat();
+        let mock = MockProver::run(17, &circuit, instance(&public)).unwrap();
+        mock.assert_satisfied();
+
+        // The wrapped key doesn't unwrap to other key data
+        let mut wrong = public.clone();
+        wrong[expected.len()] ^= 1;
+        let mock = MockProver::run(17, &circuit, instance(&wrong)).unwrap();
+        assert!(mock.verify().is_err());
+
+        // A wrapped key tampered with fails the integrity check on the IV,
+        // in A or in any of the blocks
+        for i in [0, 8, expected.len() - 1] {
+            let circuit = key_wrap_circuit(key_data.clone(), expected.clone(), Some(i));
+            let mock = MockProver::run(17, &circuit, instance(&expected)).unwrap();
+            assert!(mock.verify().is_err());
+        }
+    }
+}
+
+#[test]
+fn test_key_unwrap_rows() {
+    let key_data = hex_to_bytes("00112233445566778899aabbccddeeff");
+    let expected = hex_to_bytes("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5");
+    let circuit = key_wrap_circuit(key_data, expected.clone(), Some(0));
+    let mock = MockProver::run(
+        17,
+        &circuit,
+        vec![expected.iter().map(|&b| Fp::from(b as u64)).collect()],
+    )
+    .unwrap();
+
+    // Key wrap and unwrap of n = 2 blocks: the key data or wrapped key bytes,
+    // 6n encryptions or decryptions and the 6n XORs of t, of 1 non-zero byte.
+    // Key wrap also assigns the IV. The blocks are given as cells, so the 16 rows
+    // of the block accounted in AES_ROWS and DECRYPT_ROWS are left unused.
+    let n = 2;
+    let wrap = 8 * n + 8 + 6 * n * (AES_ROWS - 16 + 2);
+    let unwrap = 8 * (n + 1) + 6 * n * (DECRYPT_ROWS - 16 + 2);
+    let rows = crate::dev::used_rows_per_advice(&mock);
+    assert_eq!(rows[0] as u64, KEY_SCHEDULE_ROWS + wrap + unwrap);
+}
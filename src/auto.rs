@@ -0,0 +1,266 @@
+//! AES-128 config sized from the number of blocks, hiding the const generics of
+//! `FixedAes128Config`.
+//!
+//! `Aes128::configure(meta, num_blocks)` picks the smallest K holding the blocks and
+//! the key schedule in one group of columns, see `recommended_k`, so the config can be
+//! stored in other structs without K and N in its type. The circuit must be proven with
+//! 2^k rows for `Aes128::k_for(num_blocks)`.
+//!
+//! Use `FixedAes128Config` directly for more groups of columns, the separate table layout
+//! or the other modes of operation.
+
+use crate::{
+    aes128::{recommended_k, validate_params, Aes128Scheduled, CompactAes128Config, EncryptCursor},
+    block::Ciphertext,
+    error::AesError,
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{ConstraintSystem, Error},
+    },
+};
+
+// Define `Aes128` and `ScheduledAes128` over the given sizes, by K.
+// Each method calls the same one of whichever size is picked.
+macro_rules! define_auto_sized {
+    ($($variant:ident = $k:literal),* $(,)?) => {
+        #[derive(Clone, Debug)]
+        enum Config {
+            $($variant(CompactAes128Config<$k>),)*
+        }
+
+        #[derive(Clone, Debug)]
+        enum Scheduled {
+            $($variant(Aes128Scheduled<$k, 1>),)*
+        }
+
+        /// AES-128 config of `CompactAes128Config` with K picked from the number of blocks.
+        #[derive(Clone, Debug)]
+        pub struct Aes128 {
+            config: Config,
+        }
+
+        impl Aes128 {
+            /// Largest k `Aes128` can pick.
+            pub const MAX_K: u32 = {
+                let mut max = 0;
+                $(if $k > max { max = $k; })*
+                max
+            };
+
+            /// The k of the circuit holding `num_blocks` encryptions,
+            /// i.e. the one to run the prover with.
+            /// `AesError::TooManyBlocks` if the blocks need more than 2^MAX_K rows.
+            pub fn k_for(num_blocks: u64) -> Result<u32, AesError> {
+                // The table and the key schedule fit in 2^MAX_K rows,
+                // so too many blocks is the only error
+                validate_params(Self::MAX_K, 1, num_blocks)
+                    .map_err(|mut errors| errors.remove(0))?;
+                Ok(recommended_k(num_blocks, 1))
+            }
+
+            /// Configure the circuit for `num_blocks` encryptions.
+            /// Panics if the blocks need more than 2^MAX_K rows, see `k_for`.
+            pub fn configure(meta: &mut ConstraintSystem<Fp>, num_blocks: u64) -> Self {
+                let k = Self::k_for(num_blocks).unwrap_or_else(|err| {
+                    panic!("{}, use FixedAes128Config with more groups of columns", err)
+                });
+                let config = match k {
+                    $($k => Config::$variant(CompactAes128Config::<$k>::configure(meta)),)*
+                    k => unreachable!("no config for k = {}", k),
+                };
+                Self { config }
+            }
+
+            /// The k of the circuit the config is for.
+            pub fn k(&self) -> u32 {
+                match &self.config {
+                    $(Config::$variant(_) => $k,)*
+                }
+            }
+
+            /// Maximum number of `encrypt` calls that fit in the circuit.
+            pub fn max_aes_calls(&self) -> u64 {
+                match &self.config {
+                    $(Config::$variant(_) => CompactAes128Config::<$k>::max_aes_calls(),)*
+                }
+            }
+
+            /// See `FixedAes128Config::load_tables`.
+            pub fn load_tables(&mut self, layouter: &mut impl Layouter<Fp>) -> Result<(), Error> {
+                match &mut self.config {
+                    $(Config::$variant(config) => config.load_tables(layouter),)*
+                }
+            }
+
+            /// See `FixedAes128Config::schedule_key`.
+            pub fn schedule_key(
+                self,
+                layouter: &mut impl Layouter<Fp>,
+                key: [u8; 16],
+            ) -> Result<ScheduledAes128, Error> {
+                let scheduled = match self.config {
+                    $(Config::$variant(config) => {
+                        Scheduled::$variant(config.schedule_key(layouter, key)?)
+                    })*
+                };
+                Ok(ScheduledAes128 { scheduled })
+            }
+        }
+
+        /// `Aes128` with the scheduled key, returned by `Aes128::schedule_key`.
+        #[derive(Clone, Debug)]
+        pub struct ScheduledAes128 {
+            scheduled: Scheduled,
+        }
+
+        impl ScheduledAes128 {
+            /// See `Aes128Scheduled::encrypt`.
+            pub fn encrypt(
+                &self,
+                cursor: &mut EncryptCursor,
+                layouter: &mut impl Layouter<Fp>,
+                plaintext: [u8; 16],
+            ) -> Result<Ciphertext, AesError> {
+                match &self.scheduled {
+                    $(Scheduled::$variant(aes) => aes.encrypt(cursor, layouter, plaintext),)*
+                }
+            }
+
+            /// See `Aes128Scheduled::encrypt_cells`.
+            pub fn encrypt_cells(
+                &self,
+                cursor: &mut EncryptCursor,
+                layouter: &mut impl Layouter<Fp>,
+                plaintext: &[AssignedCell<Fp, Fp>],
+            ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+                match &self.scheduled {
+                    $(Scheduled::$variant(aes) => aes.encrypt_cells(cursor, layouter, plaintext),)*
+                }
+            }
+
+            /// See `FixedAes128Config::expose_ciphertext`.
+            pub fn expose_ciphertext(
+                &self,
+                cursor: &mut EncryptCursor,
+                layouter: &mut impl Layouter<Fp>,
+                ciphertext: &[AssignedCell<Fp, Fp>],
+            ) -> Result<(), Error> {
+                match &self.scheduled {
+                    $(Scheduled::$variant(aes) => {
+                        aes.expose_ciphertext(cursor, layouter, ciphertext)
+                    })*
+                }
+            }
+        }
+    };
+}
+
+// 2^22 rows hold 3083 blocks. More blocks are better split in more groups of columns.
+define_auto_sized!(K17 = 17, K18 = 18, K19 = 19, K20 = 20, K21 = 21, K22 = 22);
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        build_public_inputs,
+        halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit},
+    };
+
+    // FIPS-197 Appendix C.1
+    const KEY: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const PLAINTEXT: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+    const CIPHERTEXT: [u8; 16] = [
+        0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5,
+        0x5a,
+    ];
+
+    const NUM_BLOCKS: u64 = 2;
+
+    #[derive(Clone)]
+    struct TestCircuit;
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = Aes128;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            Aes128::configure(meta, NUM_BLOCKS)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            config.load_tables(&mut layouter)?;
+            let aes = config.schedule_key(&mut layouter, KEY)?;
+            let mut cursor = EncryptCursor::default();
+            for _ in 0..NUM_BLOCKS {
+                let ciphertext = aes.encrypt(&mut cursor, &mut layouter, PLAINTEXT)?;
+                aes.expose_ciphertext(&mut cursor, &mut layouter, &ciphertext)?;
+            }
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+    }
+
+    #[test]
+    fn test_k_for() {
+        assert_eq!(Aes128::MAX_K, 22);
+        assert_eq!(Aes128::k_for(1).unwrap(), 17);
+        assert_eq!(Aes128::k_for(96).unwrap(), 17);
+        assert_eq!(Aes128::k_for(97).unwrap(), 18);
+        assert_eq!(Aes128::k_for(3000).unwrap(), 22);
+    }
+
+    #[test]
+    fn test_k_for_too_many_blocks() {
+        assert!(matches!(
+            Aes128::k_for(10_000),
+            Err(AesError::TooManyBlocks {
+                num_blocks: 10_000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "use FixedAes128Config with more groups of columns")]
+    fn test_configure_too_many_blocks() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        Aes128::configure(&mut meta, 10_000);
+    }
+
+    #[test]
+    fn test_auto_sized_encrypt() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let config = Aes128::configure(&mut meta, NUM_BLOCKS);
+        assert_eq!(config.k(), 17);
+        assert!(config.max_aes_calls() >= NUM_BLOCKS);
+
+        let instance = [CIPHERTEXT; NUM_BLOCKS as usize]
+            .iter()
+            .flat_map(|&ciphertext| build_public_inputs(Some(ciphertext), None, None))
+            .collect();
+        let mock = MockProver::run(
+            Aes128::k_for(NUM_BLOCKS).unwrap(),
+            &TestCircuit,
+            vec![instance],
+        )
+        .unwrap();
+        mock.assert_satisfied();
+    }
+}
@@ -0,0 +1,64 @@
+//! Blocks of 16 assigned bytes returned by the cipher.
+//!
+//! The bytes are in the order of the block, the same order as the bytes given to `encrypt`,
+//! i.e. the byte i is in the row i % 4 of the column i / 4 of the AES state (FIPS-197 3.4),
+//! see `state::State`. A word of the block is a column of the state.
+
+use std::ops::Deref;
+
+use crate::halo2_proofs::{circuit::AssignedCell, halo2curves::bn256::Fr as Fp};
+
+macro_rules! define_block {
+    ($(#[$doc:meta])* $name:ident) => {
+        $(#[$doc])*
+        #[derive(Clone, Debug)]
+        pub struct $name([AssignedCell<Fp, Fp>; 16]);
+
+        impl $name {
+            // Panics if there aren't 16 cells
+            pub(crate) fn new(cells: Vec<AssignedCell<Fp, Fp>>) -> Self {
+                Self(cells.try_into().expect("Block should be 16 bytes"))
+            }
+
+            /// The 16 bytes in the order of the block.
+            pub fn bytes(&self) -> &[AssignedCell<Fp, Fp>; 16] {
+                &self.0
+            }
+
+            /// The word i of the block, i.e. the bytes 4i to 4i + 3, the column i of the state.
+            /// Panics if i isn't less than 4.
+            pub fn word(&self, i: usize) -> &[AssignedCell<Fp, Fp>; 4] {
+                self.0[4 * i..4 * i + 4].try_into().unwrap()
+            }
+
+            /// The 16 byte cells in the order of the block, e.g. to chain into the next block.
+            pub fn into_cells(self) -> Vec<AssignedCell<Fp, Fp>> {
+                self.0.into()
+            }
+        }
+
+        // Used as the bytes of the block wherever a slice of cells is taken
+        impl Deref for $name {
+            type Target = [AssignedCell<Fp, Fp>];
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl From<$name> for Vec<AssignedCell<Fp, Fp>> {
+            fn from(block: $name) -> Self {
+                block.into_cells()
+            }
+        }
+    };
+}
+
+define_block!(
+    /// Ciphertext block returned by `Aes128Scheduled::encrypt`.
+    Ciphertext
+);
+define_block!(
+    /// Plaintext block returned by `Aes128Scheduled::decrypt`.
+    Plaintext
+);
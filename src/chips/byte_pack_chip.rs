@@ -0,0 +1,234 @@
+//! Packing of up to 31 bytes into a single field element, e.g. to expose a ciphertext
+//! as one public input instead of one per byte.
+//!
+//! packed = b0 + 256 * b1 + ... + 256^(n-1) * b(n-1), accumulated from the last byte as
+//! acc_i = 256 * acc_(i-1) + b(n-1-i). Each byte is range checked, and 256^31 is below
+//! the modulus, so the packed value doesn't wrap around and unpacks to the same bytes.
+
+use crate::{
+    chips::u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+        poly::Rotation,
+    },
+};
+
+/// Most bytes packed into one field element.
+pub const MAX_PACKED_BYTES: usize = 31;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BytePackConfig {
+    advices: [Column<Advice>; 2],
+    q_first: Selector,
+    q_next: Selector,
+
+    u8_range_check_config: U8RangeCheckConfig,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BytePackChip {
+    config: BytePackConfig,
+}
+
+impl BytePackChip {
+    pub fn construct(config: BytePackConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configure byte pack chip.
+    /// advices[0] should be the column range checked by `u8_range_check_config`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 2],
+        u8_range_check_config: U8RangeCheckConfig,
+    ) -> BytePackConfig {
+        let q_first = meta.selector();
+        let q_next = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | b(n-1-i) | acc_i |
+        meta.create_gate("Pack first byte", |meta| {
+            let q = meta.query_selector(q_first);
+            let byte = meta.query_advice(advices[0], Rotation::cur());
+            let acc = meta.query_advice(advices[1], Rotation::cur());
+
+            vec![q * (acc - byte)]
+        });
+
+        meta.create_gate("Pack next byte", |meta| {
+            let q = meta.query_selector(q_next);
+            let byte = meta.query_advice(advices[0], Rotation::cur());
+            let acc = meta.query_advice(advices[1], Rotation::cur());
+            let acc_prev = meta.query_advice(advices[1], Rotation::prev());
+
+            vec![q * (acc - acc_prev * Fp::from(256) - byte)]
+        });
+
+        BytePackConfig {
+            advices,
+            q_first,
+            q_next,
+            u8_range_check_config,
+        }
+    }
+
+    /// Pack 1 to 31 bytes into a field element, the first byte the least significant.
+    /// The bytes are range checked, see `utils::pack_bytes` for the same out of the circuit.
+    pub fn pack(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        assert!(
+            !bytes.is_empty() && bytes.len() <= MAX_PACKED_BYTES,
+            "Pack 1 to 31 bytes, the packed value would wrap around the modulus otherwise"
+        );
+        let range_chip = U8RangeCheckChip::construct(self.config.u8_range_check_config);
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Pack bytes",
+            |mut region| {
+                let mut acc = None;
+                for (i, byte) in bytes.iter().rev().enumerate() {
+                    if i == 0 {
+                        self.config.q_first.enable(&mut region, i)?;
+                    } else {
+                        self.config.q_next.enable(&mut region, i)?;
+                    }
+                    let value = byte.value().map(|v| v.to_bytes()[0] as u64);
+                    let checked = range_chip.assign_byte(&mut region, i, value)?;
+                    region.constrain_equal(byte.cell(), checked.cell())?;
+
+                    let value = acc
+                        .as_ref()
+                        .map_or(Value::known(Fp::from(0)), |acc: &AssignedCell<Fp, Fp>| {
+                            acc.value() * Value::known(Fp::from(256))
+                        })
+                        + byte.value();
+                    acc = Some(region.assign_advice(|| "assign acc", advices[1], i, || value)?);
+                }
+
+                Ok(acc.unwrap())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{
+            circuit::SimpleFloorPlanner,
+            dev::MockProver,
+            plonk::{Circuit, Instance, TableColumn},
+        },
+        table::load_enc_full_table,
+        utils::pack_bytes,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        bytes: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (BytePackConfig, [TableColumn; 4], Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            let q_u8_range_check = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+
+            (
+                BytePackChip::configure(meta, advices, u8_range_check_config),
+                tables,
+                instance,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+
+            // Unchecked bytes, checked by the packing
+            let bytes = layouter.assign_region(
+                || "Assign bytes",
+                |mut region| {
+                    self.bytes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &b)| {
+                            region.assign_advice(
+                                || "assign byte",
+                                config.0.advices[1],
+                                i,
+                                || Value::known(Fp::from(b as u64)),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+
+            let packed = BytePackChip::construct(config.0).pack(&mut layouter, &bytes)?;
+            layouter.constrain_instance(packed.cell(), config.2, 0)
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_pack() {
+        for len in [1, 16, MAX_PACKED_BYTES] {
+            let bytes = (0..len).map(|i| 0xff - i as u8).collect::<Vec<_>>();
+            let packed = pack_bytes(&bytes);
+            assert_eq!(packed.len(), 1);
+            // Unpacks to the bytes, little-endian
+            assert_eq!(packed[0].to_bytes()[..len], bytes[..]);
+            assert!(packed[0].to_bytes()[len..].iter().all(|&b| b == 0));
+
+            let circuit = TestCircuit { bytes };
+            let mock = MockProver::run(17, &circuit, vec![packed]).unwrap();
+            mock.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_pack_wrong_instance_fails() {
+        let bytes = vec![1, 2, 3];
+        let circuit = TestCircuit { bytes };
+        // Big-endian instead of little-endian
+        let instance = vec![Fp::from(0x010203)];
+        let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+}
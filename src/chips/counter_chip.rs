@@ -0,0 +1,387 @@
+//! Increment of the 128 bit big-endian counter block used in CTR mode.
+//!
+//! Each byte is added with the incoming carry from the less significant byte,
+//! x + c_in = y + 256 * c_out, where y is range checked and c_out is boolean.
+//! The first carry in (of the last byte) is fixed to 1.
+//!
+//! In `CounterMode::Low32`, the inc32 of GCM, only the last 4 bytes are incremented
+//! this way and the carry out of them is dropped. The first 12 bytes, the nonce,
+//! are the same cells in the next block, so they can't change.
+
+use crate::{
+    chips::u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+        poly::Rotation,
+    },
+    utils::cells_to_bytes,
+};
+
+/// Which bytes of the counter block are the counter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CounterMode {
+    /// The whole block, wrapping around at 2^128.
+    #[default]
+    Full128,
+    /// The last 4 bytes after a 96 bit nonce, wrapping around at 2^32, as in GCM.
+    Low32,
+}
+
+impl CounterMode {
+    // Number of the incremented bytes at the end of the block
+    fn counter_bytes(self) -> usize {
+        match self {
+            CounterMode::Full128 => 16,
+            CounterMode::Low32 => 4,
+        }
+    }
+
+    /// Next counter block out of the circuit.
+    pub fn next_block(self, block: [u8; 16]) -> [u8; 16] {
+        match self {
+            CounterMode::Full128 => u128::from_be_bytes(block).wrapping_add(1).to_be_bytes(),
+            CounterMode::Low32 => {
+                let mut next = block;
+                let counter = u32::from_be_bytes(block[12..].try_into().unwrap());
+                next[12..].copy_from_slice(&counter.wrapping_add(1).to_be_bytes());
+                next
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CounterConfig {
+    advices: [Column<Advice>; 3],
+    q_first: Selector,
+    q_incr: Selector,
+
+    u8_range_check_config: U8RangeCheckConfig,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CounterChip {
+    config: CounterConfig,
+}
+
+impl CounterChip {
+    pub fn construct(config: CounterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configure counter chip.
+    /// advices[0] should be the column range checked by `u8_range_check_config`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 3],
+        u8_range_check_config: U8RangeCheckConfig,
+    ) -> CounterConfig {
+        let q_first = meta.selector();
+        let q_incr = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | y | x | c_in |
+        meta.create_gate("Counter first carry", |meta| {
+            let q = meta.query_selector(q_first);
+            let c = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![q * (c - Expression::Constant(Fp::from(1)))]
+        });
+
+        meta.create_gate("Counter byte increment", |meta| {
+            let q = meta.query_selector(q_incr);
+            let y = meta.query_advice(advices[0], Rotation::cur());
+            let x = meta.query_advice(advices[1], Rotation::cur());
+            let c_in = meta.query_advice(advices[2], Rotation::cur());
+            let c_out = meta.query_advice(advices[2], Rotation::next());
+
+            vec![
+                q.clone() * (c_out.clone() * c_out.clone() - c_out.clone()),
+                q * (x + c_in - y - c_out * Fp::from(256)),
+            ]
+        });
+
+        CounterConfig {
+            advices,
+            q_first,
+            q_incr,
+            u8_range_check_config,
+        }
+    }
+
+    /// Increment the 16 bytes big-endian counter block by one, wrapping around at 2^128.
+    pub fn increment(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        block: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        self.increment_with_mode(layouter, block, CounterMode::Full128)
+    }
+
+    /// Increment the counter of the 16 bytes block by one in the given mode.
+    /// In `CounterMode::Low32`, the first 12 bytes of the returned block are the input cells.
+    pub fn increment_with_mode(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        block: &[AssignedCell<Fp, Fp>],
+        mode: CounterMode,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let next =
+            cells_to_bytes(block).map(|bytes| mode.next_block(bytes.try_into().unwrap()).to_vec());
+
+        self.assign_increment(layouter, block, next, mode)
+    }
+
+    /// Assign the given next block with the carries derived from the block.
+    fn assign_increment(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        block: &[AssignedCell<Fp, Fp>],
+        next: Value<Vec<u8>>,
+        mode: CounterMode,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        assert_eq!(block.len(), 16, "Counter block should be 16 bytes");
+        let counter_bytes = mode.counter_bytes();
+
+        let range_chip = U8RangeCheckChip::construct(self.config.u8_range_check_config);
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Increment counter",
+            |mut region| {
+                self.config.q_first.enable(&mut region, 0)?;
+
+                // The i-th row takes the (15 - i)-th byte
+                let mut carry = Value::known(1u64);
+                let mut next_block = vec![];
+                for i in 0..counter_bytes {
+                    let pos = 15 - i;
+                    self.config.q_incr.enable(&mut region, i)?;
+
+                    let x = block[pos].copy_advice(
+                        || "copy counter byte",
+                        &mut region,
+                        advices[1],
+                        i,
+                    )?;
+                    region.assign_advice(
+                        || "assign carry",
+                        advices[2],
+                        i,
+                        || carry.map(Fp::from),
+                    )?;
+                    next_block.push(range_chip.assign_byte(
+                        &mut region,
+                        i,
+                        next.as_ref().map(|next| next[pos] as u64),
+                    )?);
+
+                    carry = x
+                        .value()
+                        .zip(carry)
+                        .map(|(x, c)| (x.to_bytes()[0] as u64 + c) >> 8);
+                }
+                region.assign_advice(
+                    || "assign overflow carry",
+                    advices[2],
+                    counter_bytes,
+                    || carry.map(Fp::from),
+                )?;
+
+                // The nonce bytes, if any, are carried over as they are
+                next_block.extend(block[..16 - counter_bytes].iter().rev().cloned());
+                next_block.reverse();
+                Ok(next_block)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner, Value},
+            dev::MockProver,
+            halo2curves::bn256::Fr as Fp,
+            plonk::{Circuit, ConstraintSystem, Error, TableColumn},
+        },
+        table::load_enc_full_table,
+        utils::cells_to_bytes,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        block: [u8; 16],
+        expected: [u8; 16],
+        mode: CounterMode,
+        // Reuse the counter value instead of incrementing it
+        malicious: bool,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (CounterConfig, U8RangeCheckConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+
+            let q_u8_range_check = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+
+            (
+                CounterChip::configure(meta, advices, u8_range_check_config),
+                u8_range_check_config,
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.2, 17)?;
+
+            let range_chip = U8RangeCheckChip::construct(config.1);
+            let block = layouter.assign_region(
+                || "assign counter block",
+                |mut region| {
+                    self.block
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &b)| {
+                            range_chip.assign_byte(&mut region, i, Value::known(b as u64))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+
+            let counter_chip = CounterChip::construct(config.0);
+            let next = if self.malicious {
+                counter_chip.assign_increment(
+                    &mut layouter,
+                    &block,
+                    cells_to_bytes(&block),
+                    self.mode,
+                )?
+            } else {
+                counter_chip.increment_with_mode(&mut layouter, &block, self.mode)?
+            };
+            cells_to_bytes(&next).assert_if_known(|bytes| bytes[..] == self.expected);
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    fn increment_cases() -> Vec<([u8; 16], [u8; 16])> {
+        let mut one = [0u8; 16];
+        one[15] = 1;
+        let mut low_ff = [0u8; 16];
+        low_ff[15] = 0xff;
+        let mut carried = [0u8; 16];
+        carried[14] = 1;
+
+        vec![([0u8; 16], one), (low_ff, carried), ([0xff; 16], [0u8; 16])]
+    }
+
+    #[test]
+    fn test_increment() {
+        for (block, expected) in increment_cases() {
+            let circuit = TestCircuit {
+                block,
+                expected,
+                mode: CounterMode::Full128,
+                malicious: false,
+            };
+
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            mock.assert_satisfied();
+        }
+    }
+
+    // (block, next block) of the 32 bit counter after a 96 bit nonce
+    fn increment_low32_cases() -> Vec<([u8; 16], [u8; 16])> {
+        let nonce = [0xca; 12];
+        let block = |counter: [u8; 4]| {
+            let mut block = [0u8; 16];
+            block[..12].copy_from_slice(&nonce);
+            block[12..].copy_from_slice(&counter);
+            block
+        };
+
+        vec![
+            (block([0, 0, 0, 1]), block([0, 0, 0, 2])),
+            (block([0, 0, 0xff, 0xff]), block([0, 1, 0, 0])),
+            // Wraps around within the 32 bits, the nonce is left as it is
+            (block([0xff; 4]), block([0; 4])),
+        ]
+    }
+
+    #[test]
+    fn test_increment_low32() {
+        for (block, expected) in increment_low32_cases() {
+            assert_eq!(CounterMode::Low32.next_block(block), expected);
+            let circuit = TestCircuit {
+                block,
+                expected,
+                mode: CounterMode::Low32,
+                malicious: false,
+            };
+
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            mock.assert_satisfied();
+        }
+
+        // The full increment carries into the nonce instead
+        let (block, _) = increment_low32_cases()[2];
+        assert_eq!(
+            CounterMode::Full128.next_block(block)[..12],
+            [[0xca; 11].as_slice(), &[0xcb]].concat()[..]
+        );
+    }
+
+    #[test]
+    fn test_reused_counter_fails() {
+        let block = [7u8; 16];
+        for mode in [CounterMode::Full128, CounterMode::Low32] {
+            let circuit = TestCircuit {
+                block,
+                expected: block,
+                mode,
+                malicious: true,
+            };
+
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert!(mock.verify().is_err());
+        }
+    }
+}
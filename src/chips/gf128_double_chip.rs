@@ -0,0 +1,343 @@
+//! Doubling in GF(2^128), i.e. multiplication by x.
+//!
+//! The 16 bytes are interpreted as a big-endian 128-bit value as in CMAC (RFC 4493).
+//! The value is shifted left by one bit and the reduction constant 0x87 is XORed
+//! into the last byte when the top bit was set.
+//!
+//! Each byte x_i is decomposed into its top bit c_i and its lower bits l_i,
+//! where x_i = 128 * c_i + l_i. Then the doubled byte is 2 * l_i + c_{i+1},
+//! and the last byte is 2 * l_15 XOR (c_0 * 0x87).
+//!
+//! l_i is range checked as a byte and so is the doubled byte 2 * l_i + c_{i+1},
+//! which bounds l_i to 0..128 and x_i to a byte.
+
+use crate::{
+    chips::{
+        u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+        u8_xor_chip::{U8XorChip, U8XorConfig},
+    },
+    constant::GF128_REDUCTION,
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+        poly::Rotation,
+    },
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Gf128DoubleConfig {
+    advices: [Column<Advice>; 3],
+    q_decompose: Selector,
+    q_shift: Selector,
+    q_reduce: Selector,
+    q_last: Selector,
+
+    u8_range_check_config: U8RangeCheckConfig,
+    u8_xor_config: U8XorConfig,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Gf128DoubleChip {
+    config: Gf128DoubleConfig,
+}
+
+impl Gf128DoubleChip {
+    pub fn construct(config: Gf128DoubleConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configure doubling chip.
+    /// advices[0] should be the column range checked by `u8_range_check_config`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 3],
+        u8_range_check_config: U8RangeCheckConfig,
+        u8_xor_config: U8XorConfig,
+    ) -> Gf128DoubleConfig {
+        let q_decompose = meta.selector();
+        let q_shift = meta.selector();
+        let q_reduce = meta.selector();
+        let q_last = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | l | x | c |
+        meta.create_gate("GF(2^128) doubling byte decomposition", |meta| {
+            let q = meta.query_selector(q_decompose);
+            let l = meta.query_advice(advices[0], Rotation::cur());
+            let x = meta.query_advice(advices[1], Rotation::cur());
+            let c = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![
+                q.clone() * (c.clone() * c.clone() - c.clone()),
+                q * (x - c * Fp::from(128) - l),
+            ]
+        });
+
+        // | out | l | c_next |
+        meta.create_gate("GF(2^128) doubling shift", |meta| {
+            let q = meta.query_selector(q_shift);
+            let out = meta.query_advice(advices[0], Rotation::cur());
+            let l = meta.query_advice(advices[1], Rotation::cur());
+            let c = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![q * (out - l * Fp::from(2) - c)]
+        });
+
+        // | t | l | - |
+        meta.create_gate("GF(2^128) doubling last shift", |meta| {
+            let q = meta.query_selector(q_last);
+            let t = meta.query_advice(advices[0], Rotation::cur());
+            let l = meta.query_advice(advices[1], Rotation::cur());
+
+            vec![q * (t - l * Fp::from(2))]
+        });
+
+        // | m | c_0 | - |
+        meta.create_gate("GF(2^128) doubling reduction", |meta| {
+            let q = meta.query_selector(q_reduce);
+            let m = meta.query_advice(advices[0], Rotation::cur());
+            let c = meta.query_advice(advices[1], Rotation::cur());
+
+            vec![q * (m - c * Fp::from(GF128_REDUCTION as u64))]
+        });
+
+        Gf128DoubleConfig {
+            advices,
+            q_decompose,
+            q_shift,
+            q_reduce,
+            q_last,
+            u8_range_check_config,
+            u8_xor_config,
+        }
+    }
+
+    /// Double the given 16 bytes in GF(2^128).
+    /// Input bytes are constrained to be u8 by the decomposition,
+    /// as the lower bits and the doubled bytes are range checked.
+    pub fn double(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        assert_eq!(x.len(), 16, "GF(2^128) doubling takes 16 bytes");
+
+        let range_chip = U8RangeCheckChip::construct(self.config.u8_range_check_config);
+        let xor_chip = U8XorChip::construct(self.config.u8_xor_config);
+        let advices = self.config.advices;
+
+        let (mut doubled, last_shifted, reduction) = layouter.assign_region(
+            || "GF(2^128) doubling",
+            |mut region| {
+                let mut lower_bits = vec![];
+                let mut top_bits = vec![];
+                for (i, byte) in x.iter().enumerate() {
+                    self.config.q_decompose.enable(&mut region, i)?;
+                    let value = byte.value().map(|v| v.to_bytes()[0] as u64);
+                    lower_bits.push(range_chip.assign_byte(
+                        &mut region,
+                        i,
+                        value.map(|v| v & 0x7f),
+                    )?);
+                    byte.copy_advice(|| "copy byte to double", &mut region, advices[1], i)?;
+                    top_bits.push(region.assign_advice(
+                        || "assign top bit",
+                        advices[2],
+                        i,
+                        || value.map(|v| Fp::from(v >> 7)),
+                    )?);
+                }
+
+                let mut doubled = vec![];
+                for i in 0..15 {
+                    let offset = 16 + i;
+                    self.config.q_shift.enable(&mut region, offset)?;
+                    lower_bits[i].copy_advice(
+                        || "copy lower bits",
+                        &mut region,
+                        advices[1],
+                        offset,
+                    )?;
+                    top_bits[i + 1].copy_advice(
+                        || "copy carried bit",
+                        &mut region,
+                        advices[2],
+                        offset,
+                    )?;
+                    let value = lower_bits[i]
+                        .value()
+                        .zip(top_bits[i + 1].value())
+                        .map(|(l, c)| 2 * l.to_bytes()[0] as u64 + c.to_bytes()[0] as u64);
+                    doubled.push(range_chip.assign_byte(&mut region, offset, value)?);
+                }
+
+                let offset = 31;
+                self.config.q_reduce.enable(&mut region, offset)?;
+                top_bits[0].copy_advice(|| "copy top bit", &mut region, advices[1], offset)?;
+                let reduction = region.assign_advice(
+                    || "assign reduction",
+                    advices[0],
+                    offset,
+                    || {
+                        top_bits[0]
+                            .value()
+                            .map(|c| *c * Fp::from(GF128_REDUCTION as u64))
+                    },
+                )?;
+
+                let offset = 32;
+                self.config.q_last.enable(&mut region, offset)?;
+                lower_bits[15].copy_advice(
+                    || "copy last lower bits",
+                    &mut region,
+                    advices[1],
+                    offset,
+                )?;
+                let last_shifted = range_chip.assign_byte(
+                    &mut region,
+                    offset,
+                    lower_bits[15].value().map(|l| 2 * l.to_bytes()[0] as u64),
+                )?;
+
+                Ok((doubled, last_shifted, reduction))
+            },
+        )?;
+
+        doubled.push(xor_chip.xor(layouter, &last_shifted, &reduction)?);
+        Ok(doubled)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner, Value},
+            dev::MockProver,
+            halo2curves::bn256::Fr as Fp,
+            plonk::{Circuit, ConstraintSystem, Error, TableColumn},
+        },
+        table::load_enc_full_table,
+        utils::cells_to_bytes,
+    };
+
+    // RFC 4493 subkey generation for K = 2b7e1516 28aed2a6 abf71588 09cf4f3c
+    const L: [u8; 16] = [
+        0x7d, 0xf7, 0x6b, 0x0c, 0x1a, 0xb8, 0x99, 0xb3, 0x3e, 0x42, 0xf0, 0x47, 0xb9, 0x1b, 0x54,
+        0x6f,
+    ];
+    const K1: [u8; 16] = [
+        0xfb, 0xee, 0xd6, 0x18, 0x35, 0x71, 0x33, 0x66, 0x7c, 0x85, 0xe0, 0x8f, 0x72, 0x36, 0xa8,
+        0xde,
+    ];
+    const K2: [u8; 16] = [
+        0xf7, 0xdd, 0xac, 0x30, 0x6a, 0xe2, 0x66, 0xcc, 0xf9, 0x0b, 0xc1, 0x1e, 0xe4, 0x6d, 0x51,
+        0x3b,
+    ];
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        input: [u8; 16],
+        expected: [u8; 16],
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (Gf128DoubleConfig, U8RangeCheckConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+
+            let q_u8_range_check = meta.complex_selector();
+            let q_u8_xor = meta.complex_selector();
+
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+            let u8_xor_config = U8XorChip::configure(
+                meta, advices[0], advices[1], advices[2], q_u8_xor, tables[0], tables[1],
+                tables[2], tables[3],
+            );
+
+            (
+                Gf128DoubleChip::configure(meta, advices, u8_range_check_config, u8_xor_config),
+                u8_range_check_config,
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.2, 17)?;
+
+            let range_chip = U8RangeCheckChip::construct(config.1);
+            let input = layouter.assign_region(
+                || "assign input",
+                |mut region| {
+                    self.input
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &b)| {
+                            range_chip.assign_byte(&mut region, i, Value::known(b as u64))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+
+            let doubled = Gf128DoubleChip::construct(config.0).double(&mut layouter, &input)?;
+            cells_to_bytes(&doubled).assert_if_known(|bytes| bytes[..] == self.expected);
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_double_without_reduction() {
+        let circuit = TestCircuit {
+            input: L,
+            expected: K1,
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[test]
+    fn test_double_with_reduction() {
+        let circuit = TestCircuit {
+            input: K1,
+            expected: K2,
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+}
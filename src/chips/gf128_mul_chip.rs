@@ -0,0 +1,466 @@
+//! Multiplication in GF(2^128) as used in GHASH (NIST SP 800-38D).
+//!
+//! The 16 bytes are a bit-reflected field element, the first bit of the field element
+//! is the top bit of the first byte. The product X * Y is computed bit by bit as
+//! Z ^= x_i * V and V = V * x, starting from V = Y.
+//!
+//! Multiplication by x is the right shift of the 128 bit string.
+//! Each byte v_i is decomposed into the shifted higher bits h_i and the lowest bit l_i,
+//! where v_i = 2 * h_i + l_i. Then the shifted byte is h_i + 128 * l_{i-1},
+//! and the first byte is h_0 XOR (l_15 * 0xe1).
+
+use crate::{
+    chips::{
+        u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+        u8_xor_chip::{U8XorChip, U8XorConfig},
+    },
+    constant::GCM_REDUCTION,
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+        poly::Rotation,
+    },
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Gf128MulConfig {
+    advices: [Column<Advice>; 3],
+    q_decompose: Selector,
+    q_shift: Selector,
+    q_reduce: Selector,
+    q_first_bit: Selector,
+    q_bit: Selector,
+    q_mask: Selector,
+
+    u8_range_check_config: U8RangeCheckConfig,
+    u8_xor_config: U8XorConfig,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Gf128MulChip {
+    config: Gf128MulConfig,
+}
+
+impl Gf128MulChip {
+    pub fn construct(config: Gf128MulConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configure GF(2^128) multiplication chip.
+    /// advices[0] should be the column range checked by `u8_range_check_config`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 3],
+        u8_range_check_config: U8RangeCheckConfig,
+        u8_xor_config: U8XorConfig,
+    ) -> Gf128MulConfig {
+        let q_decompose = meta.selector();
+        let q_shift = meta.selector();
+        let q_reduce = meta.selector();
+        let q_first_bit = meta.selector();
+        let q_bit = meta.selector();
+        let q_mask = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | h | v | l |
+        meta.create_gate("GF(2^128) mul byte decomposition", |meta| {
+            let q = meta.query_selector(q_decompose);
+            let h = meta.query_advice(advices[0], Rotation::cur());
+            let v = meta.query_advice(advices[1], Rotation::cur());
+            let l = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![
+                q.clone() * (l.clone() * l.clone() - l.clone()),
+                q * (v - h * Fp::from(2) - l),
+            ]
+        });
+
+        // | out | h | l_prev |
+        meta.create_gate("GF(2^128) mul shift", |meta| {
+            let q = meta.query_selector(q_shift);
+            let out = meta.query_advice(advices[0], Rotation::cur());
+            let h = meta.query_advice(advices[1], Rotation::cur());
+            let l = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![q * (out - h - l * Fp::from(128))]
+        });
+
+        // | m | l_15 | - |
+        meta.create_gate("GF(2^128) mul reduction", |meta| {
+            let q = meta.query_selector(q_reduce);
+            let m = meta.query_advice(advices[0], Rotation::cur());
+            let l = meta.query_advice(advices[1], Rotation::cur());
+
+            vec![q * (m - l * Fp::from(GCM_REDUCTION as u64))]
+        });
+
+        // | acc | b | - |
+        meta.create_gate("GF(2^128) mul first bit", |meta| {
+            let q = meta.query_selector(q_first_bit);
+            let acc = meta.query_advice(advices[0], Rotation::cur());
+            let b = meta.query_advice(advices[1], Rotation::cur());
+
+            vec![
+                q.clone() * (b.clone() * b.clone() - b.clone()),
+                q * (acc - b),
+            ]
+        });
+
+        // | acc | b | - |
+        meta.create_gate("GF(2^128) mul next bit", |meta| {
+            let q = meta.query_selector(q_bit);
+            let acc_prev = meta.query_advice(advices[0], Rotation::prev());
+            let acc = meta.query_advice(advices[0], Rotation::cur());
+            let b = meta.query_advice(advices[1], Rotation::cur());
+
+            vec![
+                q.clone() * (b.clone() * b.clone() - b.clone()),
+                q * (acc - acc_prev * Fp::from(2) - b),
+            ]
+        });
+
+        // | m | v | b |
+        meta.create_gate("GF(2^128) mul mask", |meta| {
+            let q = meta.query_selector(q_mask);
+            let m = meta.query_advice(advices[0], Rotation::cur());
+            let v = meta.query_advice(advices[1], Rotation::cur());
+            let b = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![q * (m - v * b)]
+        });
+
+        Gf128MulConfig {
+            advices,
+            q_decompose,
+            q_shift,
+            q_reduce,
+            q_first_bit,
+            q_bit,
+            q_mask,
+            u8_range_check_config,
+            u8_xor_config,
+        }
+    }
+
+    /// Multiply the given 16 bytes x and y in GF(2^128).
+    /// Bytes of x are constrained to be u8 by the bit decomposition,
+    /// bytes of y should be constrained to be u8.
+    pub fn mul(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &[AssignedCell<Fp, Fp>],
+        y: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        assert_eq!(x.len(), 16, "GF(2^128) multiplication takes 16 bytes");
+        assert_eq!(y.len(), 16, "GF(2^128) multiplication takes 16 bytes");
+
+        let xor_chip = U8XorChip::construct(self.config.u8_xor_config);
+
+        let bits = self.decompose_bits(layouter, x)?;
+        let mut v = y.to_vec();
+        let mut z: Option<Vec<AssignedCell<Fp, Fp>>> = None;
+
+        for (i, bit) in bits.iter().enumerate() {
+            let masked = self.mask(layouter, &v, bit)?;
+            z = Some(match z {
+                None => masked,
+                Some(z) => z
+                    .iter()
+                    .zip(masked.iter())
+                    .map(|(z, m)| xor_chip.xor(layouter, z, m))
+                    .collect::<Result<Vec<_>, Error>>()?,
+            });
+
+            if i < bits.len() - 1 {
+                v = self.mul_by_x(layouter, &v)?;
+            }
+        }
+
+        Ok(z.unwrap())
+    }
+
+    // Decompose bytes into bits, the top bit of each byte first.
+    fn decompose_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "GF(2^128) mul bit decomposition",
+            |mut region| {
+                let mut bits = vec![];
+                for (i, byte) in x.iter().enumerate() {
+                    let value = byte.value().map(|v| v.to_bytes()[0] as u64);
+                    for j in 0..8 {
+                        let offset = i * 8 + j;
+                        if j == 0 {
+                            self.config.q_first_bit.enable(&mut region, offset)?;
+                        } else {
+                            self.config.q_bit.enable(&mut region, offset)?;
+                        }
+
+                        bits.push(region.assign_advice(
+                            || "assign bit",
+                            advices[1],
+                            offset,
+                            || value.map(|v| Fp::from((v >> (7 - j)) & 1)),
+                        )?);
+                        if j == 7 {
+                            byte.copy_advice(|| "copy byte", &mut region, advices[0], offset)?;
+                        } else {
+                            region.assign_advice(
+                                || "assign accumulated bits",
+                                advices[0],
+                                offset,
+                                || value.map(|v| Fp::from(v >> (7 - j))),
+                            )?;
+                        }
+                    }
+                }
+
+                Ok(bits)
+            },
+        )
+    }
+
+    // Multiply each byte of v by the bit.
+    fn mask(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        v: &[AssignedCell<Fp, Fp>],
+        bit: &AssignedCell<Fp, Fp>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "GF(2^128) mul mask",
+            |mut region| {
+                v.iter()
+                    .enumerate()
+                    .map(|(i, byte)| {
+                        self.config.q_mask.enable(&mut region, i)?;
+                        byte.copy_advice(|| "copy byte to mask", &mut region, advices[1], i)?;
+                        bit.copy_advice(|| "copy bit", &mut region, advices[2], i)?;
+                        region.assign_advice(
+                            || "assign masked byte",
+                            advices[0],
+                            i,
+                            || byte.value().zip(bit.value()).map(|(v, b)| *v * *b),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )
+    }
+
+    // Multiply v by x, i.e. shift right by one bit with the reduction.
+    fn mul_by_x(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        v: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let range_chip = U8RangeCheckChip::construct(self.config.u8_range_check_config);
+        let xor_chip = U8XorChip::construct(self.config.u8_xor_config);
+        let advices = self.config.advices;
+
+        let (first_shifted, reduction, mut shifted) = layouter.assign_region(
+            || "GF(2^128) mul by x",
+            |mut region| {
+                let mut highs = vec![];
+                let mut low_bits = vec![];
+                for (i, byte) in v.iter().enumerate() {
+                    self.config.q_decompose.enable(&mut region, i)?;
+                    let value = byte.value().map(|v| v.to_bytes()[0] as u64);
+                    highs.push(range_chip.assign_byte(&mut region, i, value.map(|v| v >> 1))?);
+                    byte.copy_advice(|| "copy byte to shift", &mut region, advices[1], i)?;
+                    low_bits.push(region.assign_advice(
+                        || "assign low bit",
+                        advices[2],
+                        i,
+                        || value.map(|v| Fp::from(v & 1)),
+                    )?);
+                }
+
+                let mut shifted = vec![];
+                for i in 1..16 {
+                    let offset = 15 + i;
+                    self.config.q_shift.enable(&mut region, offset)?;
+                    highs[i].copy_advice(|| "copy high bits", &mut region, advices[1], offset)?;
+                    low_bits[i - 1].copy_advice(
+                        || "copy carried bit",
+                        &mut region,
+                        advices[2],
+                        offset,
+                    )?;
+                    shifted.push(region.assign_advice(
+                        || "assign shifted byte",
+                        advices[0],
+                        offset,
+                        || {
+                            highs[i]
+                                .value()
+                                .zip(low_bits[i - 1].value())
+                                .map(|(h, l)| *h + *l * Fp::from(128))
+                        },
+                    )?);
+                }
+
+                let offset = 31;
+                self.config.q_reduce.enable(&mut region, offset)?;
+                low_bits[15].copy_advice(|| "copy low bit", &mut region, advices[1], offset)?;
+                let reduction = region.assign_advice(
+                    || "assign reduction",
+                    advices[0],
+                    offset,
+                    || {
+                        low_bits[15]
+                            .value()
+                            .map(|l| *l * Fp::from(GCM_REDUCTION as u64))
+                    },
+                )?;
+
+                Ok((highs[0].clone(), reduction, shifted))
+            },
+        )?;
+
+        let mut res = vec![xor_chip.xor(layouter, &first_shifted, &reduction)?];
+        res.append(&mut shifted);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{
+            circuit::{SimpleFloorPlanner, Value},
+            dev::MockProver,
+            plonk::{Circuit, TableColumn},
+        },
+        table::load_enc_full_table,
+        utils::cells_to_bytes,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        x: [u8; 16],
+        y: [u8; 16],
+        expected: [u8; 16],
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (Gf128MulConfig, U8RangeCheckConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+
+            let q_u8_range_check = meta.complex_selector();
+            let q_u8_xor = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+            let u8_xor_config = U8XorChip::configure(
+                meta, advices[0], advices[1], advices[2], q_u8_xor, tables[0], tables[1],
+                tables[2], tables[3],
+            );
+
+            (
+                Gf128MulChip::configure(meta, advices, u8_range_check_config, u8_xor_config),
+                u8_range_check_config,
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.2, 17)?;
+            let chip = Gf128MulChip::construct(config.0);
+            let range_chip = U8RangeCheckChip::construct(config.1);
+
+            let (x, y) = layouter.assign_region(
+                || "assign inputs",
+                |mut region| {
+                    let x = self
+                        .x
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &b)| {
+                            range_chip.assign_byte(&mut region, i, Value::known(b as u64))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    let y = self
+                        .y
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &b)| {
+                            range_chip.assign_byte(&mut region, 16 + i, Value::known(b as u64))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    Ok((x, y))
+                },
+            )?;
+
+            let z = chip.mul(&mut layouter, &x, &y)?;
+            cells_to_bytes(&z).assert_if_known(|bytes| bytes[..] == self.expected);
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    fn hex_to_block(hex: &str) -> [u8; 16] {
+        std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+    }
+
+    #[test]
+    fn test_mul() {
+        // H and the first GHASH step of the GCM test case 2 (zero key, zero plaintext)
+        let h = hex_to_block("66e94bd4ef8a2c3b884cfa59ca342b2e");
+        let cases = [
+            (
+                hex_to_block("0388dace60b6a392f328c2b971b2fe78"),
+                h,
+                hex_to_block("5e2ec746917062882c85b0685353deb7"),
+            ),
+            // 1 is 0x80 || 0^120 in the bit reflected representation
+            (hex_to_block("80000000000000000000000000000000"), h, h),
+            ([0u8; 16], h, [0u8; 16]),
+        ];
+
+        for (x, y, expected) in cases {
+            let circuit = TestCircuit { x, y, expected };
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            mock.assert_satisfied();
+        }
+    }
+}
@@ -1,16 +1,22 @@
+//! Multiplication in GF(2^8).
+//! `MulBy2Chip` and `MulBy3Chip` multiply by a constant using a table of the products,
+//! `GfMulChip` multiplies two witness bytes using the log and exp tables.
+
 use crate::{
     constant::{MUL_BY_2, MUL_BY_3},
+    fault::{inject, FaultSite},
     halo2_proofs::{
-        circuit::{AssignedCell, Layouter},
+        circuit::{AssignedCell, Layouter, Value},
         halo2curves::bn256::Fr as Fp,
         plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
         poly::Rotation,
     },
     table::Tag,
+    utils::{gf_exp, gf_log},
 };
 
 macro_rules! define_mul_chip {
-    ($chip_name:ident, $config_name:ident, $table:ty, $dict:expr, $n:expr, $tag:expr) => {
+    ($chip_name:ident, $config_name:ident, $table:ty, $dict:expr, $n:expr, $tag:expr, $site:expr) => {
         #[derive(Clone, Copy, Debug)]
         pub struct $config_name {
             x: Column<Advice>,
@@ -36,6 +42,23 @@ macro_rules! define_mul_chip {
                 tag_tab: TableColumn,
                 x_tab: TableColumn,
                 y_tab: TableColumn,
+            ) -> $config_name {
+                Self::configure_with_tag_offset(
+                    meta, x_col, y_col, selector, tag_tab, x_tab, y_tab, 0,
+                )
+            }
+
+            /// Same as `configure`, looking up the tag shifted by `tag_offset`,
+            /// see `TableColumns::configure_with_tag_offset`.
+            pub fn configure_with_tag_offset(
+                meta: &mut ConstraintSystem<Fp>,
+                x_col: Column<Advice>,
+                y_col: Column<Advice>,
+                selector: Selector,
+                tag_tab: TableColumn,
+                x_tab: TableColumn,
+                y_tab: TableColumn,
+                tag_offset: u64,
             ) -> $config_name {
                 meta.lookup("Check correct gf mul by $n", |meta| {
                     let q = meta.query_selector(selector);
@@ -43,7 +66,7 @@ macro_rules! define_mul_chip {
                     let y = meta.query_advice(y_col, Rotation::cur());
 
                     vec![
-                        (q.clone() * Fp::from($tag as u64), tag_tab),
+                        (q.clone() * Fp::from($tag.value(tag_offset)), tag_tab),
                         (q.clone() * x, x_tab),
                         (q * y, y_tab),
                     ]
@@ -60,6 +83,20 @@ macro_rules! define_mul_chip {
                 &self,
                 layouter: &mut impl Layouter<Fp>,
                 x: &AssignedCell<Fp, Fp>,
+            ) -> Result<AssignedCell<Fp, Fp>, Error> {
+                let y = x
+                    .value()
+                    .map(|v| Fp::from($dict[*v.to_bytes().first().unwrap() as usize] as u64));
+                self.mul_with(layouter, x, y)
+            }
+
+            /// `mul` with the given output instead of computing it from x,
+            /// e.g. taken from a `WitnessTrace`. The lookup still constrains it.
+            pub fn mul_with(
+                &self,
+                layouter: &mut impl Layouter<Fp>,
+                x: &AssignedCell<Fp, Fp>,
+                y: Value<Fp>,
             ) -> Result<AssignedCell<Fp, Fp>, Error> {
                 layouter.assign_region(
                     || "",
@@ -76,11 +113,7 @@ macro_rules! define_mul_chip {
                             || "assign y value for gf mul by $n",
                             self.config.y,
                             0,
-                            || {
-                                x.value().map(|v| {
-                                    Fp::from($dict[*v.to_bytes().first().unwrap() as usize] as u64)
-                                })
-                            },
+                            || inject($site, y),
                         );
 
                         Ok(y)
@@ -97,7 +130,8 @@ define_mul_chip!(
     PolyMulBy2TableConfig,
     MUL_BY_2,
     2,
-    Tag::GfMul2
+    Tag::GfMul2,
+    FaultSite::Mul2
 );
 define_mul_chip!(
     MulBy3Chip,
@@ -105,5 +139,294 @@ define_mul_chip!(
     PolyMulBy3TableConfig,
     MUL_BY_3,
     3,
-    Tag::GfMul3
+    Tag::GfMul3,
+    FaultSite::Mul3
 );
+
+#[derive(Clone, Copy, Debug)]
+pub struct GfMulConfig {
+    x: Column<Advice>,
+    log: Column<Advice>,
+    q: Selector,
+}
+
+/// Multiply two bytes as x * y = exp(log x + log y).
+/// Log of 0 is large enough that the sum with it always maps to 0 in the exp table.
+#[derive(Clone, Copy, Debug)]
+pub struct GfMulChip {
+    config: GfMulConfig,
+}
+
+impl GfMulChip {
+    pub fn construct(config: GfMulConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configure the chip with the layout
+    /// | x   | log x |
+    /// | y   | log y |
+    /// | sum | z     |
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        x_col: Column<Advice>,
+        log_col: Column<Advice>,
+        selector: Selector,
+        tag_tab: TableColumn,
+        in_tab: TableColumn,
+        out_tab: TableColumn,
+    ) -> GfMulConfig {
+        Self::configure_with_tag_offset(meta, x_col, log_col, selector, tag_tab, in_tab, out_tab, 0)
+    }
+
+    /// Same as `configure`, looking up the tags shifted by `tag_offset`,
+    /// see `TableColumns::configure_with_tag_offset`.
+    pub fn configure_with_tag_offset(
+        meta: &mut ConstraintSystem<Fp>,
+        x_col: Column<Advice>,
+        log_col: Column<Advice>,
+        selector: Selector,
+        tag_tab: TableColumn,
+        in_tab: TableColumn,
+        out_tab: TableColumn,
+        tag_offset: u64,
+    ) -> GfMulConfig {
+        for (name, rotation) in [("Check log of x", 0), ("Check log of y", 1)] {
+            meta.lookup(name, |meta| {
+                let q = meta.query_selector(selector);
+                let x = meta.query_advice(x_col, Rotation(rotation));
+                let log = meta.query_advice(log_col, Rotation(rotation));
+
+                vec![
+                    (q.clone() * Fp::from(Tag::GfLog.value(tag_offset)), tag_tab),
+                    (q.clone() * x, in_tab),
+                    (q * log, out_tab),
+                ]
+            });
+        }
+
+        meta.lookup("Check exp of sum of logs", |meta| {
+            let q = meta.query_selector(selector);
+            let sum = meta.query_advice(x_col, Rotation(2));
+            let z = meta.query_advice(log_col, Rotation(2));
+
+            vec![
+                (q.clone() * Fp::from(Tag::GfExp.value(tag_offset)), tag_tab),
+                (q.clone() * sum, in_tab),
+                (q * z, out_tab),
+            ]
+        });
+
+        meta.create_gate("Sum of logs", |meta| {
+            let q = meta.query_selector(selector);
+            let log_x = meta.query_advice(log_col, Rotation::cur());
+            let log_y = meta.query_advice(log_col, Rotation::next());
+            let sum = meta.query_advice(x_col, Rotation(2));
+
+            vec![q * (log_x + log_y - sum)]
+        });
+
+        GfMulConfig {
+            x: x_col,
+            log: log_col,
+            q: selector,
+        }
+    }
+
+    pub fn mul(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &AssignedCell<Fp, Fp>,
+        y: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "gf mul",
+            |mut region| {
+                self.config.q.enable(&mut region, 0)?;
+
+                let x_log = x.value().map(|v| gf_log(v.to_bytes()[0]));
+                let y_log = y.value().map(|v| gf_log(v.to_bytes()[0]));
+                let sum = x_log.zip(y_log).map(|(x, y)| x + y);
+
+                x.copy_advice(|| "assign x for gf mul", &mut region, self.config.x, 0)?;
+                region.assign_advice(
+                    || "assign log x for gf mul",
+                    self.config.log,
+                    0,
+                    || x_log.map(Fp::from),
+                )?;
+                y.copy_advice(|| "assign y for gf mul", &mut region, self.config.x, 1)?;
+                region.assign_advice(
+                    || "assign log y for gf mul",
+                    self.config.log,
+                    1,
+                    || y_log.map(Fp::from),
+                )?;
+                region.assign_advice(
+                    || "assign sum of logs for gf mul",
+                    self.config.x,
+                    2,
+                    || sum.map(Fp::from),
+                )?;
+                region.assign_advice(
+                    || "assign z for gf mul",
+                    self.config.log,
+                    2,
+                    || sum.map(|e| Fp::from(gf_exp(e) as u64)),
+                )
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        chips::u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+        halo2_proofs::{
+            circuit::{SimpleFloorPlanner, Value},
+            dev::MockProver,
+            plonk::Circuit,
+        },
+        table::load_enc_full_table,
+        utils::gf_mul,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        pairs: Vec<(u8, u8)>,
+        // Claim this product for the first pair instead of the computed one
+        malicious_z: Option<u8>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (GfMulConfig, U8RangeCheckConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+            advices
+                .iter()
+                .for_each(|&advice| meta.enable_equality(advice));
+
+            let q_u8_range_check = meta.complex_selector();
+            let q_gf_mul = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+
+            (
+                GfMulChip::configure(
+                    meta, advices[0], advices[1], q_gf_mul, tables[0], tables[1], tables[2],
+                ),
+                u8_range_check_config,
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.2, 17)?;
+            let gf_mul_chip = GfMulChip::construct(config.0);
+            let range_chip = U8RangeCheckChip::construct(config.1);
+
+            for (i, &(x, y)) in self.pairs.iter().enumerate() {
+                let (x, y) = layouter.assign_region(
+                    || "assign inputs",
+                    |mut region| {
+                        let x = range_chip.assign_byte(&mut region, 0, Value::known(x as u64))?;
+                        let y = range_chip.assign_byte(&mut region, 1, Value::known(y as u64))?;
+                        Ok((x, y))
+                    },
+                )?;
+
+                let z = match self.malicious_z {
+                    Some(z) if i == 0 => layouter.assign_region(
+                        || "malicious gf mul",
+                        |mut region| {
+                            let config = config.0;
+                            config.q.enable(&mut region, 0)?;
+                            x.copy_advice(|| "", &mut region, config.x, 0)?;
+                            y.copy_advice(|| "", &mut region, config.x, 1)?;
+                            let x_log = x.value().map(|v| gf_log(v.to_bytes()[0]));
+                            let y_log = y.value().map(|v| gf_log(v.to_bytes()[0]));
+                            region.assign_advice(|| "", config.log, 0, || x_log.map(Fp::from))?;
+                            region.assign_advice(|| "", config.log, 1, || y_log.map(Fp::from))?;
+                            region.assign_advice(
+                                || "",
+                                config.x,
+                                2,
+                                || x_log.zip(y_log).map(|(x, y)| Fp::from(x + y)),
+                            )?;
+                            region.assign_advice(
+                                || "",
+                                config.log,
+                                2,
+                                || Value::known(Fp::from(z as u64)),
+                            )
+                        },
+                    )?,
+                    _ => gf_mul_chip.mul(&mut layouter, &x, &y)?,
+                };
+
+                z.value()
+                    .zip(x.value().zip(y.value()))
+                    .assert_if_known(|(z, (x, y))| {
+                        self.malicious_z.is_some()
+                            || z.to_bytes()[0] == gf_mul(x.to_bytes()[0], y.to_bytes()[0])
+                    });
+            }
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_gf_mul() {
+        // All products with x in a few bytes including 0, and all the squares
+        let pairs = [0u8, 1, 2, 3, 0x57, 0x83, 0xff]
+            .iter()
+            .flat_map(|&x| (0..=255u8).map(move |y| (x, y)))
+            .chain((0..=255u8).map(|x| (x, x)))
+            .collect::<Vec<_>>();
+        let circuit = TestCircuit {
+            pairs,
+            malicious_z: None,
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[test]
+    fn test_gf_mul_wrong_product_fails() {
+        for (pair, z) in [((0x57, 0x83), 0xc0), ((0, 0x83), 1), ((0x57, 0), 0x57)] {
+            let circuit = TestCircuit {
+                pairs: vec![pair],
+                malicious_z: Some(z),
+            };
+
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert!(mock.verify().is_err());
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Byte maps of the inverse cipher: the inverse S-box of InvSubBytes and
+//! the multiplications by 9, 11, 13 and 14 of InvMixColumns.
+//!
+//! Each map is looked up in its sub-table of the tagged table: `Tag::InvSbox`,
+//! `Tag::GfMul9`, `Tag::GfMul11`, `Tag::GfMul13` and `Tag::GfMul14`.
+//! The five maps share one lookup argument, the tag of a row being the sum of the selectors
+//! times their tags, as only one of them is enabled on a row. So the inverse cipher adds
+//! a single lookup argument to each group of columns, not one per map.
+
+use crate::{
+    constant::{INV_S_BOX, MUL_BY_11, MUL_BY_13, MUL_BY_14, MUL_BY_9},
+    error::AesError,
+    fault::{inject, FaultSite},
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector, TableColumn},
+        poly::Rotation,
+    },
+    table::Tag,
+};
+
+// Coefficients of InvMixColumns with their sub-tables, in the order of the selectors
+const MULS: [(u32, Tag, [u8; 256]); 4] = [
+    (9, Tag::GfMul9, MUL_BY_9),
+    (11, Tag::GfMul11, MUL_BY_11),
+    (13, Tag::GfMul13, MUL_BY_13),
+    (14, Tag::GfMul14, MUL_BY_14),
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct InvCipherConfig {
+    x: Column<Advice>,
+    y: Column<Advice>,
+    q_inv_sbox: Selector,
+    q_mul: [Selector; 4],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct InvCipherChip {
+    config: InvCipherConfig,
+}
+
+impl InvCipherChip {
+    pub fn construct(config: InvCipherConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        x_col: Column<Advice>,
+        y_col: Column<Advice>,
+        tag_tab: TableColumn,
+        x_tab: TableColumn,
+        y_tab: TableColumn,
+    ) -> InvCipherConfig {
+        Self::configure_with_tag_offset(meta, x_col, y_col, tag_tab, x_tab, y_tab, 0)
+    }
+
+    /// Same as `configure`, looking up the tags shifted by `tag_offset`,
+    /// see `TableColumns::configure_with_tag_offset`.
+    pub fn configure_with_tag_offset(
+        meta: &mut ConstraintSystem<Fp>,
+        x_col: Column<Advice>,
+        y_col: Column<Advice>,
+        tag_tab: TableColumn,
+        x_tab: TableColumn,
+        y_tab: TableColumn,
+        tag_offset: u64,
+    ) -> InvCipherConfig {
+        let q_inv_sbox = meta.complex_selector();
+        let q_mul = std::array::from_fn(|_| meta.complex_selector());
+
+        meta.lookup("Check correct inverse cipher byte map", |meta| {
+            let x = meta.query_advice(x_col, Rotation::cur());
+            let y = meta.query_advice(y_col, Rotation::cur());
+
+            let selectors = std::iter::once((q_inv_sbox, Tag::InvSbox))
+                .chain(q_mul.iter().zip(MULS).map(|(&q, (_, tag, _))| (q, tag)));
+            let zero = || Expression::Constant(Fp::from(0));
+            let (tag, q) = selectors.fold((zero(), zero()), |(tag, q_any), (q, tag_of_q)| {
+                let q = meta.query_selector(q);
+                (
+                    tag + q.clone() * Fp::from(tag_of_q.value(tag_offset)),
+                    q_any + q,
+                )
+            });
+
+            vec![(tag, tag_tab), (q.clone() * x, x_tab), (q * y, y_tab)]
+        });
+
+        InvCipherConfig {
+            x: x_col,
+            y: y_col,
+            q_inv_sbox,
+            q_mul,
+        }
+    }
+
+    /// Inverse S-box of x, for InvSubBytes.
+    pub fn inv_substitute(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let y = x
+            .value()
+            .map(|v| Fp::from(INV_S_BOX[v.to_bytes()[0] as usize] as u64));
+        self.assign(layouter, self.config.q_inv_sbox, x, y)
+    }
+
+    /// Multiply x by the coefficient of InvMixColumns, 9, 11, 13 or 14.
+    /// Other coefficients fail with `AesError::UnsupportedCoefficient`.
+    pub fn mul(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &AssignedCell<Fp, Fp>,
+        coeff: u32,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let (q, products) = self
+            .config
+            .q_mul
+            .iter()
+            .zip(MULS)
+            .find(|(_, (c, _, _))| *c == coeff)
+            .map(|(&q, (_, _, products))| (q, products))
+            .ok_or(AesError::UnsupportedCoefficient { coeff })?;
+
+        let y = x
+            .value()
+            .map(|v| Fp::from(products[v.to_bytes()[0] as usize] as u64));
+        self.assign(layouter, q, x, y)
+    }
+
+    // Copy x and assign y in a row with the selector of the map enabled
+    fn assign(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        q: Selector,
+        x: &AssignedCell<Fp, Fp>,
+        y: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "",
+            |mut region| {
+                q.enable(&mut region, 0)?;
+                x.copy_advice(
+                    || "assign x value for inverse cipher",
+                    &mut region,
+                    self.config.x,
+                    0,
+                )?;
+
+                region.assign_advice(
+                    || "assign y value for inverse cipher",
+                    self.config.y,
+                    0,
+                    || inject(FaultSite::InvCipher, y),
+                )
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        chips::u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+        halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit},
+        table::load_dec_full_table,
+        utils::gf_mul,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        // Claim this output of the mul by the coefficient for every input byte
+        malicious: Option<(u32, u8)>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (InvCipherConfig, U8RangeCheckConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+            advices
+                .iter()
+                .for_each(|&advice| meta.enable_equality(advice));
+
+            let q_u8_range_check = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+
+            (
+                InvCipherChip::configure(
+                    meta, advices[0], advices[1], tables[0], tables[1], tables[2],
+                ),
+                u8_range_check_config,
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_dec_full_table(&mut layouter, config.2, 17)?;
+            let chip = InvCipherChip::construct(config.0);
+            let range_chip = U8RangeCheckChip::construct(config.1);
+
+            for x in [0u8, 1, 0x52, 0x63, 0xed, 0xff] {
+                let x = layouter.assign_region(
+                    || "assign input",
+                    |mut region| range_chip.assign_byte(&mut region, 0, Value::known(x as u64)),
+                )?;
+
+                let y = chip.inv_substitute(&mut layouter, &x)?;
+                x.value().zip(y.value()).assert_if_known(|(x, y)| {
+                    y.to_bytes()[0] == INV_S_BOX[x.to_bytes()[0] as usize]
+                });
+
+                for (coeff, _, _) in MULS {
+                    let y = match self.malicious {
+                        Some((c, y)) if c == coeff => chip.assign(
+                            &mut layouter,
+                            config.0.q_mul[MULS.iter().position(|m| m.0 == c).unwrap()],
+                            &x,
+                            Value::known(Fp::from(y as u64)),
+                        )?,
+                        _ => chip.mul(&mut layouter, &x, coeff)?,
+                    };
+                    x.value().zip(y.value()).assert_if_known(|(x, y)| {
+                        self.malicious.is_some()
+                            || y.to_bytes()[0] == gf_mul(x.to_bytes()[0], coeff as u8)
+                    });
+                }
+            }
+
+            let x = layouter.assign_region(
+                || "assign input",
+                |mut region| range_chip.assign_byte(&mut region, 0, Value::known(1)),
+            )?;
+            assert!(matches!(
+                chip.mul(&mut layouter, &x, 2),
+                Err(Error::Synthesis)
+            ));
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_inv_cipher_maps() {
+        let mock = MockProver::run(17, &TestCircuit { malicious: None }, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[test]
+    fn test_inv_cipher_wrong_product_fails() {
+        // The product by another coefficient, looked up under the wrong tag
+        for (coeff, y) in [(9, gf_mul(0xff, 11)), (14, 0)] {
+            let circuit = TestCircuit {
+                malicious: Some((coeff, y)),
+            };
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert!(mock.verify().is_err());
+        }
+    }
+}
@@ -1,4 +1,13 @@
+pub mod byte_pack_chip;
+pub mod counter_chip;
+pub mod gf128_double_chip;
+pub mod gf128_mul_chip;
 pub mod gf_mul_chip;
+pub mod inv_cipher_chip;
+pub mod not_equal_chip;
 pub mod sbox_chip;
+pub mod select_chip;
+pub mod u32_decompose_chip;
+pub mod u64_decompose_chip;
 pub mod u8_range_check_chip;
 pub mod u8_xor_chip;
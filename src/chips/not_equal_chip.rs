@@ -0,0 +1,210 @@
+//! Inequality of two blocks of bytes, i.e. at least one of the bytes differs.
+//!
+//! The differences of the bytes are combined as D = sum (x_i - y_i) * 256^(15 - i).
+//! As the bytes are range checked, |D| < 2^128, far below the modulus,
+//! so D is zero iff all the bytes are equal. D != 0 is constrained by
+//! the witness of its inverse, D * inv = 1.
+
+use crate::halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Value},
+    halo2curves::bn256::Fr as Fp,
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+    poly::Rotation,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct NotEqualConfig {
+    advices: [Column<Advice>; 3],
+    q_first: Selector,
+    q_next: Selector,
+    q_inv: Selector,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NotEqualChip {
+    config: NotEqualConfig,
+}
+
+impl NotEqualChip {
+    pub fn construct(config: NotEqualConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 3],
+    ) -> NotEqualConfig {
+        let q_first = meta.selector();
+        let q_next = meta.selector();
+        let q_inv = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | x_i | y_i | acc_i |
+        meta.create_gate("Not equal first", |meta| {
+            let q = meta.query_selector(q_first);
+            let x = meta.query_advice(advices[0], Rotation::cur());
+            let y = meta.query_advice(advices[1], Rotation::cur());
+            let acc = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![q * (acc - (x - y))]
+        });
+
+        meta.create_gate("Not equal next", |meta| {
+            let q = meta.query_selector(q_next);
+            let x = meta.query_advice(advices[0], Rotation::cur());
+            let y = meta.query_advice(advices[1], Rotation::cur());
+            let acc = meta.query_advice(advices[2], Rotation::cur());
+            let acc_prev = meta.query_advice(advices[2], Rotation::prev());
+
+            vec![q * (acc - acc_prev * Fp::from(256) - (x - y))]
+        });
+
+        // | ...    |  | acc |
+        // | inv    |  |     |
+        meta.create_gate("Not equal inverse", |meta| {
+            let q = meta.query_selector(q_inv);
+            let acc = meta.query_advice(advices[2], Rotation::cur());
+            let inv = meta.query_advice(advices[0], Rotation::next());
+
+            vec![q * (acc * inv - Expression::Constant(Fp::from(1)))]
+        });
+
+        NotEqualConfig {
+            advices,
+            q_first,
+            q_next,
+            q_inv,
+        }
+    }
+
+    /// Constrain that at least one byte of `x` differs from the byte of `y` at the same position.
+    /// Both should be range checked bytes of the same length, at most 16.
+    /// Unsatisfiable if they are equal.
+    pub fn assert_not_equal(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &[AssignedCell<Fp, Fp>],
+        y: &[AssignedCell<Fp, Fp>],
+    ) -> Result<(), Error> {
+        assert_eq!(x.len(), y.len(), "Compare the bytes of the same length");
+        assert!(
+            !x.is_empty() && x.len() <= 16,
+            "Compare 1 to 16 bytes, D would wrap around the modulus otherwise"
+        );
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Assert not equal",
+            |mut region| {
+                let mut acc = Value::known(Fp::from(0));
+                for (i, (x, y)) in x.iter().zip(y.iter()).enumerate() {
+                    if i == 0 {
+                        self.config.q_first.enable(&mut region, i)?;
+                    } else {
+                        self.config.q_next.enable(&mut region, i)?;
+                    }
+                    let x = x.copy_advice(|| "copy x", &mut region, advices[0], i)?;
+                    let y = y.copy_advice(|| "copy y", &mut region, advices[1], i)?;
+
+                    acc =
+                        acc * Value::known(Fp::from(256)) + x.value().copied() - y.value().copied();
+                    region.assign_advice(|| "assign acc", advices[2], i, || acc)?;
+                }
+
+                let last = x.len() - 1;
+                self.config.q_inv.enable(&mut region, last)?;
+                // Zero has no inverse, leaving the constraint unsatisfied
+                let inv = acc.map(|acc| Option::from(acc.invert()).unwrap_or(Fp::from(0)));
+                region.assign_advice(|| "assign inverse", advices[0], last + 1, || inv)?;
+
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit};
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        x: [u8; 16],
+        y: [u8; 16],
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = NotEqualConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            NotEqualChip::configure(meta, advices)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let (x, y) = layouter.assign_region(
+                || "Assign bytes",
+                |mut region| {
+                    let mut assign = |bytes: &[u8; 16], column| {
+                        bytes
+                            .iter()
+                            .enumerate()
+                            .map(|(i, &b)| {
+                                region.assign_advice(
+                                    || "assign byte",
+                                    column,
+                                    i,
+                                    || Value::known(Fp::from(b as u64)),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, Error>>()
+                    };
+                    let x = assign(&self.x, config.advices[0])?;
+                    let y = assign(&self.y, config.advices[1])?;
+                    Ok((x, y))
+                },
+            )?;
+
+            NotEqualChip::construct(config).assert_not_equal(&mut layouter, &x, &y)
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_not_equal() {
+        let x = [0xa5; 16];
+        for i in [0, 7, 15] {
+            for delta in [1, 0x80, 0xff] {
+                let mut y = x;
+                y[i] ^= delta;
+                let mock = MockProver::run(8, &TestCircuit { x, y }, vec![]).unwrap();
+                mock.assert_satisfied();
+            }
+        }
+    }
+
+    #[test]
+    fn test_equal_fails() {
+        let x = [0xa5; 16];
+        let mock = MockProver::run(8, &TestCircuit { x, y: x }, vec![]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
+    fault::{inject, FaultSite},
     halo2_proofs::{
-        circuit::{AssignedCell, Layouter},
+        circuit::{AssignedCell, Layouter, Value},
         halo2curves::bn256::Fr as Fp,
         plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
         poly::Rotation,
@@ -34,6 +35,21 @@ impl SboxChip {
         tag_tab: TableColumn,
         x_tab: TableColumn,
         y_tab: TableColumn,
+    ) -> SboxConfig {
+        Self::configure_with_tag_offset(meta, x_col, y_col, selector, tag_tab, x_tab, y_tab, 0)
+    }
+
+    /// Same as `configure`, looking up the tag shifted by `tag_offset`,
+    /// see `TableColumns::configure_with_tag_offset`.
+    pub fn configure_with_tag_offset(
+        meta: &mut ConstraintSystem<Fp>,
+        x_col: Column<Advice>,
+        y_col: Column<Advice>,
+        selector: Selector,
+        tag_tab: TableColumn,
+        x_tab: TableColumn,
+        y_tab: TableColumn,
+        tag_offset: u64,
     ) -> SboxConfig {
         meta.lookup("Check correct Sbox substitution", |meta| {
             let q = meta.query_selector(selector);
@@ -41,7 +57,7 @@ impl SboxChip {
             let y = meta.query_advice(y_col, Rotation::cur());
 
             vec![
-                (q.clone() * Fp::from(Tag::Sbox as u64), tag_tab),
+                (q.clone() * Fp::from(Tag::Sbox.value(tag_offset)), tag_tab),
                 (q.clone() * x, x_tab),
                 (q * y, y_tab),
             ]
@@ -58,12 +74,23 @@ impl SboxChip {
         &self,
         layouter: &mut impl Layouter<Fp>,
         x: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        self.substitute_with(layouter, x, sub_byte(&x.value().copied()))
+    }
+
+    /// `substitute` with the given output instead of computing it from x,
+    /// e.g. taken from a `WitnessTrace`. The lookup still constrains it.
+    pub fn substitute_with(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &AssignedCell<Fp, Fp>,
+        y: Value<Fp>,
     ) -> Result<AssignedCell<Fp, Fp>, Error> {
         layouter.assign_region(
             || "",
             |mut region| {
                 self.config.q.enable(&mut region, 0)?;
-                let x_copied = x.copy_advice(
+                x.copy_advice(
                     || "assign x value for sbox_sub",
                     &mut region,
                     self.config.x,
@@ -74,7 +101,7 @@ impl SboxChip {
                     || "assign y value for sbox_sub",
                     self.config.y,
                     0,
-                    || sub_byte(&x_copied.value_field().evaluate()),
+                    || inject(FaultSite::Sbox, y),
                 );
 
                 Ok(y)
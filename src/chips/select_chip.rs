@@ -0,0 +1,422 @@
+//! Selection of one of several values by an index.
+//!
+//! The index is turned into one-hot bits s_j constrained by
+//! s_j * (1 - s_j) = 0, s_j * (index - j) = 0 and sum s_j = 1,
+//! so that exactly the bit at the index is set, and an index out of range is unsatisfiable.
+//! A selected value is sum s_j * v_j accumulated down the rows.
+//!
+//! Between two values, `mux` takes a boolean cell instead: out = b + sel * (a - b),
+//! with sel * (1 - sel) = 0, i.e. a if sel is 1 and b if sel is 0.
+//!
+//! NOTE: The constants are assigned with `assign_advice_from_constant`,
+//! so a constant column must be enabled in the constraint system.
+
+use crate::{
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+        poly::Rotation,
+    },
+    utils::assert_byte_equals,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct SelectConfig {
+    advices: [Column<Advice>; 3],
+    q_bit: Selector,
+    q_first: Selector,
+    q_next: Selector,
+    q_mux: Selector,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SelectChip {
+    config: SelectConfig,
+}
+
+impl SelectChip {
+    pub fn construct(config: SelectConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 3],
+    ) -> SelectConfig {
+        let q_bit = meta.selector();
+        let q_first = meta.selector();
+        let q_next = meta.selector();
+        let q_mux = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | s_j | j | index |
+        meta.create_gate("Select bit", |meta| {
+            let q = meta.query_selector(q_bit);
+            let s = meta.query_advice(advices[0], Rotation::cur());
+            let j = meta.query_advice(advices[1], Rotation::cur());
+            let index = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![
+                q.clone() * s.clone() * (Expression::Constant(Fp::from(1)) - s.clone()),
+                q * s * (index - j),
+            ]
+        });
+
+        // | v_j | s_j | acc_j |
+        meta.create_gate("Select first", |meta| {
+            let q = meta.query_selector(q_first);
+            let v = meta.query_advice(advices[0], Rotation::cur());
+            let s = meta.query_advice(advices[1], Rotation::cur());
+            let acc = meta.query_advice(advices[2], Rotation::cur());
+
+            vec![q * (acc - v * s)]
+        });
+
+        meta.create_gate("Select next", |meta| {
+            let q = meta.query_selector(q_next);
+            let v = meta.query_advice(advices[0], Rotation::cur());
+            let s = meta.query_advice(advices[1], Rotation::cur());
+            let acc = meta.query_advice(advices[2], Rotation::cur());
+            let acc_prev = meta.query_advice(advices[2], Rotation::prev());
+
+            vec![q * (acc - acc_prev - v * s)]
+        });
+
+        // | a   | b | sel |
+        // | out |   |     |
+        meta.create_gate("Mux", |meta| {
+            let q = meta.query_selector(q_mux);
+            let a = meta.query_advice(advices[0], Rotation::cur());
+            let b = meta.query_advice(advices[1], Rotation::cur());
+            let sel = meta.query_advice(advices[2], Rotation::cur());
+            let out = meta.query_advice(advices[0], Rotation::next());
+
+            vec![
+                q.clone() * sel.clone() * (Expression::Constant(Fp::from(1)) - sel.clone()),
+                q * (out - b.clone() - sel * (a - b)),
+            ]
+        });
+
+        SelectConfig {
+            advices,
+            q_bit,
+            q_first,
+            q_next,
+            q_mux,
+        }
+    }
+
+    /// Assign the index to be selected.
+    pub fn assign_index(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        index: Value<u64>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "Assign select index",
+            |mut region| {
+                region.assign_advice(
+                    || "assign index",
+                    self.config.advices[2],
+                    0,
+                    || index.map(Fp::from),
+                )
+            },
+        )
+    }
+
+    /// One-hot bits of the index among `count` choices.
+    pub fn select_bits(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        index: &AssignedCell<Fp, Fp>,
+        count: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let advices = self.config.advices;
+
+        let bits = layouter.assign_region(
+            || "Select bits",
+            |mut region| {
+                (0..count)
+                    .map(|j| {
+                        self.config.q_bit.enable(&mut region, j)?;
+                        let j_field = Fp::from(j as u64);
+                        region.assign_advice_from_constant(
+                            || "assign j",
+                            advices[1],
+                            j,
+                            j_field,
+                        )?;
+                        index.copy_advice(|| "copy index", &mut region, advices[2], j)?;
+                        region.assign_advice(
+                            || "assign bit",
+                            advices[0],
+                            j,
+                            || {
+                                index.value().map(|index| {
+                                    if *index == j_field {
+                                        Fp::from(1)
+                                    } else {
+                                        Fp::from(0)
+                                    }
+                                })
+                            },
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )?;
+
+        // Exactly one bit is set
+        let one = layouter.assign_region(
+            || "Assign one",
+            |mut region| {
+                region.assign_advice_from_constant(|| "assign one", advices[0], 0, Fp::from(1))
+            },
+        )?;
+        let sum = self.select(layouter, &bits, &vec![&one; count])?;
+        assert_byte_equals(layouter, &sum, 1)?;
+
+        Ok(bits)
+    }
+
+    /// Select the value at the index given by the one-hot bits.
+    pub fn select(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bits: &[AssignedCell<Fp, Fp>],
+        values: &[&AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        assert_eq!(bits.len(), values.len(), "Each value needs a bit");
+        assert!(!values.is_empty(), "Select from at least one value");
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Select value",
+            |mut region| {
+                let mut acc: Option<AssignedCell<Fp, Fp>> = None;
+                for (j, (bit, value)) in bits.iter().zip(values.iter()).enumerate() {
+                    if j == 0 {
+                        self.config.q_first.enable(&mut region, j)?;
+                    } else {
+                        self.config.q_next.enable(&mut region, j)?;
+                    }
+                    let v = value.copy_advice(|| "copy value", &mut region, advices[0], j)?;
+                    let s = bit.copy_advice(|| "copy bit", &mut region, advices[1], j)?;
+
+                    let prev = acc
+                        .as_ref()
+                        .map_or(Value::known(Fp::from(0)), |acc| acc.value().copied());
+                    let next = prev + v.value().copied() * s.value().copied();
+                    acc = Some(region.assign_advice(|| "assign acc", advices[2], j, || next)?);
+                }
+
+                Ok(acc.unwrap())
+            },
+        )
+    }
+
+    /// `a` if `sel` is 1 and `b` if `sel` is 0, where `sel` is constrained to be boolean.
+    /// The output is a byte if both `a` and `b` are.
+    pub fn mux(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        sel: &AssignedCell<Fp, Fp>,
+        a: &AssignedCell<Fp, Fp>,
+        b: &AssignedCell<Fp, Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Mux",
+            |mut region| {
+                self.config.q_mux.enable(&mut region, 0)?;
+                let a = a.copy_advice(|| "copy a", &mut region, advices[0], 0)?;
+                let b = b.copy_advice(|| "copy b", &mut region, advices[1], 0)?;
+                let sel = sel.copy_advice(|| "copy sel", &mut region, advices[2], 0)?;
+
+                let out = b.value().copied()
+                    + sel.value().copied() * (a.value().copied() - b.value().copied());
+                region.assign_advice(|| "assign out", advices[0], 1, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        dev::MockProver,
+        plonk::{Circuit, Instance},
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        values: Vec<u64>,
+        index: u64,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (SelectConfig, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            (SelectChip::configure(meta, advices), instance)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = SelectChip::construct(config.0);
+
+            let values = layouter.assign_region(
+                || "Assign values",
+                |mut region| {
+                    self.values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &v)| {
+                            region.assign_advice_from_constant(
+                                || "assign value",
+                                config.0.advices[0],
+                                i,
+                                Fp::from(v),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+            let index = chip.assign_index(&mut layouter, Value::known(self.index))?;
+            layouter.constrain_instance(index.cell(), config.1, 0)?;
+
+            let bits = chip.select_bits(&mut layouter, &index, values.len())?;
+            let selected = chip.select(&mut layouter, &bits, &values.iter().collect::<Vec<_>>())?;
+            layouter.constrain_instance(selected.cell(), config.1, 1)?;
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let values = vec![7, 11, 13];
+        for (index, &value) in values.iter().enumerate() {
+            let circuit = TestCircuit {
+                values: values.clone(),
+                index: index as u64,
+            };
+            let instance = vec![Fp::from(index as u64), Fp::from(value)];
+            let mock = MockProver::run(8, &circuit, vec![instance]).unwrap();
+            mock.assert_satisfied();
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestMuxCircuit {
+        sel: u64,
+        a: u64,
+        b: u64,
+    }
+
+    impl Circuit<Fp> for TestMuxCircuit {
+        type Config = (SelectConfig, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            TestCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let chip = SelectChip::construct(config.0);
+
+            let [sel, a, b] = layouter.assign_region(
+                || "Assign inputs",
+                |mut region| {
+                    let cells = [self.sel, self.a, self.b]
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &v)| {
+                            region.assign_advice(
+                                || "assign input",
+                                config.0.advices[0],
+                                i,
+                                || Value::known(Fp::from(v)),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    Ok([cells[0].clone(), cells[1].clone(), cells[2].clone()])
+                },
+            )?;
+            let out = chip.mux(&mut layouter, &sel, &a, &b)?;
+            layouter.constrain_instance(out.cell(), config.1, 0)?;
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_mux() {
+        for (a, b) in [(0x12, 0xab), (0xff, 0), (7, 7)] {
+            for sel in [0, 1] {
+                let circuit = TestMuxCircuit { sel, a, b };
+                let expected = if sel == 1 { a } else { b };
+                let mock = MockProver::run(8, &circuit, vec![vec![Fp::from(expected)]]).unwrap();
+                mock.assert_satisfied();
+            }
+        }
+    }
+
+    #[test]
+    fn test_mux_non_boolean_selector_fails() {
+        // out = b + 2 * (a - b) satisfies the mux equation, but sel isn't boolean
+        let circuit = TestMuxCircuit {
+            sel: 2,
+            a: 0x12,
+            b: 0x10,
+        };
+        let mock = MockProver::run(8, &circuit, vec![vec![Fp::from(0x14)]]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+
+    #[test]
+    fn test_select_index_out_of_range_fails() {
+        let circuit = TestCircuit {
+            values: vec![7, 11, 13],
+            index: 3,
+        };
+        let mock = MockProver::run(8, &circuit, vec![vec![Fp::from(3), Fp::from(0)]]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+}
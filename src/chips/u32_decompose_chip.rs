@@ -0,0 +1,247 @@
+//! Decomposition of a 32 bit word into its 4 little-endian bytes,
+//! e.g. to use the outputs of the T-tables in the byte oriented operations.
+//!
+//! word = b0 + 256 * b1 + 65536 * b2 + 2^24 * b3, where each byte is range checked.
+
+use crate::{
+    chips::u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+        poly::Rotation,
+    },
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct U32DecomposeConfig {
+    advices: [Column<Advice>; 2],
+    q_compose: Selector,
+
+    u8_range_check_config: U8RangeCheckConfig,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct U32DecomposeChip {
+    config: U32DecomposeConfig,
+}
+
+impl U32DecomposeChip {
+    pub fn construct(config: U32DecomposeConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configure u32 decompose chip.
+    /// advices[0] should be the column range checked by `u8_range_check_config`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 2],
+        u8_range_check_config: U8RangeCheckConfig,
+    ) -> U32DecomposeConfig {
+        let q_compose = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | b0 | word |
+        // | b1 |      |
+        // | b2 |      |
+        // | b3 |      |
+        meta.create_gate("Compose u32 word", |meta| {
+            let q = meta.query_selector(q_compose);
+            let word = meta.query_advice(advices[1], Rotation::cur());
+            let composed = (0..4)
+                .rev()
+                .fold(Expression::Constant(Fp::from(0)), |acc, i| {
+                    acc * Fp::from(256) + meta.query_advice(advices[0], Rotation(i))
+                });
+
+            vec![q * (word - composed)]
+        });
+
+        U32DecomposeConfig {
+            advices,
+            q_compose,
+            u8_range_check_config,
+        }
+    }
+
+    /// Decompose the word into its 4 bytes, least significant first.
+    pub fn decompose(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        word: &AssignedCell<Fp, Fp>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let bytes = word.value().map(|word| {
+            let word = u32::from_le_bytes(word.to_bytes()[..4].try_into().unwrap());
+            word.to_le_bytes().map(|b| b as u64)
+        });
+
+        self.assign_decomposition(layouter, word, bytes)
+    }
+
+    /// Compose the word from 4 range checked bytes, least significant first.
+    pub fn compose(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        assert_eq!(bytes.len(), 4, "Word should be 4 bytes");
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Compose u32 word",
+            |mut region| {
+                self.config.q_compose.enable(&mut region, 0)?;
+
+                let mut word = Value::known(Fp::from(0));
+                for (i, byte) in bytes.iter().enumerate().rev() {
+                    byte.copy_advice(|| "copy byte", &mut region, advices[0], i)?;
+                    word = word * Value::known(Fp::from(256)) + byte.value().copied();
+                }
+
+                region.assign_advice(|| "assign word", advices[1], 0, || word)
+            },
+        )
+    }
+
+    /// Assign the given bytes as the decomposition of the word.
+    fn assign_decomposition(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        word: &AssignedCell<Fp, Fp>,
+        bytes: Value<[u64; 4]>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let range_chip = U8RangeCheckChip::construct(self.config.u8_range_check_config);
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Decompose u32 word",
+            |mut region| {
+                self.config.q_compose.enable(&mut region, 0)?;
+                word.copy_advice(|| "copy word", &mut region, advices[1], 0)?;
+
+                (0..4)
+                    .map(|i| range_chip.assign_byte(&mut region, i, bytes.map(|bytes| bytes[i])))
+                    .collect()
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{
+            circuit::SimpleFloorPlanner,
+            dev::MockProver,
+            plonk::{Circuit, TableColumn},
+        },
+        table::load_enc_full_table,
+        utils::{assert_cells_equal, cells_to_bytes},
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        word: u64,
+        // Decompose into these bytes instead of the bytes of the word
+        malicious_bytes: Option<[u64; 4]>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (U32DecomposeConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+
+            let q_u8_range_check = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+
+            (
+                U32DecomposeChip::configure(meta, advices, u8_range_check_config),
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+
+            let chip = U32DecomposeChip::construct(config.0);
+            let word = layouter.assign_region(
+                || "assign word",
+                |mut region| {
+                    region.assign_advice(
+                        || "assign word",
+                        config.0.advices[1],
+                        0,
+                        || Value::known(Fp::from(self.word)),
+                    )
+                },
+            )?;
+
+            let bytes = match self.malicious_bytes {
+                Some(bytes) => {
+                    chip.assign_decomposition(&mut layouter, &word, Value::known(bytes))?
+                }
+                None => chip.decompose(&mut layouter, &word)?,
+            };
+            cells_to_bytes(&bytes).assert_if_known(|bytes| {
+                self.malicious_bytes.is_some() || bytes[..] == (self.word as u32).to_le_bytes()
+            });
+
+            let composed = chip.compose(&mut layouter, &bytes)?;
+            assert_cells_equal(&mut layouter, &word, &composed)?;
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_decompose_round_trip() {
+        for word in [0, 1, 0xff, 0x100, 0x01020304, 0xdeadbeef, 0xffffffff] {
+            let circuit = TestCircuit {
+                word,
+                malicious_bytes: None,
+            };
+
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            mock.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_decompose_wrong_bytes_fails() {
+        let circuit = TestCircuit {
+            word: 0x01020304,
+            malicious_bytes: Some([0x04, 0x03, 0x02, 0x02]),
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+}
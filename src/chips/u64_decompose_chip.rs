@@ -0,0 +1,233 @@
+//! Decomposition of a field element packing 8 bytes into its little-endian bytes,
+//! e.g. to take the plaintext packed upstream into fewer cells.
+//!
+//! packed = b0 + 256 * b1 + ... + 2^56 * b7, where each byte is range checked.
+//! The sum is less than 2^64, far below the modulus, so a packed value of 2^64 or more
+//! has no decomposition and the bytes of a valid one are unique.
+
+use crate::{
+    chips::u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+        poly::Rotation,
+    },
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct U64DecomposeConfig {
+    advices: [Column<Advice>; 2],
+    q_compose: Selector,
+
+    u8_range_check_config: U8RangeCheckConfig,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct U64DecomposeChip {
+    config: U64DecomposeConfig,
+}
+
+impl U64DecomposeChip {
+    pub fn construct(config: U64DecomposeConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configure u64 decompose chip.
+    /// advices[0] should be the column range checked by `u8_range_check_config`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        advices: [Column<Advice>; 2],
+        u8_range_check_config: U8RangeCheckConfig,
+    ) -> U64DecomposeConfig {
+        let q_compose = meta.selector();
+
+        advices.iter().for_each(|advice| {
+            meta.enable_equality(*advice);
+        });
+
+        // | b0 | packed |
+        // | b1 |        |
+        // | .. |        |
+        // | b7 |        |
+        meta.create_gate("Compose u64 packed bytes", |meta| {
+            let q = meta.query_selector(q_compose);
+            let packed = meta.query_advice(advices[1], Rotation::cur());
+            let composed = (0..8)
+                .rev()
+                .fold(Expression::Constant(Fp::from(0)), |acc, i| {
+                    acc * Fp::from(256) + meta.query_advice(advices[0], Rotation(i))
+                });
+
+            vec![q * (packed - composed)]
+        });
+
+        U64DecomposeConfig {
+            advices,
+            q_compose,
+            u8_range_check_config,
+        }
+    }
+
+    /// Decompose the packed value into its 8 bytes, least significant first.
+    /// Unsatisfiable if the value is 2^64 or more.
+    pub fn decompose(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        packed: &AssignedCell<Fp, Fp>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let bytes = packed
+            .value()
+            .map(|packed| std::array::from_fn(|i| packed.to_bytes()[i] as u64));
+
+        self.assign_decomposition(layouter, packed, bytes)
+    }
+
+    /// Assign the given bytes as the decomposition of the packed value.
+    fn assign_decomposition(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        packed: &AssignedCell<Fp, Fp>,
+        bytes: Value<[u64; 8]>,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let range_chip = U8RangeCheckChip::construct(self.config.u8_range_check_config);
+        let advices = self.config.advices;
+
+        layouter.assign_region(
+            || "Decompose u64 packed bytes",
+            |mut region| {
+                self.config.q_compose.enable(&mut region, 0)?;
+                packed.copy_advice(|| "copy packed", &mut region, advices[1], 0)?;
+
+                (0..8)
+                    .map(|i| range_chip.assign_byte(&mut region, i, bytes.map(|bytes| bytes[i])))
+                    .collect()
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{
+            circuit::SimpleFloorPlanner,
+            dev::MockProver,
+            plonk::{Circuit, TableColumn},
+        },
+        table::load_enc_full_table,
+        utils::cells_to_bytes,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        packed: Fp,
+        // Decompose into these bytes instead of the bytes of the packed value
+        malicious_bytes: Option<[u64; 8]>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (U64DecomposeConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+
+            let q_u8_range_check = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+
+            (
+                U64DecomposeChip::configure(meta, advices, u8_range_check_config),
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+
+            let chip = U64DecomposeChip::construct(config.0);
+            let packed = layouter.assign_region(
+                || "assign packed",
+                |mut region| {
+                    region.assign_advice(
+                        || "assign packed",
+                        config.0.advices[1],
+                        0,
+                        || Value::known(self.packed),
+                    )
+                },
+            )?;
+
+            let bytes = match self.malicious_bytes {
+                Some(bytes) => {
+                    chip.assign_decomposition(&mut layouter, &packed, Value::known(bytes))?
+                }
+                None => chip.decompose(&mut layouter, &packed)?,
+            };
+            cells_to_bytes(&bytes).assert_if_known(|bytes| {
+                self.malicious_bytes.is_some() || bytes[..] == self.packed.to_bytes()[..8]
+            });
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_decompose_u64() {
+        for packed in [0, 1, 0xff, 0x0102030405060708, u64::MAX] {
+            let circuit = TestCircuit {
+                packed: Fp::from(packed),
+                malicious_bytes: None,
+            };
+
+            let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+            mock.assert_satisfied();
+        }
+    }
+
+    #[test]
+    fn test_decompose_u64_wrong_bytes_fails() {
+        let circuit = TestCircuit {
+            packed: Fp::from(0x0102030405060708),
+            malicious_bytes: Some([0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x02]),
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+
+    #[test]
+    fn test_decompose_u64_overflow_fails() {
+        // 2^64 doesn't fit in 8 bytes
+        let circuit = TestCircuit {
+            packed: Fp::from(u64::MAX) + Fp::from(1),
+            malicious_bytes: None,
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+}
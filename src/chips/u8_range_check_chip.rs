@@ -1,6 +1,6 @@
 use crate::{
     halo2_proofs::{
-        circuit::{AssignedCell, Layouter},
+        circuit::{AssignedCell, Layouter, Region, Value},
         halo2curves::bn256::Fr as Fp,
         plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
         poly::Rotation,
@@ -31,13 +31,26 @@ impl U8RangeCheckChip {
         selector: Selector,
         tag_tab: TableColumn,
         value_tab: TableColumn,
+    ) -> U8RangeCheckConfig {
+        Self::configure_with_tag_offset(meta, x_col, selector, tag_tab, value_tab, 0)
+    }
+
+    /// Same as `configure`, looking up the tag shifted by `tag_offset`,
+    /// see `TableColumns::configure_with_tag_offset`.
+    pub fn configure_with_tag_offset(
+        meta: &mut ConstraintSystem<Fp>,
+        x_col: Column<Advice>,
+        selector: Selector,
+        tag_tab: TableColumn,
+        value_tab: TableColumn,
+        tag_offset: u64,
     ) -> U8RangeCheckConfig {
         meta.lookup("Range check u8 value", |meta| {
             let q = meta.query_selector(selector);
             let x = meta.query_advice(x_col, Rotation::cur());
 
             vec![
-                (q.clone() * Fp::from(Tag::U8 as u64), tag_tab),
+                (q.clone() * Fp::from(Tag::U8.value(tag_offset)), tag_tab),
                 (q * x, value_tab),
             ]
         });
@@ -52,20 +65,163 @@ impl U8RangeCheckChip {
         &self,
         layouter: &mut impl Layouter<Fp>,
         x: &AssignedCell<Fp, Fp>,
+    ) -> Result<(), Error> {
+        self.range_check_many(layouter, std::slice::from_ref(x))
+    }
+
+    /// Range check the bytes in a single region, one byte per row.
+    /// Same rows as checking them one by one, but without a region per byte.
+    pub fn range_check_many(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[AssignedCell<Fp, Fp>],
     ) -> Result<(), Error> {
         layouter.assign_region(
-            || "",
+            || "range check bytes",
             |mut region| {
-                self.config.q.enable(&mut region, 0)?;
-                x.copy_advice(
-                    || "assign x value to check u8 xor",
-                    &mut region,
-                    self.config.x,
-                    0,
-                )?;
+                for (offset, byte) in bytes.iter().enumerate() {
+                    self.config.q.enable(&mut region, offset)?;
+                    byte.copy_advice(
+                        || "assign x value to check u8 xor",
+                        &mut region,
+                        self.config.x,
+                        offset,
+                    )?;
+                }
 
                 Ok(())
             },
         )
     }
+
+    /// Assign a byte in the given advice column and range check it in one call.
+    /// The byte is checked in place if `advice` is the range checked column,
+    /// otherwise it's copied to that column, so `advice` should have equality enabled.
+    pub fn assign_checked_byte(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        advice: Column<Advice>,
+        value: u8,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        if advice == self.config.x {
+            return layouter.assign_region(
+                || "assign checked byte",
+                |mut region| self.assign_byte(&mut region, 0, Value::known(value as u64)),
+            );
+        }
+
+        let byte = layouter.assign_region(
+            || "assign byte",
+            |mut region| {
+                region.assign_advice(
+                    || "assign byte to be range checked",
+                    advice,
+                    0,
+                    || Value::known(Fp::from(value as u64)),
+                )
+            },
+        )?;
+        self.range_check(layouter, &byte)?;
+
+        Ok(byte)
+    }
+
+    /// Assign a byte value in the given region and range check it in place.
+    /// All the byte assignments from raw values should go through this method.
+    pub fn assign_byte(
+        &self,
+        region: &mut Region<'_, Fp>,
+        offset: usize,
+        value: Value<u64>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        #[cfg(debug_assertions)]
+        value.assert_if_known(|v| *v <= u8::MAX as u64);
+
+        self.config.q.enable(region, offset)?;
+        region.assign_advice(
+            || "assign range checked byte",
+            self.config.x,
+            offset,
+            || value.map(Fp::from),
+        )
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{circuit::SimpleFloorPlanner, dev::MockProver, plonk::Circuit},
+        table::load_enc_full_table,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        values: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (U8RangeCheckConfig, Column<Advice>, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+            advices
+                .iter()
+                .for_each(|&advice| meta.enable_equality(advice));
+
+            let q_u8_range_check = meta.complex_selector();
+            let u8_range_check_config = U8RangeCheckChip::configure(
+                meta,
+                advices[0],
+                q_u8_range_check,
+                tables[0],
+                tables[1],
+            );
+
+            (u8_range_check_config, advices[1], tables)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.2, 17)?;
+            let chip = U8RangeCheckChip::construct(config.0);
+
+            for &value in self.values.iter() {
+                // In the range checked column and in another column
+                for advice in [config.0.x, config.1] {
+                    let byte = chip.assign_checked_byte(&mut layouter, advice, value)?;
+                    byte.value()
+                        .assert_if_known(|v| **v == Fp::from(value as u64));
+                }
+            }
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_assign_checked_byte() {
+        let circuit = TestCircuit {
+            values: vec![0, 1, 0x7f, 0x80, 0xff],
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
 }
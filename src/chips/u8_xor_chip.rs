@@ -1,6 +1,7 @@
 use crate::{
+    fault::{inject, FaultSite},
     halo2_proofs::{
-        circuit::{AssignedCell, Layouter},
+        circuit::{AssignedCell, Layouter, Region, Value},
         halo2curves::bn256::Fr as Fp,
         plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
         poly::Rotation,
@@ -37,6 +38,25 @@ impl U8XorChip {
         x_tab: TableColumn,
         y_tab: TableColumn,
         z_tab: TableColumn,
+    ) -> U8XorConfig {
+        Self::configure_with_tag_offset(
+            meta, x_col, y_col, z_col, selector, tag_tab, x_tab, y_tab, z_tab, 0,
+        )
+    }
+
+    /// Same as `configure`, looking up the tag shifted by `tag_offset`,
+    /// see `TableColumns::configure_with_tag_offset`.
+    pub fn configure_with_tag_offset(
+        meta: &mut ConstraintSystem<Fp>,
+        x_col: Column<Advice>,
+        y_col: Column<Advice>,
+        z_col: Column<Advice>,
+        selector: Selector,
+        tag_tab: TableColumn,
+        x_tab: TableColumn,
+        y_tab: TableColumn,
+        z_tab: TableColumn,
+        tag_offset: u64,
     ) -> U8XorConfig {
         meta.lookup("Check correct XOR of u8 values", |meta| {
             let q = meta.query_selector(selector);
@@ -45,7 +65,7 @@ impl U8XorChip {
             let z = meta.query_advice(z_col, Rotation::cur());
 
             vec![
-                (q.clone() * Fp::from(Tag::Xor as u64), tag_tab),
+                (q.clone() * Fp::from(Tag::Xor.value(tag_offset)), tag_tab),
                 (q.clone() * x, x_tab),
                 (q.clone() * y, y_tab),
                 (q * z, z_tab),
@@ -66,36 +86,386 @@ impl U8XorChip {
         x: &AssignedCell<Fp, Fp>,
         y: &AssignedCell<Fp, Fp>,
     ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let z = xor_bytes(&x.value().copied(), &y.value().copied());
+        self.xor_with(layouter, x, y, z)
+    }
+
+    /// `xor` with the given output instead of computing it from x and y,
+    /// e.g. taken from a `WitnessTrace`. The lookup still constrains it.
+    pub fn xor_with(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        x: &AssignedCell<Fp, Fp>,
+        y: &AssignedCell<Fp, Fp>,
+        z: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(|| "", |mut region| self.assign_xor(&mut region, 0, x, y, z))
+    }
+
+    /// XOR of two blocks byte by byte, e.g. the feedback of CBC or the keystream of CTR.
+    /// All the XORs are in a single region, one row per byte.
+    /// The blocks should be of the same length, which may be short of 16 for the last block of CTR.
+    pub fn xor_blocks(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: &[AssignedCell<Fp, Fp>],
+        b: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        assert_eq!(a.len(), b.len(), "XOR blocks of the same length");
+
         layouter.assign_region(
-            || "",
+            || "XOR blocks",
             |mut region| {
-                self.config.q.enable(&mut region, 0)?;
-                let x_copied = x.copy_advice(
-                    || "assign x value to check u8 xor",
-                    &mut region,
-                    self.config.x,
-                    0,
-                )?;
-                let y_copied = y.copy_advice(
-                    || "assign y value to check u8 xor",
-                    &mut region,
-                    self.config.y,
-                    0,
-                )?;
-                let z = region.assign_advice(
-                    || "assign z value to check u8 xor",
-                    self.config.z,
-                    0,
-                    || {
-                        xor_bytes(
-                            &x_copied.value_field().evaluate(),
-                            &y_copied.value_field().evaluate(),
-                        )
-                    },
-                );
-
-                Ok(z)
+                a.iter()
+                    .zip(b.iter())
+                    .enumerate()
+                    .map(|(offset, (x, y))| {
+                        let z = xor_bytes(&x.value().copied(), &y.value().copied());
+                        self.assign_xor(&mut region, offset, x, y, z)
+                    })
+                    .collect()
             },
-        )?
+        )
+    }
+
+    fn assign_xor(
+        &self,
+        region: &mut Region<'_, Fp>,
+        offset: usize,
+        x: &AssignedCell<Fp, Fp>,
+        y: &AssignedCell<Fp, Fp>,
+        z: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        self.config.q.enable(region, offset)?;
+        x.copy_advice(
+            || "assign x value to check u8 xor",
+            region,
+            self.config.x,
+            offset,
+        )?;
+        y.copy_advice(
+            || "assign y value to check u8 xor",
+            region,
+            self.config.y,
+            offset,
+        )?;
+        region.assign_advice(
+            || "assign z value to check u8 xor",
+            self.config.z,
+            offset,
+            || inject(FaultSite::Xor, z),
+        )
+    }
+
+    /// XOR of all the bytes, reduced by a balanced tree of pairwise XORs,
+    /// so the depth is log2 of the number of bytes rather than linear.
+    /// Takes `cells.len() - 1` XORs like a fold.
+    pub fn xor_many(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        cells: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        assert!(!cells.is_empty(), "XOR of at least one byte");
+
+        let mut level = cells.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [x, y] => self.xor(layouter, x, y),
+                    // The odd one out goes to the next level as is
+                    [x] => Ok(x.clone()),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+        }
+
+        Ok(level.pop().unwrap())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::{
+        halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner, Value},
+            dev::MockProver,
+            halo2curves::bn256::Fr as Fp,
+            plonk::{Circuit, ConstraintSystem, Error, TableColumn},
+        },
+        table::load_enc_full_table,
+        utils::cells_to_bytes,
+    };
+
+    #[derive(Clone)]
+    struct TestCircuit {
+        z: u64,
+    }
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = (U8XorConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+            let q_u8_xor = meta.complex_selector();
+
+            (
+                U8XorChip::configure(
+                    meta, advices[0], advices[1], advices[2], q_u8_xor, tables[0], tables[1],
+                    tables[2], tables[3],
+                ),
+                tables,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+            let config = config.0;
+
+            // Assign the XOR row by hand with the given output
+            layouter.assign_region(
+                || "XOR with given output",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "x", config.x, 0, || Value::known(Fp::from(0)))?;
+                    region.assign_advice(|| "y", config.y, 0, || Value::known(Fp::from(0)))?;
+                    region.assign_advice(|| "z", config.z, 0, || Value::known(Fp::from(self.z)))
+                },
+            )?;
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestXorPairCircuit {
+        x: u8,
+        y: u8,
+    }
+
+    impl Circuit<Fp> for TestXorPairCircuit {
+        type Config = (U8XorConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            TestCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+            let config = config.0;
+
+            let (x, y) = layouter.assign_region(
+                || "assign x and y",
+                |mut region| {
+                    let x = region.assign_advice(
+                        || "x",
+                        config.x,
+                        0,
+                        || Value::known(Fp::from(self.x as u64)),
+                    )?;
+                    let y = region.assign_advice(
+                        || "y",
+                        config.y,
+                        0,
+                        || Value::known(Fp::from(self.y as u64)),
+                    )?;
+                    Ok((x, y))
+                },
+            )?;
+            let z = U8XorChip::construct(config).xor(&mut layouter, &x, &y)?;
+
+            cells_to_bytes(&[z]).assert_if_known(|z| z[0] == self.x ^ self.y);
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    // a xor a = 0 is the diagonal of the table, a xor 0 = a its first row and column.
+    // 0 xor 0 = 0 is the same as the zero row but must be found with the XOR tag.
+    #[test]
+    fn test_xor_identities() {
+        for a in [0, 1, 0x5a, 0x80, 0xff] {
+            for (x, y) in [(a, a), (a, 0), (0, a)] {
+                let mock = MockProver::run(17, &TestXorPairCircuit { x, y }, vec![]).unwrap();
+                mock.assert_satisfied();
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestXorManyCircuit {
+        bytes: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for TestXorManyCircuit {
+        type Config = (U8XorConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            TestCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+            let config = config.0;
+
+            let cells = layouter.assign_region(
+                || "assign bytes",
+                |mut region| {
+                    self.bytes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &byte)| {
+                            region.assign_advice(
+                                || "assign byte",
+                                config.x,
+                                i,
+                                || Value::known(Fp::from(byte as u64)),
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+            let xored = U8XorChip::construct(config).xor_many(&mut layouter, &cells)?;
+
+            let expected = self.bytes.iter().fold(0, |acc, byte| acc ^ byte);
+            cells_to_bytes(&[xored]).assert_if_known(|xored| xored[0] == expected);
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestXorBlocksCircuit {
+        a: [u8; 16],
+        b: [u8; 16],
+    }
+
+    impl Circuit<Fp> for TestXorBlocksCircuit {
+        type Config = (U8XorConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            TestCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+            let config = config.0;
+
+            let (a, b) = layouter.assign_region(
+                || "assign blocks",
+                |mut region| {
+                    let mut assign = |block: &[u8; 16], column| {
+                        block
+                            .iter()
+                            .enumerate()
+                            .map(|(i, &byte)| {
+                                region.assign_advice(
+                                    || "assign byte",
+                                    column,
+                                    i,
+                                    || Value::known(Fp::from(byte as u64)),
+                                )
+                            })
+                            .collect::<Result<Vec<_>, Error>>()
+                    };
+                    Ok((assign(&self.a, config.x)?, assign(&self.b, config.y)?))
+                },
+            )?;
+            let xored = U8XorChip::construct(config).xor_blocks(&mut layouter, &a, &b)?;
+
+            cells_to_bytes(&xored).assert_if_known(|xored| {
+                xored[..] == std::array::from_fn::<u8, 16, _>(|i| self.a[i] ^ self.b[i])[..]
+            });
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_xor_blocks() {
+        // FIPS-197 Appendix B, the input XOR the first round key is the start of round 1
+        let a = [
+            0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d, 0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37,
+            0x07, 0x34,
+        ];
+        let b = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let expected = [
+            0x19, 0x3d, 0xe3, 0xbe, 0xa0, 0xf4, 0xe2, 0x2b, 0x9a, 0xc6, 0x8d, 0x2a, 0xe9, 0xf8,
+            0x48, 0x08,
+        ];
+        assert_eq!(std::array::from_fn::<u8, 16, _>(|i| a[i] ^ b[i]), expected);
+
+        let mock = MockProver::run(17, &TestXorBlocksCircuit { a, b }, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[test]
+    fn test_xor_many() {
+        for len in [1, 2, 3, 4, 7] {
+            let bytes = (0..len).map(|i| (0x11 * (i as u8 + 1)) ^ 0x80).collect();
+            let mock = MockProver::run(17, &TestXorManyCircuit { bytes }, vec![]).unwrap();
+            mock.assert_satisfied();
+        }
+    }
+
+    // The output of XOR is constrained to be u8 by the lookup itself,
+    // so it doesn't need to be range checked again.
+    #[test]
+    fn test_xor_output_is_byte_constrained() {
+        let mock = MockProver::run(17, &TestCircuit { z: 0 }, vec![]).unwrap();
+        mock.assert_satisfied();
+
+        // 0 xor 0 = 256 (mod 256) is not in the table
+        let mock = MockProver::run(17, &TestCircuit { z: 256 }, vec![]).unwrap();
+        assert!(mock.verify().is_err());
     }
 }
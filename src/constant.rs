@@ -1,3 +1,8 @@
+//! Constants of AES as specified in FIPS-197.
+//!
+//! The tables are indexed by the input byte, e.g. `S_BOX[x]` is SubBytes of x.
+
+/// S-box of SubBytes, FIPS-197 Figure 7.
 pub const S_BOX: [u8; 256] = [
     99, 124, 119, 123, 242, 107, 111, 197, 48, 1, 103, 43, 254, 215, 171, 118, 202, 130, 201, 125,
     250, 89, 71, 240, 173, 212, 162, 175, 156, 164, 114, 192, 183, 253, 147, 38, 54, 63, 247, 204,
@@ -14,6 +19,24 @@ pub const S_BOX: [u8; 256] = [
     153, 45, 15, 176, 84, 187, 23,
 ];
 
+/// Inverse S-box of InvSubBytes, FIPS-197 Figure 14.
+pub const INV_S_BOX: [u8; 256] = [
+    82, 9, 106, 213, 48, 54, 165, 56, 191, 64, 163, 158, 129, 243, 215, 251, 124, 227, 57, 130,
+    155, 47, 0, 255, 52, 142, 67, 68, 196, 222, 233, 203, 84, 123, 148, 50, 166, 194, 35, 61, 238,
+    76, 149, 11, 66, 250, 195, 78, 8, 46, 161, 102, 40, 217, 36, 178, 118, 91, 162, 73, 109, 139,
+    209, 37, 114, 248, 246, 100, 134, 104, 152, 22, 212, 164, 92, 204, 93, 101, 182, 146, 108, 112,
+    72, 80, 253, 237, 185, 218, 94, 21, 70, 87, 167, 141, 157, 132, 144, 216, 171, 0, 140, 188,
+    211, 10, 247, 228, 88, 5, 184, 179, 69, 6, 208, 44, 30, 143, 202, 63, 15, 2, 193, 175, 189, 3,
+    1, 19, 138, 107, 58, 145, 17, 65, 79, 103, 220, 234, 151, 242, 207, 206, 240, 180, 230, 115,
+    150, 172, 116, 34, 231, 173, 53, 133, 226, 249, 55, 232, 28, 117, 223, 110, 71, 241, 26, 113,
+    29, 41, 197, 137, 111, 183, 98, 14, 170, 24, 190, 27, 252, 86, 62, 75, 198, 210, 121, 32, 154,
+    219, 192, 254, 120, 205, 90, 244, 31, 221, 168, 51, 136, 7, 199, 49, 177, 18, 16, 89, 39, 128,
+    236, 95, 96, 81, 127, 169, 25, 181, 74, 13, 45, 229, 122, 159, 147, 201, 156, 239, 160, 224,
+    59, 77, 174, 42, 245, 176, 200, 235, 187, 60, 131, 83, 153, 97, 23, 43, 4, 126, 186, 119, 214,
+    38, 225, 105, 20, 99, 85, 33, 12, 125,
+];
+
+/// Multiplication by 2 in GF(2^8), used in MixColumns, FIPS-197 Section 4.2.
 pub const MUL_BY_2: [u8; 256] = [
     0, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 32, 34, 36, 38, 40, 42, 44, 46, 48,
     50, 52, 54, 56, 58, 60, 62, 64, 66, 68, 70, 72, 74, 76, 78, 80, 82, 84, 86, 88, 90, 92, 94, 96,
@@ -30,6 +53,7 @@ pub const MUL_BY_2: [u8; 256] = [
     239, 237, 227, 225, 231, 229,
 ];
 
+/// Multiplication by 3 in GF(2^8), used in MixColumns, FIPS-197 Section 4.2.
 pub const MUL_BY_3: [u8; 256] = [
     0, 3, 6, 5, 12, 15, 10, 9, 24, 27, 30, 29, 20, 23, 18, 17, 48, 51, 54, 53, 60, 63, 58, 57, 40,
     43, 46, 45, 36, 39, 34, 33, 96, 99, 102, 101, 108, 111, 106, 105, 120, 123, 126, 125, 116, 119,
@@ -46,6 +70,7 @@ pub const MUL_BY_3: [u8; 256] = [
     19, 16, 21, 22, 31, 28, 25, 26,
 ];
 
+/// Multiplication by 9 in GF(2^8), used in InvMixColumns, FIPS-197 Section 4.2.
 pub const MUL_BY_9: [u8; 256] = [
     0, 9, 18, 27, 36, 45, 54, 63, 72, 65, 90, 83, 108, 101, 126, 119, 144, 153, 130, 139, 180, 189,
     166, 175, 216, 209, 202, 195, 252, 245, 238, 231, 59, 50, 41, 32, 31, 22, 13, 4, 115, 122, 97,
@@ -62,6 +87,7 @@ pub const MUL_BY_9: [u8; 256] = [
     7, 14, 121, 112, 107, 98, 93, 84, 79, 70,
 ];
 
+/// Multiplication by 11 in GF(2^8), used in InvMixColumns, FIPS-197 Section 4.2.
 pub const MUL_BY_11: [u8; 256] = [
     0, 11, 22, 29, 44, 39, 58, 49, 88, 83, 78, 69, 116, 127, 98, 105, 176, 187, 166, 173, 156, 151,
     138, 129, 232, 227, 254, 245, 196, 207, 210, 217, 123, 112, 109, 102, 87, 92, 65, 74, 35, 40,
@@ -78,6 +104,7 @@ pub const MUL_BY_11: [u8; 256] = [
     153, 132, 143, 190, 181, 168, 163,
 ];
 
+/// Multiplication by 13 in GF(2^8), used in InvMixColumns, FIPS-197 Section 4.2.
 pub const MUL_BY_13: [u8; 256] = [
     0, 13, 26, 23, 52, 57, 46, 35, 104, 101, 114, 127, 92, 81, 70, 75, 208, 221, 202, 199, 228,
     233, 254, 243, 184, 181, 162, 175, 140, 129, 150, 155, 187, 182, 161, 172, 143, 130, 149, 152,
@@ -94,6 +121,7 @@ pub const MUL_BY_13: [u8; 256] = [
     180, 185, 174, 163, 128, 141, 154, 151,
 ];
 
+/// Multiplication by 14 in GF(2^8), used in InvMixColumns, FIPS-197 Section 4.2.
 pub const MUL_BY_14: [u8; 256] = [
     0, 14, 28, 18, 56, 54, 36, 42, 112, 126, 108, 98, 72, 70, 84, 90, 224, 238, 252, 242, 216, 214,
     196, 202, 144, 158, 140, 130, 168, 166, 180, 186, 219, 213, 199, 201, 227, 237, 255, 241, 171,
@@ -110,5 +138,104 @@ pub const MUL_BY_14: [u8; 256] = [
     167, 169, 187, 181, 159, 145, 131, 141,
 ];
 
-pub(crate) const KEY_SCHEDULE_ROWS: u64 = 1760;
+/// Reduction constant of the GF(2^128) doubling used in CMAC and XTS.
+/// x^128 + x^7 + x^2 + x + 1
+pub const GF128_REDUCTION: u8 = 0x87;
+
+/// Reduction constant of the GF(2^128) multiplication in GHASH.
+/// x^128 + x^7 + x^2 + x + 1 in the bit reflected representation.
+pub const GCM_REDUCTION: u8 = 0xe1;
+
+/// Default initial value of AES Key Wrap, RFC 3394 Section 2.2.3.1.
+pub const KEY_WRAP_IV: [u8; 8] = [0xa6; 8];
+
+/// Log of 0 in the GF(2^8) log table.
+/// Greater than the sum of any two logs of non zero bytes (254 + 254).
+pub const GF_LOG_ZERO: u64 = 510;
+
+/// Round constants of the key expansion, FIPS-197 Section 5.2.
+/// The i-th one is x^i in GF(2^8), the first byte of the word Rcon[i + 1].
+/// AES-128 uses the first 10 constants, AES-192 8 and AES-256 7.
+/// Rijndael with 256 bit block uses up to 29, with a 128 bit key.
+pub const ROUND_CONSTANT: [u8; 29] = [
+    1, 2, 4, 8, 16, 32, 64, 128, 27, 54, 108, 216, 171, 77, 154, 47, 94, 188, 99, 198, 151, 53,
+    106, 212, 179, 125, 250, 239, 197,
+];
+
+/// MixColumns matrix. The i-th output byte of a word is the linear combination
+/// of the word with the i-th row of the matrix.
+pub const MIX_COLUMNS: [[u32; 4]; 4] = [[2, 3, 1, 1], [1, 2, 3, 1], [1, 1, 2, 3], [3, 1, 1, 2]];
+
+/// InvMixColumns matrix, the inverse of `MIX_COLUMNS`.
+pub const INV_MIX_COLUMNS: [[u32; 4]; 4] = [
+    [14, 11, 13, 9],
+    [9, 14, 11, 13],
+    [13, 9, 14, 11],
+    [11, 13, 9, 14],
+];
+
+// Rows of the key schedule: the key (16) and 10 rounds of 4 words,
+// the first word with RotWord (4), SubWord (4), the round constant (4 + 4),
+// every word with the XOR (4) of its bytes, and the range check of the 160 derived bytes.
+pub(crate) const KEY_SCHEDULE_ROWS: u64 = 16 + 10 * (16 + 4 * 4) + 160;
 pub(crate) const AES_ROWS: u64 = 1360;
+// Rows of the initial AddRoundKey with its plaintext: the plaintext (16) and the XOR (16)
+pub(crate) const INITIAL_ROUND_ROWS: u64 = 16 + 16;
+// Rows of a round with MixColumns: SubBytes (16), MixColumns (16 * (4 + 3)) and AddRoundKey (16)
+pub(crate) const ROUND_ROWS: u64 = 16 + 16 * 7 + 16;
+// Rows of a round without MixColumns: SubBytes and AddRoundKey
+pub(crate) const LAST_ROUND_ROWS: u64 = 16 + 16;
+// Rows of InvMixColumns of a column: 4 lookups of the coefficients and 3 XORs per byte
+pub(crate) const INV_MIX_COLUMN_ROWS: u64 = 4 * (4 + 3);
+// Rows of a decryption: ciphertext (16), initial AddRoundKey (16), 9 rounds of
+// InvSubBytes (16), AddRoundKey (16) and InvMixColumns, and the last round without it
+pub(crate) const DECRYPT_ROWS: u64 = 16 + 16 + 9 * (16 + 16 + 4 * INV_MIX_COLUMN_ROWS) + 16 + 16;
+// Rows of one GHASH block: assignment and XOR of the block (16 + 16), and the multiplication.
+// The multiplication takes 128 rows of bits, 128 masks (16), 127 XORs (16) and 127 mul by x (32 + 1).
+pub(crate) const GHASH_BLOCK_ROWS: u64 = 16 + 16 + 128 + 128 * 16 + 127 * 16 + 127 * 33;
+
+// Rows of each key selected among by `schedule_key_select` besides its key schedule,
+// where the 16 constant key bytes take the place of the assigned key:
+// its select bit (1), its term of the sum of the bits (1) and of the 176 selected round key bytes
+pub(crate) const KEY_SELECT_ROWS_PER_KEY: u64 = 1 + 1 + 176;
+// Rows of the key selection independent of the number of keys: the constant one the bits sum to
+pub(crate) const KEY_SELECT_ROWS: u64 = 1;
+
+// Rows of one CTR block besides AES: counter increment, plaintext and xor
+pub(crate) const CTR_BLOCK_ROWS: u64 = 49;
+
+// Rows of one CBC block besides AES: plaintext and xor with the previous block
+pub(crate) const CBC_BLOCK_ROWS: u64 = 16 + 16;
+
+// Rows of the inequality of a ciphertext to a target: the target and the differences with the inverse
+pub(crate) const NOT_EQUAL_ROWS: u64 = 16 + 16 + 1;
+
+// Rows of the decomposition of a block packed in 2 cells of 8 bytes
+pub(crate) const PACKED_BLOCK_ROWS: u64 = 2 * 8;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::gf_mul;
+
+    #[test]
+    fn test_tables() {
+        for x in 0..=255u8 {
+            assert_eq!(INV_S_BOX[S_BOX[x as usize] as usize], x);
+            for (n, table) in [
+                (2, MUL_BY_2),
+                (3, MUL_BY_3),
+                (9, MUL_BY_9),
+                (11, MUL_BY_11),
+                (13, MUL_BY_13),
+                (14, MUL_BY_14),
+            ] {
+                assert_eq!(table[x as usize], gf_mul(x, n));
+            }
+        }
+
+        for (i, &rc) in ROUND_CONSTANT.iter().enumerate().skip(1) {
+            assert_eq!(rc, gf_mul(ROUND_CONSTANT[i - 1], 2));
+        }
+    }
+}
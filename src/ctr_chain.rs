@@ -0,0 +1,230 @@
+//! Chaining CTR encryption of a large message across multiple circuits.
+//!
+//! A message too large for one circuit is split by `split_ctr_chunks` into chunks
+//! of whole blocks (only the last chunk can end with a partial block).
+//! Each chunk is proven by its own circuit calling `Aes128Scheduled::ctr_encrypt_chunk`,
+//! whose public inputs are
+//!
+//! | counter_in (16) | ciphertext (chunk length) | counter_out (16) |
+//!
+//! where counter_out is the counter block following the last one used in the chunk.
+//! The chain is valid if the first counter_in is the IV and the counter_out of
+//! the chunk i is the counter_in of the chunk i + 1, checked by `check_ctr_chain`
+//! on the public inputs of the verified proofs.
+//! This guarantees that no counter block is reused across the chunks.
+//!
+//! NOTE: The chunks are NOT bound to the same key by this protocol.
+//! Expose the key, or a commitment to it, in every chunk if that matters.
+
+use crate::halo2_proofs::halo2curves::bn256::Fr as Fp;
+
+/// One chunk of the message with the counter block it starts from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CtrChunk {
+    pub counter: [u8; 16],
+    pub plaintext: Vec<u8>,
+}
+
+/// Add n to the 16 bytes big-endian counter block, wrapping around at 2^128.
+pub fn add_counter(counter: [u8; 16], n: u64) -> [u8; 16] {
+    u128::from_be_bytes(counter)
+        .wrapping_add(n as u128)
+        .to_be_bytes()
+}
+
+/// Split the plaintext into chunks of `blocks_per_chunk` blocks,
+/// each starting from the counter block following the previous chunk.
+pub fn split_ctr_chunks(iv: [u8; 16], plaintext: &[u8], blocks_per_chunk: usize) -> Vec<CtrChunk> {
+    assert!(blocks_per_chunk > 0, "Chunk should have at least one block");
+
+    plaintext
+        .chunks(blocks_per_chunk * 16)
+        .enumerate()
+        .map(|(i, chunk)| CtrChunk {
+            counter: add_counter(iv, (i * blocks_per_chunk) as u64),
+            plaintext: chunk.to_vec(),
+        })
+        .collect()
+}
+
+/// Build the public input vector of a chunk circuit, see the module doc for the layout.
+pub fn ctr_chunk_public_inputs(counter: [u8; 16], ciphertext: &[u8]) -> Vec<Fp> {
+    let blocks = ((ciphertext.len() + 15) / 16) as u64;
+
+    counter
+        .iter()
+        .chain(ciphertext.iter())
+        .chain(add_counter(counter, blocks).iter())
+        .map(|&b| Fp::from(b as u64))
+        .collect()
+}
+
+/// Check that the public inputs of the chunk circuits, in order, form a chain from the IV.
+pub fn check_ctr_chain(iv: [u8; 16], public_inputs: &[Vec<Fp>]) -> bool {
+    let iv = iv.iter().map(|&b| Fp::from(b as u64)).collect::<Vec<_>>();
+    if public_inputs.iter().any(|inputs| inputs.len() < 32) {
+        return false;
+    }
+
+    let mut expected = &iv[..];
+    for inputs in public_inputs {
+        if inputs[..16] != *expected {
+            return false;
+        }
+        expected = &inputs[inputs.len() - 16..];
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        aes128::{EncryptCursor, FixedAes128Config},
+        halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner},
+            plonk::{Circuit, ConstraintSystem, Error},
+        },
+        utils::cells_to_bytes,
+    };
+
+    fn hex_to_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn hex_to_block(hex: &str) -> [u8; 16] {
+        hex_to_bytes(hex).try_into().unwrap()
+    }
+
+    #[derive(Clone)]
+    struct TestChunkCircuit {
+        key: [u8; 16],
+        chunk: CtrChunk,
+    }
+
+    impl Circuit<Fp> for TestChunkCircuit {
+        type Config = FixedAes128Config<17, 1>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            FixedAes128Config::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            config.load_tables(&mut layouter)?;
+            let aes = config.schedule_key(&mut layouter, self.key)?;
+            let mut cursor = EncryptCursor::default();
+            let ciphertext = aes.ctr_encrypt_chunk(
+                &mut cursor,
+                &mut layouter,
+                self.chunk.counter,
+                &self.chunk.plaintext,
+            )?;
+
+            cells_to_bytes(&ciphertext)
+                .assert_if_known(|bytes| bytes.len() == self.chunk.plaintext.len());
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_split_ctr_chunks() {
+        let iv = hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let chunks = split_ctr_chunks(iv, &[0u8; 36], 2);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].counter, iv);
+        assert_eq!(chunks[0].plaintext.len(), 32);
+        assert_eq!(
+            chunks[1].counter,
+            hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdff01")
+        );
+        assert_eq!(chunks[1].plaintext.len(), 4);
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_two_chunks() {
+        use crate::halo2_proofs::dev::MockProver;
+
+        // NIST SP 800-38A F.5.1 CTR-AES128.Encrypt, the last block truncated to 4 bytes
+        let key = hex_to_block("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let plaintext = hex_to_bytes(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46",
+        );
+        let ciphertext = hex_to_bytes(
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff5ae4df3e",
+        );
+
+        let chunks = split_ctr_chunks(iv, &plaintext, 2);
+        let public_inputs = chunks
+            .iter()
+            .zip(ciphertext.chunks(32))
+            .map(|(chunk, ciphertext)| ctr_chunk_public_inputs(chunk.counter, ciphertext))
+            .collect::<Vec<_>>();
+
+        for (chunk, inputs) in chunks.iter().zip(public_inputs.iter()) {
+            let circuit = TestChunkCircuit {
+                key,
+                chunk: chunk.clone(),
+            };
+            let mock = MockProver::run(17, &circuit, vec![inputs.clone()]).unwrap();
+            mock.assert_satisfied();
+        }
+        assert!(check_ctr_chain(iv, &public_inputs));
+
+        // Reusing the counter of the first chunk breaks the chain
+        let reused = ctr_chunk_public_inputs(iv, &ciphertext[32..]);
+        assert!(!check_ctr_chain(
+            iv,
+            &[public_inputs[0].clone(), reused.clone()]
+        ));
+
+        // and the circuit doesn't accept the ciphertext under the other counter
+        let circuit = TestChunkCircuit {
+            key,
+            chunk: CtrChunk {
+                counter: iv,
+                plaintext: chunks[1].plaintext.clone(),
+            },
+        };
+        let mock = MockProver::run(17, &circuit, vec![reused]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_empty_chunk() {
+        use crate::halo2_proofs::dev::MockProver;
+
+        // Empty chunk passes the counter through
+        let iv = hex_to_block("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        assert!(split_ctr_chunks(iv, &[], 2).is_empty());
+
+        let circuit = TestChunkCircuit {
+            key: hex_to_block("2b7e151628aed2a6abf7158809cf4f3c"),
+            chunk: CtrChunk {
+                counter: iv,
+                plaintext: vec![],
+            },
+        };
+        let inputs = ctr_chunk_public_inputs(iv, &[]);
+        assert_eq!(inputs[..16], inputs[16..]);
+
+        let mock = MockProver::run(17, &circuit, vec![inputs]).unwrap();
+        mock.assert_satisfied();
+    }
+}
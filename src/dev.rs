@@ -0,0 +1,97 @@
+//! Diagnostics to validate the capacity constants against the actual layout.
+
+use crate::halo2_proofs::{
+    circuit::{Cell, Layouter, Region, Table, Value},
+    dev::{CellValue, MockProver},
+    halo2curves::bn256::Fr as Fp,
+    plonk::{Challenge, Column, Error, Instance},
+};
+
+/// Number of used rows of each advice column after the MockProver run,
+/// i.e. the highest assigned row plus one. Unused columns report 0.
+pub fn used_rows_per_advice(mock: &MockProver<Fp>) -> Vec<usize> {
+    mock.advice()
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .rposition(|cell| matches!(cell, CellValue::Assigned(_)))
+                .map_or(0, |row| row + 1)
+        })
+        .collect()
+}
+
+/// Layouter counting the regions assigned through it, e.g. to compare the regions
+/// of a gadget against a baseline and notice when a refactoring changes them.
+/// Regions assigned in a `namespace` of it aren't counted, as they go to the root layouter.
+pub struct RegionCounter<'a, L> {
+    inner: &'a mut L,
+    regions: usize,
+}
+
+impl<'a, L: Layouter<Fp>> RegionCounter<'a, L> {
+    pub fn new(inner: &'a mut L) -> Self {
+        Self { inner, regions: 0 }
+    }
+
+    /// Number of regions assigned since the creation or the last reset.
+    pub fn regions(&self) -> usize {
+        self.regions
+    }
+
+    pub fn reset(&mut self) {
+        self.regions = 0;
+    }
+}
+
+impl<L: Layouter<Fp>> Layouter<Fp> for RegionCounter<'_, L> {
+    type Root = L::Root;
+
+    fn assign_region<A, AR, N, NR>(&mut self, name: N, assignment: A) -> Result<AR, Error>
+    where
+        A: FnMut(Region<'_, Fp>) -> Result<AR, Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        self.regions += 1;
+        self.inner.assign_region(name, assignment)
+    }
+
+    fn assign_table<A, N, NR>(&mut self, name: N, assignment: A) -> Result<(), Error>
+    where
+        A: FnMut(Table<'_, Fp>) -> Result<(), Error>,
+        N: Fn() -> NR,
+        NR: Into<String>,
+    {
+        self.inner.assign_table(name, assignment)
+    }
+
+    fn constrain_instance(
+        &mut self,
+        cell: Cell,
+        column: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error> {
+        self.inner.constrain_instance(cell, column, row)
+    }
+
+    fn get_challenge(&self, challenge: Challenge) -> Value<Fp> {
+        self.inner.get_challenge(challenge)
+    }
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self.inner.get_root()
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.inner.push_namespace(name_fn)
+    }
+
+    fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.inner.pop_namespace(gadget_name)
+    }
+}
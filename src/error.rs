@@ -0,0 +1,161 @@
+use std::fmt;
+
+use crate::{halo2_proofs::plonk::Error, reference::WitnessTrace};
+
+/// Errors raised by the AES circuits.
+#[derive(Debug)]
+pub enum AesError {
+    /// The lookup table doesn't fit in the 2^k rows of the circuit.
+    TableTooLargeForK { needed: u32, k: u32 },
+    /// Encrypting before `FixedAes128Config::load_tables`.
+    TablesNotLoaded,
+    /// Encrypting under a key schedule whose rows aren't accounted,
+    /// see `Aes128Scheduled::schedule_another_key`.
+    KeyScheduleNotAccounted,
+    /// The input of a mode without padding isn't a multiple of 16 bytes.
+    InvalidLength { len: usize },
+    /// The key data of AES Key Wrap isn't at least 2 blocks of 8 bytes,
+    /// see `Aes128Scheduled::key_wrap`.
+    InvalidKeyDataLength { len: usize },
+    /// No round constant for the round of the key expansion, see `constant::ROUND_CONSTANT`.
+    RoundConstantOutOfRange { round: u32 },
+    /// The group of columns isn't after the current one or doesn't exist,
+    /// see `FixedAes128Config::set_active_group`.
+    InvalidGroup {
+        group: usize,
+        current: usize,
+        n: usize,
+    },
+    /// The instance column doesn't exist, see `FixedAes128Config::configure_with_instances`.
+    InvalidInstanceColumn { column: usize, n: usize },
+    /// No group of advice columns to lay out the encryptions in.
+    NoGroups,
+    /// No key to select from, see `FixedAes128Config::schedule_key_select`.
+    NoKeys,
+    /// The key schedule doesn't fit in the 2^k rows of the first group of columns.
+    KeyScheduleTooLargeForK { rows: u64, k: u32 },
+    /// The rows requested don't fit in the rows available in the last group of columns,
+    /// e.g. encrypting more blocks than fit in the circuit.
+    CapacityExceeded { requested: u64, available: u64 },
+    /// More blocks than the encryptions fitting in the circuit, see `recommended_k`.
+    TooManyBlocks { num_blocks: u64, max: u64 },
+    /// No chip multiplying by the coefficient of the (Inv)MixColumns matrix,
+    /// only 1, 2, 3, 9, 11, 13 and 14 are supported.
+    UnsupportedCoefficient { coeff: u32 },
+    /// The witness trace isn't of one encryption, see `Aes128Scheduled::encrypt_with_trace`.
+    InvalidTraceLength { len: usize },
+    /// The numeral string isn't of an even positive length of digits less than the radix,
+    /// or a half of it takes more than 2^32 values, see `FfxConfig::encrypt`.
+    InvalidNumeral { radix: u32, len: usize },
+    /// Error from the synthesis.
+    Halo2(Error),
+}
+
+impl fmt::Display for AesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AesError::TableTooLargeForK { needed, k } => write!(
+                f,
+                "lookup table doesn't fit in 2^{} rows, need k >= {}",
+                k, needed
+            ),
+            AesError::TablesNotLoaded => write!(
+                f,
+                "lookup table isn't loaded, call load_tables before encrypting"
+            ),
+            AesError::KeyScheduleNotAccounted => write!(
+                f,
+                "key is scheduled twice, use schedule_another_key for more keys"
+            ),
+            AesError::InvalidLength { len } => write!(
+                f,
+                "input of {} bytes isn't a multiple of the 16 bytes block",
+                len
+            ),
+            AesError::InvalidKeyDataLength { len } => write!(
+                f,
+                "key data of {} bytes isn't at least 2 blocks of 8 bytes",
+                len
+            ),
+            AesError::RoundConstantOutOfRange { round } => {
+                write!(f, "no round constant for the round {}", round)
+            }
+            AesError::InvalidGroup { group, current, n } => write!(
+                f,
+                "can't move from the group {} to the group {} of {} groups",
+                current, group, n
+            ),
+            AesError::InvalidInstanceColumn { column, n } => {
+                write!(f, "no instance column {} of {} instance columns", column, n)
+            }
+            AesError::NoGroups => write!(f, "need at least one group of advice columns"),
+            AesError::NoKeys => write!(f, "need at least one key to select from"),
+            AesError::KeyScheduleTooLargeForK { rows, k } => write!(
+                f,
+                "key schedule of {} rows doesn't fit in 2^{} rows",
+                rows, k
+            ),
+            AesError::CapacityExceeded {
+                requested,
+                available,
+            } => write!(
+                f,
+                "{} rows don't fit in the circuit, {} rows are available",
+                requested, available
+            ),
+            AesError::TooManyBlocks { num_blocks, max } => write!(
+                f,
+                "{} blocks don't fit in the circuit, at most {} blocks fit",
+                num_blocks, max
+            ),
+            AesError::UnsupportedCoefficient { coeff } => {
+                write!(f, "can't multiply by the coefficient {}", coeff)
+            }
+            AesError::InvalidTraceLength { len } => write!(
+                f,
+                "witness trace of {} bytes isn't the {} outputs of one encryption",
+                len,
+                WitnessTrace::LEN
+            ),
+            AesError::InvalidNumeral { radix, len } => write!(
+                f,
+                "numeral string of {} digits in radix {} isn't in the domain of FFX",
+                len, radix
+            ),
+            AesError::Halo2(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for AesError {}
+
+impl From<AesError> for Error {
+    fn from(err: AesError) -> Self {
+        match err {
+            AesError::TableTooLargeForK { k, .. } | AesError::KeyScheduleTooLargeForK { k, .. } => {
+                Error::NotEnoughRowsAvailable { current_k: k }
+            }
+            AesError::TablesNotLoaded
+            | AesError::KeyScheduleNotAccounted
+            | AesError::InvalidLength { .. }
+            | AesError::InvalidKeyDataLength { .. }
+            | AesError::RoundConstantOutOfRange { .. }
+            | AesError::InvalidGroup { .. }
+            | AesError::InvalidInstanceColumn { .. }
+            | AesError::NoGroups
+            | AesError::NoKeys
+            | AesError::CapacityExceeded { .. }
+            | AesError::TooManyBlocks { .. }
+            | AesError::UnsupportedCoefficient { .. }
+            | AesError::InvalidTraceLength { .. }
+            | AesError::InvalidNumeral { .. } => Error::Synthesis,
+            AesError::Halo2(err) => err,
+        }
+    }
+}
+
+impl From<Error> for AesError {
+    fn from(err: Error) -> Self {
+        AesError::Halo2(err)
+    }
+}
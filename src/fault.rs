@@ -0,0 +1,70 @@
+//! Fault injection for the negative tests.
+//!
+//! The chips pass the witness of their output cells through `inject`.
+//! A test plans a fault with `with_fault`, which adds 1 to the n-th witness of the site
+//! assigned while running the closure, to check that the cell is actually constrained.
+//! Outside the tests `inject` returns the witness as is.
+
+use crate::halo2_proofs::{circuit::Value, halo2curves::bn256::Fr as Fp};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FaultSite {
+    /// Output of the S-box chip, including SubWord of the key schedule
+    Sbox,
+    /// Output of the XOR chip, including the key schedule
+    Xor,
+    /// Output of the gf mul by 2 chip
+    Mul2,
+    /// Output of the gf mul by 3 chip
+    Mul3,
+    /// Output of the inverse cipher chip: the inverse S-box and the mul by 9, 11, 13 and 14
+    InvCipher,
+    /// Round constant word (rc, 0, 0, 0) of the key schedule
+    RoundConstant,
+}
+
+#[cfg(not(test))]
+#[inline(always)]
+pub(crate) fn inject(_site: FaultSite, value: Value<Fp>) -> Value<Fp> {
+    value
+}
+
+#[cfg(test)]
+pub(crate) use plan::{inject, with_fault};
+
+#[cfg(test)]
+mod plan {
+    use std::cell::Cell;
+
+    use super::*;
+
+    thread_local! {
+        // Site and index of the planned fault
+        static PLAN: Cell<Option<(FaultSite, usize)>> = const { Cell::new(None) };
+        // Number of the witnesses of the planned site seen so far
+        static SEEN: Cell<usize> = const { Cell::new(0) };
+    }
+
+    pub(crate) fn inject(site: FaultSite, value: Value<Fp>) -> Value<Fp> {
+        match PLAN.with(|plan| plan.get()) {
+            Some((planned, index)) if planned == site => {
+                let seen = SEEN.with(|seen| seen.replace(seen.get() + 1));
+                if seen == index {
+                    value.map(|v| v + Fp::from(1))
+                } else {
+                    value
+                }
+            }
+            _ => value,
+        }
+    }
+
+    /// Run f with the fault planned at the index-th witness of the site.
+    pub(crate) fn with_fault<T>(site: FaultSite, index: usize, f: impl FnOnce() -> T) -> T {
+        PLAN.with(|plan| plan.set(Some((site, index))));
+        SEEN.with(|seen| seen.set(0));
+        let res = f();
+        PLAN.with(|plan| plan.set(None));
+        res
+    }
+}
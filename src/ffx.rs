@@ -0,0 +1,422 @@
+//! FFX-style format-preserving encryption with AES-128 as the round function.
+//!
+//! A numeral string of even length n in radix r is split into the halves A and B
+//! of m = n / 2 digits, read as big-endian numbers below M = r^m (at most 2^32).
+//! The halves go through a balanced Feistel network of `ROUNDS` rounds, the round i being
+//!
+//! y = first 4 bytes of AES_K(i || 0^11 || B), as a big-endian u32
+//! (A, B) <- (B, (A + y) mod M)
+//!
+//! This follows the structure of FFX (Bellare, Rogaway and Spies) with a simplified round
+//! function without tweak, so it is NOT compatible with FF1 or FF3-1.
+//! y mod M is biased unless M divides 2^32, use it for research only.
+
+use crate::{
+    aes128::{Aes128Scheduled, EncryptCursor},
+    chips::{
+        u32_decompose_chip::{U32DecomposeChip, U32DecomposeConfig},
+        u8_range_check_chip::U8RangeCheckChip,
+    },
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+        poly::Rotation,
+    },
+    reference::encrypt_block_reference,
+    table::TableColumns,
+    AesError,
+};
+
+/// Number of Feistel rounds.
+pub const ROUNDS: usize = 10;
+
+// Plaintext block of the round function
+fn round_block(round: usize, b: u64) -> [u8; 16] {
+    let mut block = [0u8; 16];
+    block[0] = round as u8;
+    block[12..].copy_from_slice(&(b as u32).to_be_bytes());
+    block
+}
+
+fn round_value(round_keys: &[[u8; 16]; 11], round: usize, b: u64) -> u64 {
+    let output = encrypt_block_reference(round_keys, round_block(round, b));
+    u32::from_be_bytes(output[..4].try_into().unwrap()) as u64
+}
+
+// M = radix^m, checking the parameters of the domain
+fn half_modulus(radix: u32, digits: usize) -> u64 {
+    assert!(radix >= 2, "Radix should be at least 2");
+    assert!(
+        digits > 0 && digits % 2 == 0,
+        "Numeral string should have an even positive length"
+    );
+
+    let modulus = (radix as u64)
+        .checked_pow((digits / 2) as u32)
+        .filter(|&modulus| modulus <= 1 << 32);
+    modulus.expect("Half of the domain should be at most 2^32")
+}
+
+// M = radix^m, checking the numeral string is in the domain before assigning any cell
+fn check_numeral(radix: u32, digits: &[u8]) -> Result<u64, AesError> {
+    let len = digits.len();
+    let modulus = (radix as u64)
+        .checked_pow((len / 2) as u32)
+        .filter(|&modulus| modulus <= 1 << 32);
+
+    match modulus {
+        Some(modulus)
+            if radix >= 2
+                && len > 0
+                && len % 2 == 0
+                && digits.iter().all(|&d| (d as u32) < radix) =>
+        {
+            Ok(modulus)
+        }
+        _ => Err(AesError::InvalidNumeral { radix, len }),
+    }
+}
+
+/// Big-endian numbers of the two halves of the numeral string.
+pub fn numeral_to_halves(radix: u32, digits: &[u8]) -> (u64, u64) {
+    half_modulus(radix, digits.len());
+    assert!(
+        digits.iter().all(|&d| (d as u32) < radix),
+        "Digit should be less than the radix"
+    );
+
+    let number = |half: &[u8]| half.iter().fold(0, |acc, &d| acc * radix as u64 + d as u64);
+    let (a, b) = digits.split_at(digits.len() / 2);
+    (number(a), number(b))
+}
+
+/// Numeral string of the given length from the numbers of its two halves.
+pub fn halves_to_numeral(radix: u32, len: usize, halves: (u64, u64)) -> Vec<u8> {
+    half_modulus(radix, len);
+
+    let digits = |mut half: u64| {
+        let mut digits = vec![0u8; len / 2];
+        for digit in digits.iter_mut().rev() {
+            *digit = (half % radix as u64) as u8;
+            half /= radix as u64;
+        }
+        digits
+    };
+    [digits(halves.0), digits(halves.1)].concat()
+}
+
+/// Encrypt the numeral string out of the circuit, as `FfxConfig::encrypt`.
+pub fn ffx_encrypt_reference(round_keys: &[[u8; 16]; 11], radix: u32, digits: &[u8]) -> Vec<u8> {
+    let modulus = half_modulus(radix, digits.len());
+    let (mut a, mut b) = numeral_to_halves(radix, digits);
+
+    for round in 0..ROUNDS {
+        let c = (a + round_value(round_keys, round, b)) % modulus;
+        (a, b) = (b, c);
+    }
+    halves_to_numeral(radix, digits.len(), (a, b))
+}
+
+/// Decrypt the numeral string out of the circuit.
+pub fn ffx_decrypt_reference(round_keys: &[[u8; 16]; 11], radix: u32, digits: &[u8]) -> Vec<u8> {
+    let modulus = half_modulus(radix, digits.len());
+    let (mut a, mut b) = numeral_to_halves(radix, digits);
+
+    for round in (0..ROUNDS).rev() {
+        let y = round_value(round_keys, round, a) % modulus;
+        (a, b) = ((b + modulus - y) % modulus, a);
+    }
+    halves_to_numeral(radix, digits.len(), (a, b))
+}
+
+fn field_to_u64(value: &Fp) -> u64 {
+    u64::from_le_bytes(value.to_bytes()[..8].try_into().unwrap())
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FfxConfig {
+    advices: [Column<Advice>; 2],
+    q_mod: Selector,
+    decompose_config: U32DecomposeConfig,
+}
+
+impl FfxConfig {
+    /// Configure on its own 2 advice columns, the first one range checked by the u8 table.
+    /// NOTE: A constant column must be enabled in the constraint system,
+    /// which `FixedAes128Config` does for the key schedule.
+    pub fn configure(meta: &mut ConstraintSystem<Fp>, tables: &TableColumns) -> Self {
+        let advices = [meta.advice_column(), meta.advice_column()];
+        let q_mod = meta.selector();
+
+        let q_u8_range_check = meta.complex_selector();
+        let u8_range_check_config = U8RangeCheckChip::configure(
+            meta,
+            advices[0],
+            q_u8_range_check,
+            tables.u8[0],
+            tables.u8[1],
+        );
+        let decompose_config = U32DecomposeChip::configure(meta, advices, u8_range_check_config);
+
+        // | a | y | q | m | c | d | in advices[1]
+        // a + y = q * m + c, and c + d = m - 1 so that c < m once c and d are u32.
+        meta.create_gate("Reduce modulo m", |meta| {
+            let q_mod = meta.query_selector(q_mod);
+            let [a, y, q, m, c, d] =
+                [0, 1, 2, 3, 4, 5].map(|i| meta.query_advice(advices[1], Rotation(i)));
+
+            vec![
+                q_mod.clone() * (a + y - q * m.clone() - c.clone()),
+                q_mod * (m - Expression::Constant(Fp::from(1)) - c - d),
+            ]
+        });
+
+        Self {
+            advices,
+            q_mod,
+            decompose_config,
+        }
+    }
+
+    /// Encrypt the numeral string in radix `radix`, returning the numbers of the two halves
+    /// of the ciphertext, see `halves_to_numeral`.
+    /// The halves of the plaintext are private witnesses.
+    /// The numeral string should be of an even positive length, with digits less than
+    /// the radix of at least 2 and at most 2^32 values per half, else `AesError::InvalidNumeral`.
+    pub fn encrypt<const K: u32, const N: usize>(
+        &self,
+        aes: &Aes128Scheduled<K, N>,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        radix: u32,
+        digits: &[u8],
+    ) -> Result<[AssignedCell<Fp, Fp>; 2], AesError> {
+        let modulus = check_numeral(radix, digits)?;
+        let (a, b) = numeral_to_halves(radix, digits);
+        let decompose_chip = U32DecomposeChip::construct(self.decompose_config);
+        let zero = self.assign_constants(layouter, &[0])?.remove(0);
+        let a = self.assign_value(layouter, Value::known(Fp::from(a)))?;
+        let b = self.assign_value(layouter, Value::known(Fp::from(b)))?;
+        let mut a = self.reduce(layouter, &zero, &a, modulus)?;
+        let mut b = self.reduce(layouter, &zero, &b, modulus)?;
+
+        for round in 0..ROUNDS {
+            let mut block = self.assign_constants(layouter, &round_block(round, 0)[..12])?;
+            block.extend(decompose_chip.decompose(layouter, &b)?.into_iter().rev());
+
+            let output = aes.encrypt_cells(cursor, layouter, &block)?;
+            let y_bytes = output[..4].iter().rev().cloned().collect::<Vec<_>>();
+            let y = decompose_chip.compose(layouter, &y_bytes)?;
+
+            let c = self.reduce(layouter, &a, &y, modulus)?;
+            (a, b) = (b, c);
+        }
+
+        Ok([a, b])
+    }
+
+    // Reduce a + y modulo m, where a and y are less than 2^32 and m at most 2^32.
+    fn reduce(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: &AssignedCell<Fp, Fp>,
+        y: &AssignedCell<Fp, Fp>,
+        modulus: u64,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let decompose_chip = U32DecomposeChip::construct(self.decompose_config);
+        let column = self.advices[1];
+
+        let sum = a
+            .value()
+            .zip(y.value())
+            .map(|(a, y)| field_to_u64(a) + field_to_u64(y));
+        let (q, c, d) = layouter.assign_region(
+            || "Reduce modulo m",
+            |mut region| {
+                self.q_mod.enable(&mut region, 0)?;
+                a.copy_advice(|| "copy a", &mut region, column, 0)?;
+                y.copy_advice(|| "copy y", &mut region, column, 1)?;
+                let q = region.assign_advice(
+                    || "assign q",
+                    column,
+                    2,
+                    || sum.map(|sum| Fp::from(sum / modulus)),
+                )?;
+                region.assign_advice_from_constant(|| "assign m", column, 3, Fp::from(modulus))?;
+                let c = region.assign_advice(
+                    || "assign c",
+                    column,
+                    4,
+                    || sum.map(|sum| Fp::from(sum % modulus)),
+                )?;
+                let d = region.assign_advice(
+                    || "assign d",
+                    column,
+                    5,
+                    || sum.map(|sum| Fp::from(modulus - 1 - sum % modulus)),
+                )?;
+                Ok((q, c, d))
+            },
+        )?;
+
+        // Range check q, c and d to u32, so that the equations hold over the integers
+        for word in [&q, &c, &d] {
+            decompose_chip.decompose(layouter, word)?;
+        }
+        Ok(c)
+    }
+
+    fn assign_value(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        value: Value<Fp>,
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        layouter.assign_region(
+            || "Assign half",
+            |mut region| region.assign_advice(|| "assign half", self.advices[1], 0, || value),
+        )
+    }
+
+    fn assign_constants(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        bytes: &[u8],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        layouter.assign_region(
+            || "Assign constant bytes",
+            |mut region| {
+                bytes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &b)| {
+                        region.assign_advice_from_constant(
+                            || "Assign constant byte",
+                            self.advices[0],
+                            i,
+                            Fp::from(b as u64),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        aes128::FixedAes128Config,
+        halo2_proofs::{
+            circuit::SimpleFloorPlanner,
+            plonk::{Circuit, ConstraintSystem},
+        },
+        reference::key_schedule_reference,
+    };
+    use std::collections::HashSet;
+
+    // FIPS-197 Appendix C.1
+    const KEY: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+
+    #[test]
+    fn test_numeral_halves() {
+        assert_eq!(numeral_to_halves(10, &[1, 2, 3, 4]), (12, 34));
+        assert_eq!(halves_to_numeral(10, 4, (12, 34)), vec![1, 2, 3, 4]);
+        assert_eq!(halves_to_numeral(10, 4, (0, 5)), vec![0, 0, 0, 5]);
+    }
+
+    #[test]
+    fn test_check_numeral() {
+        assert_eq!(check_numeral(10, &[1, 2, 3, 4]).unwrap(), 100);
+        assert_eq!(check_numeral(2, &[1; 64]).unwrap(), 1 << 32);
+
+        for (radix, digits) in [
+            (10, &[][..]),
+            (10, &[1, 2, 3][..]),
+            (1, &[0, 0][..]),
+            (10, &[1, 10][..]),
+            (2, &[1; 66][..]),
+        ] {
+            assert!(matches!(
+                check_numeral(radix, digits),
+                Err(AesError::InvalidNumeral { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ffx_round_trip() {
+        let round_keys = key_schedule_reference(KEY);
+
+        // Every 4 digits decimal string
+        let mut ciphertexts = HashSet::new();
+        for x in 0..10_000 {
+            let digits = halves_to_numeral(10, 4, (x / 100, x % 100));
+            let ciphertext = ffx_encrypt_reference(&round_keys, 10, &digits);
+
+            assert!(ciphertext.iter().all(|&d| d < 10));
+            assert_eq!(ffx_decrypt_reference(&round_keys, 10, &ciphertext), digits);
+            ciphertexts.insert(ciphertext);
+        }
+        assert_eq!(ciphertexts.len(), 10_000);
+    }
+
+    #[derive(Clone)]
+    struct TestFfxCircuit {
+        digits: Vec<u8>,
+    }
+
+    impl Circuit<Fp> for TestFfxCircuit {
+        type Config = (FixedAes128Config<17, 1>, FfxConfig);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let aes_config = FixedAes128Config::configure(meta);
+            let ffx_config = FfxConfig::configure(meta, &aes_config.tables);
+            (aes_config, ffx_config)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let (mut aes_config, ffx_config) = config;
+            aes_config.load_tables(&mut layouter)?;
+            let aes = aes_config.schedule_key(&mut layouter, KEY)?;
+            let mut cursor = EncryptCursor::default();
+
+            let [a, b] = ffx_config.encrypt(&aes, &mut cursor, &mut layouter, 10, &self.digits)?;
+
+            let expected = numeral_to_halves(
+                10,
+                &ffx_encrypt_reference(&key_schedule_reference(KEY), 10, &self.digits),
+            );
+            a.value()
+                .zip(b.value())
+                .assert_if_known(|(a, b)| (field_to_u64(a), field_to_u64(b)) == expected);
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_ffx_circuit() {
+        use crate::halo2_proofs::dev::MockProver;
+
+        let circuit = TestFfxCircuit {
+            digits: vec![1, 2, 3, 4, 5, 6],
+        };
+        let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+        mock.assert_satisfied();
+    }
+}
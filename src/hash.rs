@@ -0,0 +1,113 @@
+//! Poseidon hash of the 16 bytes block, used to commit to a key or the ciphertext
+//! with a single field element instead of 16 instance cells.
+//!
+//! The hash is Poseidon with width 3 and rate 2 over the BN256 scalar field,
+//! each byte being one element of the constant length message.
+
+use halo2_gadgets::poseidon::{
+    primitives::{self as poseidon, generate_constants, ConstantLength, Mds, Spec},
+    Hash, Pow5Chip, Pow5Config,
+};
+
+use crate::halo2_proofs::{
+    arithmetic::Field,
+    circuit::{AssignedCell, Layouter},
+    halo2curves::bn256::Fr as Fp,
+    plonk::{ConstraintSystem, Error},
+};
+
+const WIDTH: usize = 3;
+const RATE: usize = 2;
+
+/// Poseidon parameters for the BN256 scalar field, x^5 S-box.
+#[derive(Clone, Copy, Debug)]
+pub struct PoseidonSpec;
+
+impl Spec<Fp, WIDTH, RATE> for PoseidonSpec {
+    fn full_rounds() -> usize {
+        8
+    }
+
+    fn partial_rounds() -> usize {
+        57
+    }
+
+    fn sbox(val: Fp) -> Fp {
+        val.pow_vartime([5])
+    }
+
+    fn secure_mds() -> usize {
+        0
+    }
+
+    fn constants() -> (Vec<[Fp; WIDTH]>, Mds<Fp, WIDTH>, Mds<Fp, WIDTH>) {
+        generate_constants::<_, Self, WIDTH, RATE>()
+    }
+}
+
+/// Hash of the block computed out of the circuit, e.g. to build the instance.
+pub fn hash_block(block: [u8; 16]) -> Fp {
+    let message = block.map(|b| Fp::from(b as u64));
+    poseidon::Hash::<_, PoseidonSpec, ConstantLength<16>, WIDTH, RATE>::init().hash(message)
+}
+
+/// Commitments to the CTR keystream exposed by `Aes128Scheduled::ctr_commit_keystream`,
+/// i.e. the hash of each 16 bytes block of the keystream.
+pub fn keystream_commitments(keystream: &[u8]) -> Vec<Fp> {
+    assert_eq!(keystream.len() % 16, 0, "Keystream should be whole blocks");
+
+    keystream
+        .chunks(16)
+        .map(|block| hash_block(block.try_into().unwrap()))
+        .collect()
+}
+
+/// Public inputs of `Aes128Scheduled::batch_encrypt_committed`:
+/// the key commitment, i.e. the hash of the key, then the bytes of each ciphertext.
+pub fn batch_public_inputs(key: [u8; 16], ciphertexts: &[[u8; 16]]) -> Vec<Fp> {
+    std::iter::once(hash_block(key))
+        .chain(ciphertexts.iter().flatten().map(|&b| Fp::from(b as u64)))
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockHashConfig {
+    poseidon: Pow5Config<Fp, WIDTH, RATE>,
+}
+
+impl BlockHashConfig {
+    /// Configure the Poseidon chip on its own columns.
+    /// NOTE: A constant column must be enabled in the constraint system,
+    /// which `FixedAes128Config` does for the key schedule.
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        let state = std::array::from_fn(|_| meta.advice_column());
+        let partial_sbox = meta.advice_column();
+        let rc_a = std::array::from_fn(|_| meta.fixed_column());
+        let rc_b = std::array::from_fn(|_| meta.fixed_column());
+
+        state
+            .iter()
+            .for_each(|advice| meta.enable_equality(*advice));
+
+        Self {
+            poseidon: Pow5Chip::configure::<PoseidonSpec>(meta, state, partial_sbox, rc_a, rc_b),
+        }
+    }
+
+    /// Hash the 16 bytes block given as cells.
+    pub fn hash_block(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        block: &[AssignedCell<Fp, Fp>],
+    ) -> Result<AssignedCell<Fp, Fp>, Error> {
+        let message: [AssignedCell<Fp, Fp>; 16] =
+            block.to_vec().try_into().expect("Block should be 16 bytes");
+
+        let chip = Pow5Chip::construct(self.poseidon.clone());
+        let hasher = Hash::<_, _, PoseidonSpec, ConstantLength<16>, WIDTH, RATE>::init(
+            chip,
+            layouter.namespace(|| "Init block hash"),
+        )?;
+        hasher.hash(layouter.namespace(|| "Hash block"), message)
+    }
+}
@@ -0,0 +1,125 @@
+//! Proof of knowledge of an AES-128 key mapping a public plaintext to a public ciphertext.
+//!
+//! The key is a private witness. The instance column holds the plaintext
+//! followed by the ciphertext, see `key_knowledge_public_inputs`.
+
+use crate::{
+    aes128::{EncryptCursor, FixedAes128Config},
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+};
+
+/// Build the public input vector of `KeyKnowledgeCircuit`.
+pub fn key_knowledge_public_inputs(plaintext: [u8; 16], ciphertext: [u8; 16]) -> Vec<Fp> {
+    plaintext
+        .iter()
+        .chain(ciphertext.iter())
+        .map(|&b| Fp::from(b as u64))
+        .collect()
+}
+
+/// Schedule the private key, encrypt the public plaintext
+/// and constrain the ciphertext to the public ciphertext.
+pub fn prove_key_knowledge<const K: u32, const N: usize>(
+    config: FixedAes128Config<K, N>,
+    layouter: &mut impl Layouter<Fp>,
+    key: [u8; 16],
+    plaintext: [u8; 16],
+) -> Result<(), Error> {
+    let aes = config.schedule_key(layouter, key)?;
+    let mut cursor = EncryptCursor::default();
+    let ciphertext = aes.encrypt_public_plaintext(&mut cursor, layouter, plaintext)?;
+    aes.expose_ciphertext(&mut cursor, layouter, &ciphertext)
+}
+
+/// Circuit proving "I know the key which encrypts this plaintext to this ciphertext".
+#[derive(Clone, Debug)]
+pub struct KeyKnowledgeCircuit<const K: u32> {
+    key: [u8; 16],
+    plaintext: [u8; 16],
+}
+
+impl<const K: u32> KeyKnowledgeCircuit<K> {
+    pub fn new(key: [u8; 16], plaintext: [u8; 16]) -> Self {
+        Self { key, plaintext }
+    }
+}
+
+impl<const K: u32> Circuit<Fp> for KeyKnowledgeCircuit<K> {
+    type Config = FixedAes128Config<K, 1>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        FixedAes128Config::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        mut config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.load_tables(&mut layouter)?;
+        prove_key_knowledge(config, &mut layouter, self.key, self.plaintext)
+    }
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            key: [0u8; 16],
+            plaintext: self.plaintext,
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "halo2-pse")]
+mod tests {
+    use super::*;
+
+    use crate::halo2_proofs::dev::MockProver;
+
+    // FIPS-197 Appendix C.1
+    const KEY: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const PLAINTEXT: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+    const CIPHERTEXT: [u8; 16] = [
+        0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5,
+        0x5a,
+    ];
+
+    #[test]
+    fn test_key_knowledge() {
+        let circuit = KeyKnowledgeCircuit::<17>::new(KEY, PLAINTEXT);
+        let instance = key_knowledge_public_inputs(PLAINTEXT, CIPHERTEXT);
+        let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[test]
+    fn test_key_knowledge_wrong_key_fails() {
+        let mut key = KEY;
+        key[0] ^= 1;
+        let circuit = KeyKnowledgeCircuit::<17>::new(key, PLAINTEXT);
+        let instance = key_knowledge_public_inputs(PLAINTEXT, CIPHERTEXT);
+        let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+
+    #[test]
+    fn test_key_knowledge_wrong_plaintext_fails() {
+        // The prover encrypts another plaintext than the public one
+        let mut plaintext = PLAINTEXT;
+        plaintext[15] ^= 1;
+        let circuit = KeyKnowledgeCircuit::<17>::new(KEY, plaintext);
+        let instance = key_knowledge_public_inputs(PLAINTEXT, CIPHERTEXT);
+        let mock = MockProver::run(17, &circuit, vec![instance]).unwrap();
+        assert!(mock.verify().is_err());
+    }
+}
@@ -1,11 +1,16 @@
 //! Key expansion chip for AES key scheduling
-//! NOTE: currently implemented only for 128 bit key.
+//! NOTE: `schedule_keys` is implemented only for 128 bit key,
+//! `schedule_keys_192` and `schedule_keys_256` for the other key sizes.
 //!
 //! What key expansion does?
 //! Take 4 words (=16 bytes) as input and output 44 words.
 //! This suffices for the initial AddRoundKey phase and 10 rounds.
+//! A key of Nk words is expanded to 4 * (Nk + 7) words for Nk + 6 rounds:
+//! 52 words for the 6 words of a 192 bit key, 60 words for the 8 words of a 256 bit key,
+//! with the extra SubWord on the 4th word of every 8.
 //!
-//! Key expansion
+//! Key expansion is done on the flat stream of words,
+//! so the same logic expands the keys of the other sizes.
 
 use crate::{
     chips::{
@@ -13,6 +18,7 @@ use crate::{
         u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
         u8_xor_chip::{U8XorChip, U8XorConfig},
     },
+    fault::{inject, FaultSite},
     halo2_proofs::{
         circuit::{AssignedCell, Layouter, Value},
         halo2curves::bn256::Fr as Fp,
@@ -33,6 +39,9 @@ pub struct Aes128KeyScheduleConfig {
     u8_range_check_config: U8RangeCheckConfig,
     u8_xor_config: U8XorConfig,
     sbox_config: SboxConfig,
+
+    // Skip range checks of the derived words
+    skip_range_check: bool,
 }
 
 impl Aes128KeyScheduleConfig {
@@ -73,93 +82,236 @@ impl Aes128KeyScheduleConfig {
             u8_range_check_config,
             u8_xor_config,
             sbox_config,
+            skip_range_check: false,
         }
     }
 
+    /// Name the columns owned by the key schedule.
+    #[cfg(feature = "halo2-pse")]
+    pub fn annotate_columns(&self, meta: &mut ConstraintSystem<Fp>) {
+        meta.annotate_lookup_any_column(self.words_column, || "key_schedule_words");
+        meta.annotate_lookup_any_column(self.round_constants, || "key_schedule_round_constants");
+    }
+
+    /// Skip range checks of the derived words, saving 4 rows per word (160 rows for AES128).
+    ///
+    /// Each byte of a derived word is the output `z` of a XOR lookup,
+    /// and the table only contains rows with z in 0..256, so the bytes are
+    /// already constrained to be u8 without the range checks
+    /// (see `test_xor_output_is_byte_constrained` of the XOR chip).
+    pub fn set_skip_range_check(&mut self, skip: bool) {
+        self.skip_range_check = skip;
+    }
+
     /// Expand given 4 words key to 44 words key where each AssignedCell<Fp,Fp> represent a byte.
     pub fn schedule_keys(
         &self,
         layouter: &mut impl Layouter<Fp>,
         key: [u8; 16],
     ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, Error> {
-        let mut words = vec![];
+        self.schedule_round_keys(layouter, key)
+    }
+
+    /// Expand given 6 words key to 52 words key, the 13 round keys of AES-192.
+    /// The SubWord steps fall on every 6th word, so not on the round key boundaries.
+    pub fn schedule_keys_192(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: [u8; 24],
+    ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, Error> {
+        self.schedule_round_keys(layouter, key)
+    }
+
+    /// Expand given 8 words key to 60 words key, the 15 round keys of AES-256.
+    pub fn schedule_keys_256(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: [u8; 32],
+    ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, Error> {
+        self.schedule_round_keys(layouter, key)
+    }
 
-        let mut round = self.assign_first_round(layouter, key)?;
-        words.push(round.clone());
+    // Expand the key of KEY_BYTES / 4 words to the round keys of its KEY_BYTES / 4 + 6 rounds.
+    fn schedule_round_keys<const KEY_BYTES: usize>(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: [u8; KEY_BYTES],
+    ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, Error> {
+        let nk = KEY_BYTES / 4;
+        let words = self.expand_key(layouter, &key, 4 * (nk + 7))?;
+        Ok(words.chunks(16).map(|round| round.to_vec()).collect())
+    }
 
-        for i in 1..=10 {
-            round = self.assign_round(layouter, i, round)?;
-            words.push(round.clone())
+    /// Same as `schedule_keys` but takes the key as already assigned byte cells,
+    /// so that the key can be constrained by other chips.
+    /// The cells should be constrained to be bytes.
+    pub fn schedule_keys_from_cells(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: &[AssignedCell<Fp, Fp>],
+    ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, Error> {
+        assert_eq!(key.len(), 16, "key should be 16 bytes");
+        let words = self.expand_words(layouter, key.to_vec(), 44)?;
+        Ok(words.chunks(16).map(|round| round.to_vec()).collect())
+    }
+
+    /// Expand the key of Nk = key.len() / 4 words to `total_words` words
+    /// following the Rijndael key expansion. Returns the bytes of the words.
+    pub(crate) fn expand_key(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: &[u8],
+        total_words: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let first_round = self.assign_first_round(layouter, key)?;
+        self.expand_words(layouter, first_round, total_words)
+    }
+
+    // Expand the assigned key bytes to `total_words` words.
+    fn expand_words(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        key: Vec<AssignedCell<Fp, Fp>>,
+        total_words: usize,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let nk = key.len() / 4;
+        let mut words = key.chunks(4).map(|word| word.to_vec()).collect::<Vec<_>>();
+
+        for i in nk..total_words {
+            let word = self.assign_word(layouter, i, nk, &words[i - nk], &words[i - 1])?;
+            words.push(word);
+        }
+
+        // The derived words are range checked together after the expansion,
+        // the key bytes are already checked when assigned.
+        let words = words.concat();
+        if !self.skip_range_check {
+            let range_chip = U8RangeCheckChip::construct(self.u8_range_check_config);
+            range_chip.range_check_many(layouter, &words[key.len()..])?;
         }
 
         Ok(words)
     }
 
+    /// Assign precomputed round keys without deriving them from the key.
+    /// Each byte is range checked, but the key expansion relation is NOT constrained.
+    pub fn assign_precomputed_keys(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        round_keys: [[u8; 16]; 11],
+    ) -> Result<Vec<Vec<AssignedCell<Fp, Fp>>>, Error> {
+        let range_chip = U8RangeCheckChip::construct(self.u8_range_check_config);
+
+        round_keys
+            .iter()
+            .map(|round_key| {
+                layouter.assign_region(
+                    || "Assign precomputed round key",
+                    |mut region| {
+                        round_key
+                            .iter()
+                            .enumerate()
+                            .map(|(i, &byte)| {
+                                range_chip.assign_byte(&mut region, i, Value::known(byte as u64))
+                            })
+                            .collect::<Result<Vec<_>, Error>>()
+                    },
+                )
+            })
+            .collect()
+    }
+
     fn assign_first_round(
         &self,
         layouter: &mut impl Layouter<Fp>,
-        key: [u8; 16],
+        key: &[u8],
     ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let range_chip = U8RangeCheckChip::construct(self.u8_range_check_config);
+
         layouter.assign_region(
-            || "Assign first four words",
+            || "Assign first words",
             |mut region| {
-                let mut words: Vec<AssignedCell<Fp, Fp>> = vec![];
-                for (i, &byte) in key.iter().enumerate() {
-                    words.push(region.assign_advice(
-                        || format!("Assign {}-th word, {}-th byte", i / 4, i % 4),
-                        self.words_column,
-                        i,
-                        || Value::known(Fp::from(byte as u64)),
-                    )?);
-                }
-                Ok(words)
+                key.iter()
+                    .enumerate()
+                    .map(|(i, &byte)| {
+                        range_chip.assign_byte(&mut region, i, Value::known(byte as u64))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
             },
         )
     }
 
-    /// Assign intermediate bytes for each round.
-    /// prev_round_bytes has 16 bytes
-    fn assign_round(
+    /// Derive i-th word from (i - nk)-th word and the previous word.
+    fn assign_word(
         &self,
         layouter: &mut impl Layouter<Fp>,
-        round: u32,
-        prev_round_bytes: Vec<AssignedCell<Fp, Fp>>,
+        i: usize,
+        nk: usize,
+        first_word: &[AssignedCell<Fp, Fp>],
+        prev_word: &[AssignedCell<Fp, Fp>],
     ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
         let xor_chip = U8XorChip::construct(self.u8_xor_config);
         let sbox_chip = SboxChip::construct(self.sbox_config);
-        let range_chip = U8RangeCheckChip::construct(self.u8_range_check_config);
 
-        // resulting words == 44 words = 176 byte
-        let mut words: Vec<AssignedCell<Fp, Fp>> = vec![];
+        // Bytes XORed with the first word, byte by byte
+        let terms = if i % nk == 0 {
+            // copy prev word to words_column.
+            // prev_word is rotated one byte left-shifted
+            let rotated = layouter.assign_region(
+                || "rotate previous word",
+                |mut region| {
+                    [1usize, 2, 3, 0]
+                        .iter()
+                        .enumerate()
+                        .map(|(j, &v)| {
+                            prev_word[v].copy_advice(
+                                || "Copy previous word",
+                                &mut region,
+                                self.words_column,
+                                j,
+                            )
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                },
+            )?;
+
+            let subbed = rotated
+                .iter()
+                .map(|byte| sbox_chip.substitute(layouter, byte))
+                .collect::<Result<Vec<_>, Error>>()?;
 
-        // Derive the first word of the round.
-        // copy prev word to words_column. (last 4 bytes of prev_round_bytes)
-        // prev_word is rotated one byte left-shifted
-        let shifted = layouter.assign_region(
-            || "shift previous round",
-            |mut region| {
-                vec![13usize, 14, 15, 12]
-                    .iter()
-                    .enumerate()
-                    .map(|(i, &v)| {
-                        prev_round_bytes[v].copy_advice(
-                            || "Copy word from prev_round",
-                            &mut region,
-                            self.words_column,
-                            i,
-                        )
-                    })
-                    .collect::<Result<Vec<_>, Error>>()
-            },
-        )?;
+            let rc_assigned = self.assign_round_constant(layouter, (i / nk - 1) as u32)?;
+
+            subbed
+                .into_iter()
+                .zip(rc_assigned)
+                .map(|(s, r)| vec![s, r])
+                .collect::<Vec<_>>()
+        } else if nk > 6 && i % nk == 4 {
+            // Extra SubWord for 256 bit key
+            prev_word
+                .iter()
+                .map(|byte| sbox_chip.substitute(layouter, byte).map(|s| vec![s]))
+                .collect::<Result<Vec<_>, Error>>()?
+        } else {
+            prev_word.iter().map(|byte| vec![byte.clone()]).collect()
+        };
 
-        let subbed = shifted
+        first_word
             .iter()
-            .map(|byte| sbox_chip.substitute(layouter, byte))
-            .collect::<Result<Vec<_>, Error>>()?;
+            .zip(terms)
+            .map(|(f, terms)| xor_chip.xor_many(layouter, &[vec![f.clone()], terms].concat()))
+            .collect()
+    }
 
-        let rc = get_round_constant(round - 1);
-        let rc_assigned = layouter.assign_region(
+    /// Assign the round constant word (rc, 0, 0, 0) of given round.
+    fn assign_round_constant(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        round: u32,
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, Error> {
+        let rc = get_round_constant(round)?;
+        layouter.assign_region(
             || "Assign rc",
             |mut region| {
                 let mut res = vec![];
@@ -171,56 +323,24 @@ impl Aes128KeyScheduleConfig {
                     || "Copy fixed value to words_column",
                     self.words_column,
                     0,
-                    || rc,
+                    || inject(FaultSite::RoundConstant, rc),
                 )?);
 
+                // The padding is fixed to constants, otherwise it's a free witness
                 for i in 0..3 {
-                    res.push(region.assign_advice(
+                    let pad = region.assign_advice(
                         || "Pad 0",
                         self.words_column,
                         i + 1,
-                        || Value::known(Fp::from(0)),
-                    )?);
+                        || inject(FaultSite::RoundConstant, Value::known(Fp::from(0))),
+                    )?;
+                    region.constrain_constant(pad.cell(), Fp::from(0))?;
+                    res.push(pad);
                 }
 
                 Ok(res)
             },
-        )?;
-
-        let rconned = subbed
-            .iter()
-            .zip(rc_assigned)
-            .map(|(s, r)| xor_chip.xor(layouter, &s, &r))
-            .collect::<Result<Vec<_>, Error>>()?;
-
-        // xor prev_round_word and rconned_word
-        let mut next_word = prev_round_bytes
-            .iter()
-            .take(4)
-            .zip(rconned)
-            .map(|(p, r)| xor_chip.xor(layouter, &p, &r))
-            .collect::<Result<Vec<_>, Error>>()?;
-
-        words.append(&mut next_word.clone());
-
-        // consecutive 3 words
-        for i in 1..4 {
-            next_word = prev_round_bytes
-                .iter()
-                .skip(i * 4)
-                .take(4)
-                .zip(next_word)
-                .map(|(p, n)| xor_chip.xor(layouter, &p, &n))
-                .collect::<Result<Vec<_>, Error>>()?;
-            words.append(&mut next_word.clone());
-        }
-
-        words
-            .iter()
-            .map(|byte| range_chip.range_check(layouter, &byte))
-            .collect::<Result<Vec<_>, Error>>()?;
-
-        Ok(words)
+        )
     }
 }
 
@@ -237,11 +357,13 @@ mod tests {
             plonk::{Circuit, ConstraintSystem, Error, TableColumn},
         },
         table::load_enc_full_table,
+        utils::cells_to_bytes,
     };
 
     #[derive(Clone)]
     struct TestCircuit {
         key: [u8; 16],
+        skip_range_check: bool,
     }
 
     impl Circuit<Fp> for TestCircuit {
@@ -298,10 +420,11 @@ mod tests {
             config: Self::Config,
             mut layouter: impl Layouter<Fp>,
         ) -> Result<(), Error> {
-            load_enc_full_table(&mut layouter, config.1)?;
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+            let mut key_schedule_config = config.0;
+            key_schedule_config.set_skip_range_check(self.skip_range_check);
             // let words =
-            config
-                .0
+            key_schedule_config
                 .schedule_keys(&mut layouter.namespace(|| "AES128 schedule key"), self.key)?;
 
             // words.iter().enumerate().for_each(|(i, word)| {
@@ -320,18 +443,9 @@ mod tests {
     }
 
     fn get_key_positions() -> Vec<usize> {
-        let mut indicies = (0..16).collect::<Vec<_>>();
-        let offset = 16;
-        let interval = 48;
-        let word_starts = [20, 28, 36, 44];
-
-        for i in 0..10 {
-            for start in word_starts {
-                (0..4).for_each(|j| indicies.push(offset + i * interval + start + j));
-            }
-        }
-
-        indicies
+        // The derived words are range checked in order after the 10 rounds of 32 rows
+        let offset = 16 + 10 * 32;
+        (0..16).chain(offset..offset + 160).collect()
     }
 
     const EXPANDED: [&str; 44] = [
@@ -349,7 +463,10 @@ mod tests {
     #[ignore]
     fn test_correct_key_scheduling() {
         let k = 17;
-        let circuit = TestCircuit { key: [0u8; 16] };
+        let circuit = TestCircuit {
+            key: [0u8; 16],
+            skip_range_check: false,
+        };
 
         let mock = MockProver::run(k, &circuit, vec![]).unwrap();
 
@@ -385,19 +502,188 @@ mod tests {
     #[test]
     fn test_constraints() {
         let k = 17;
-        let circuit = TestCircuit { key: [0u8; 16] };
+        let circuit = TestCircuit {
+            key: [0u8; 16],
+            skip_range_check: false,
+        };
 
         let mock = MockProver::run(k, &circuit, vec![]).unwrap();
         mock.assert_satisfied();
     }
 
+    // Schedules the 192 or 256 bit key and checks the round keys against the expected ones
+    #[derive(Clone)]
+    struct TestKeySizeCircuit {
+        key: Vec<u8>,
+        expected: Vec<[u8; 16]>,
+    }
+
+    impl Circuit<Fp> for TestKeySizeCircuit {
+        type Config = (Aes128KeyScheduleConfig, [TableColumn; 4]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            TestCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, config.1, 17)?;
+            let mut layouter = layouter.namespace(|| "schedule key");
+            let round_keys = match self.key.len() {
+                24 => config
+                    .0
+                    .schedule_keys_192(&mut layouter, self.key.clone().try_into().unwrap())?,
+                32 => config
+                    .0
+                    .schedule_keys_256(&mut layouter, self.key.clone().try_into().unwrap())?,
+                len => panic!("no AES key of {} bytes", len),
+            };
+
+            assert_eq!(round_keys.len(), self.expected.len());
+            for (round_key, expected) in round_keys.iter().zip(&self.expected) {
+                cells_to_bytes(round_key).assert_if_known(|bytes| bytes[..] == expected[..]);
+            }
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    // FIPS-197 Appendix A.2
+    const KEY_192: &str = "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b";
+    const EXPANDED_192: [&str; 13] = [
+        "8e73b0f7da0e6452c810f32b809079e5",
+        "62f8ead2522c6b7bfe0c91f72402f5a5",
+        "ec12068e6c827f6b0e7a95b95c56fec2",
+        "4db7b4bd69b5411885a74796e92538fd",
+        "e75fad44bb095386485af05721efb14f",
+        "a448f6d94d6dce24aa326360113b30e6",
+        "a25e7ed583b1cf9a27f939436a94f767",
+        "c0a69407d19da4e1ec1786eb6fa64971",
+        "485f703222cb8755e26d135233f0b7b3",
+        "40beeb282f18a2596747d26b458c553e",
+        "a7e1466c9411f1df821f750aad07d753",
+        "ca4005388fcc5006282d166abc3ce7b5",
+        "e98ba06f448c773c8ecc720401002202",
+    ];
+
+    // FIPS-197 Appendix A.3
+    const KEY_256: &str = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+    const EXPANDED_256: [&str; 15] = [
+        "603deb1015ca71be2b73aef0857d7781",
+        "1f352c073b6108d72d9810a30914dff4",
+        "9ba354118e6925afa51a8b5f2067fcde",
+        "a8b09c1a93d194cdbe49846eb75d5b9a",
+        "d59aecb85bf3c917fee94248de8ebe96",
+        "b5a9328a2678a647983122292f6c79b3",
+        "812c81addadf48ba24360af2fab8b464",
+        "98c5bfc9bebd198e268c3ba709e04214",
+        "68007bacb2df331696e939e46c518d80",
+        "c814e20476a9fb8a5025c02d59c58239",
+        "de1369676ccc5a71fa2563959674ee15",
+        "5886ca5d2e2f31d77e0af1fa27cf73c3",
+        "749c47ab18501ddae2757e4f7401905a",
+        "cafaaae3e4d59b349adf6acebd10190d",
+        "fe4890d1e6188d0b046df344706c631e",
+    ];
+
+    fn hex_to_bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn key_size_circuit(key: &str, expanded: &[&str]) -> TestKeySizeCircuit {
+        TestKeySizeCircuit {
+            key: hex_to_bytes(key),
+            expected: expanded
+                .iter()
+                .map(|hex| hex_to_bytes(hex).try_into().unwrap())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_constraints_192() {
+        let circuit = key_size_circuit(KEY_192, &EXPANDED_192);
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[test]
+    fn test_constraints_256() {
+        let circuit = key_size_circuit(KEY_256, &EXPANDED_256);
+        let mock = MockProver::run(17, &circuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[test]
+    fn test_constraints_without_range_check() {
+        let k = 17;
+        let with_check = MockProver::run(
+            k,
+            &TestCircuit {
+                key: [0u8; 16],
+                skip_range_check: false,
+            },
+            vec![],
+        )
+        .unwrap();
+        let without_check = MockProver::run(
+            k,
+            &TestCircuit {
+                key: [0u8; 16],
+                skip_range_check: true,
+            },
+            vec![],
+        )
+        .unwrap();
+        without_check.assert_satisfied();
+
+        // 40 derived words of 4 bytes
+        let rows = crate::dev::used_rows_per_advice(&with_check)[0];
+        let rows_skipped = crate::dev::used_rows_per_advice(&without_check)[0];
+        assert_eq!(rows - rows_skipped, 160);
+    }
+
+    #[test]
+    fn test_key_schedule_rows() {
+        let mock = MockProver::run(
+            17,
+            &TestCircuit {
+                key: [0u8; 16],
+                skip_range_check: false,
+            },
+            vec![],
+        )
+        .unwrap();
+
+        // The capacity of the first group of columns relies on this
+        let rows = crate::dev::used_rows_per_advice(&mock);
+        assert_eq!(rows[0] as u64, crate::constant::KEY_SCHEDULE_ROWS);
+        assert!(rows
+            .iter()
+            .all(|&r| r as u64 <= crate::constant::KEY_SCHEDULE_ROWS));
+    }
+
     #[cfg(feature = "dev-graph")]
     #[test]
     fn print_key_schedule() {
         use plotters::prelude::*;
 
         let k = 17;
-        let circuit = TestCircuit { key: [0u8; 16] };
+        let circuit = TestCircuit {
+            key: [0u8; 16],
+            skip_range_check: false,
+        };
 
         let root =
             BitMapBackend::new("prints/key-schedule-layout.png", (2048, 32768)).into_drawing_area();
@@ -417,7 +703,10 @@ mod tests {
         use halo2_proofs::dev::cost_model::{from_circuit_to_model_circuit, CommitmentScheme};
 
         let k = 17;
-        let circuit = TestCircuit { key: [0u8; 16] };
+        let circuit = TestCircuit {
+            key: [0u8; 16],
+            skip_range_check: false,
+        };
 
         let model = from_circuit_to_model_circuit::<_, _, 56, 56>(
             k,
@@ -429,5 +718,12 @@ mod tests {
             "Cost of AES128 key schedule: \n{}",
             serde_json::to_string_pretty(&model).unwrap()
         );
+
+        let mock = MockProver::run(k, &circuit, vec![]).unwrap();
+        println!(
+            "Used rows per advice column: {:?} (KEY_SCHEDULE_ROWS: {})",
+            crate::dev::used_rows_per_advice(&mock),
+            crate::constant::KEY_SCHEDULE_ROWS
+        );
     }
 }
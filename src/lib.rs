@@ -1,11 +1,33 @@
 pub mod aes128;
+pub mod auto;
+pub mod block;
 pub mod chips;
 pub mod constant;
+pub mod ctr_chain;
+#[cfg(feature = "halo2-pse")]
+pub mod dev;
+pub mod error;
+pub(crate) mod fault;
+pub mod ffx;
+#[cfg(feature = "poseidon")]
+pub mod hash;
+pub mod key_knowledge;
 pub mod key_schedule;
+#[cfg(feature = "halo2-pse")]
+pub mod prover;
+pub mod reference;
+pub mod rijndael256;
+pub mod state;
 pub mod table;
 pub mod utils;
 
-pub use aes128::FixedAes128Config;
+pub use aes128::{
+    build_public_inputs, recommended_k, validate_params, Aes128Scheduled, CompactAes128Config,
+    EncryptCursor, EncryptOutput, FixedAes128Config,
+};
+pub use auto::{Aes128, ScheduledAes128};
+pub use block::{Ciphertext, Plaintext};
+pub use error::AesError;
 
 #[cfg(feature = "halo2-pse")]
 pub use halo2_proofs;
@@ -2,27 +2,15 @@ use ark_std::{end_timer, start_timer};
 use halo2_aes::{
     halo2_proofs::{
         circuit::{Layouter, SimpleFloorPlanner},
-        halo2curves::bn256::{Bn256, Fr as Fp, G1Affine},
-        plonk::{
-            create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, Error, ProvingKey,
-            VerifyingKey,
-        },
-        poly::{
-            commitment::Params,
-            kzg::{
-                commitment::{KZGCommitmentScheme, ParamsKZG},
-                multiopen::ProverSHPLONK,
-            },
-        },
-        transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{Circuit, ConstraintSystem, Error},
     },
-    table::load_enc_full_table,
-    FixedAes128Config,
+    prover::{keygen, kzg_params, prove_kzg},
+    validate_params, EncryptCursor, FixedAes128Config,
 };
-use rand::rngs::OsRng;
-use std::fs::File;
 
 const K: u32 = 20;
+const N: usize = 4;
 
 #[derive(Clone, Copy)]
 struct Aes128BenchCircuit {
@@ -32,7 +20,7 @@ struct Aes128BenchCircuit {
 }
 
 impl Circuit<Fp> for Aes128BenchCircuit {
-    type Config = FixedAes128Config<K, 4>;
+    type Config = FixedAes128Config<K, N>;
     type FloorPlanner = SimpleFloorPlanner;
 
     fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
@@ -44,10 +32,11 @@ impl Circuit<Fp> for Aes128BenchCircuit {
         mut config: Self::Config,
         mut layouter: impl Layouter<Fp>,
     ) -> Result<(), Error> {
-        load_enc_full_table(&mut layouter, config.tables)?;
-        config.schedule_key(&mut layouter, self.key)?;
+        config.load_tables(&mut layouter)?;
+        let aes = config.schedule_key(&mut layouter, self.key)?;
+        let mut cursor = EncryptCursor::default();
         for _ in 0..self.encrypt_num {
-            config.encrypt(&mut layouter, self.plaintext)?;
+            aes.encrypt(&mut cursor, &mut layouter, self.plaintext)?;
         }
 
         Ok(())
@@ -58,50 +47,28 @@ impl Circuit<Fp> for Aes128BenchCircuit {
     }
 }
 
-fn setup_params<C: Circuit<Fp>>(
-    k: u32,
-    circuit: C,
-) -> (
-    ParamsKZG<Bn256>,
-    ProvingKey<G1Affine>,
-    VerifyingKey<G1Affine>,
-) {
-    // load kzg params if available
-    let path = format!("ptau/kzg_bn254_{}.srs", k);
-    let params = if let Ok(mut fs) = File::open(path) {
-        ParamsKZG::<Bn256>::read(&mut fs).expect("Failed to read params")
-    } else {
-        ParamsKZG::<Bn256>::setup(k, OsRng)
-    };
-    println!("Parameter files loaded");
-
-    let vk = keygen_vk(&params, &circuit).expect("vk generation should not fail");
-    let pk = keygen_pk(&params, vk.clone(), &circuit).expect("pk generation should not fail");
-    (params, pk, vk)
-}
-
 fn main() {
     let circuit = Aes128BenchCircuit {
         key: [0u8; 16],
         plaintext: [0u8; 16],
         encrypt_num: 3000,
     };
-    let (params, pk, _) = setup_params(K, circuit.clone());
+    if let Err(errors) = validate_params(K, N, circuit.encrypt_num as u64) {
+        for err in errors {
+            eprintln!("{}", err);
+        }
+        panic!("Invalid parameters");
+    }
 
-    let tm = start_timer!(|| "Prove: AES encrypt start");
-    let mut transcript = Blake2bWrite::<Vec<u8>, G1Affine, Challenge255<G1Affine>>::init(vec![]);
+    let params = kzg_params(K);
+    println!("Parameter files loaded");
+    let (pk, _) = keygen(&params, &circuit).expect("key generation should not fail");
 
-    let result = create_proof::<
-        KZGCommitmentScheme<Bn256>,
-        ProverSHPLONK<'_, Bn256>,
-        Challenge255<G1Affine>,
-        _,
-        _,
-        _,
-    >(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript);
+    let tm = start_timer!(|| "Prove: AES encrypt start");
+    let result = prove_kzg(&params, &pk, circuit, &[&[]]);
     end_timer!(tm);
 
-    println!("Error: {:?}", result);
+    println!("Error: {:?}", result.as_ref().err());
     if result.is_err() {
         panic!("Create proof fail");
     }
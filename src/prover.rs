@@ -0,0 +1,413 @@
+//! Proving harness generic over the commitment scheme.
+//!
+//! `prove` and `verify` take the commitment scheme, prover and verifier as type parameters,
+//! so the same circuit can be proven with KZG, which needs a trusted setup,
+//! or IPA, which doesn't. `*_kzg` and `*_ipa` are shorthands for the common choices:
+//! KZG with SHPLONK multiopen on BN256, and IPA on the BN256 G1 curve.
+//!
+//! `prove_with_rng` and `prove_kzg_with_rng` take the RNG instead of `OsRng`,
+//! e.g. a seeded one on wasm32, where the params should be built in memory as well,
+//! see `examples/wasm.rs`, or for reproducible proofs in tests.
+//!
+//! `load_or_generate_params` reads the KZG params from any reader, e.g. a file,
+//! bytes embedded with `include_bytes!` or an in-memory buffer, independent of the file layout.
+//!
+//! The helpers using `OsRng` or `std::fs` aren't compiled on wasm32,
+//! which has neither.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::halo2_proofs::{
+    halo2curves::{
+        bn256::{Bn256, Fr as Fp, G1Affine},
+        CurveAffine,
+    },
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, Error, ProvingKey, VerifyingKey,
+    },
+    poly::{
+        commitment::{CommitmentScheme, Params, ParamsProver, Prover, Verifier},
+        ipa::{
+            commitment::{IPACommitmentScheme, ParamsIPA},
+            multiopen::{ProverIPA, VerifierIPA},
+            strategy::SingleStrategy as IpaSingleStrategy,
+        },
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy as KzgSingleStrategy,
+        },
+        VerificationStrategy,
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+
+/// Generate the proving key and the verifying key of the circuit.
+pub fn keygen<'params, C, P, ConcreteCircuit>(
+    params: &P,
+    circuit: &ConcreteCircuit,
+) -> Result<(ProvingKey<C>, VerifyingKey<C>), Error>
+where
+    C: CurveAffine<ScalarExt = Fp>,
+    P: Params<'params, C>,
+    ConcreteCircuit: Circuit<Fp>,
+{
+    let vk = keygen_vk(params, circuit)?;
+    let pk = keygen_pk(params, vk.clone(), circuit)?;
+
+    Ok((pk, vk))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Create a proof of the circuit with the given instance columns.
+pub fn prove<'params, Scheme, P, ConcreteCircuit>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuit: ConcreteCircuit,
+    instances: &[&[Fp]],
+) -> Result<Vec<u8>, Error>
+where
+    Scheme: CommitmentScheme<Scalar = Fp>,
+    P: Prover<'params, Scheme>,
+    ConcreteCircuit: Circuit<Fp>,
+{
+    prove_with_rng::<Scheme, P, _, _>(params, pk, circuit, instances, OsRng)
+}
+
+/// `prove` with the given RNG for the blinding factors.
+pub fn prove_with_rng<'params, Scheme, P, ConcreteCircuit, R>(
+    params: &'params Scheme::ParamsProver,
+    pk: &ProvingKey<Scheme::Curve>,
+    circuit: ConcreteCircuit,
+    instances: &[&[Fp]],
+    rng: R,
+) -> Result<Vec<u8>, Error>
+where
+    Scheme: CommitmentScheme<Scalar = Fp>,
+    P: Prover<'params, Scheme>,
+    ConcreteCircuit: Circuit<Fp>,
+    R: RngCore,
+{
+    let mut transcript =
+        Blake2bWrite::<Vec<u8>, Scheme::Curve, Challenge255<Scheme::Curve>>::init(vec![]);
+    create_proof::<Scheme, P, _, _, _, _>(
+        params,
+        pk,
+        &[circuit],
+        &[instances],
+        rng,
+        &mut transcript,
+    )?;
+
+    Ok(transcript.finalize())
+}
+
+/// Verify the proof against the given instance columns.
+pub fn verify<'params, Scheme, V, Strategy>(
+    params: &'params Scheme::ParamsVerifier,
+    vk: &VerifyingKey<Scheme::Curve>,
+    proof: &[u8],
+    instances: &[&[Fp]],
+) -> Result<(), Error>
+where
+    Scheme: CommitmentScheme<Scalar = Fp>,
+    V: Verifier<'params, Scheme>,
+    Strategy: VerificationStrategy<'params, Scheme, V, Output = ()>,
+{
+    let strategy = Strategy::new(params);
+    let mut transcript =
+        Blake2bRead::<&[u8], Scheme::Curve, Challenge255<Scheme::Curve>>::init(proof);
+
+    verify_proof::<Scheme, V, _, _, Strategy>(params, vk, strategy, &[instances], &mut transcript)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Read KZG params for `k` from the reader, or run an insecure setup for testing if there is none.
+/// Params for a larger k are downsized to `k`, params for a smaller k are an `InvalidData` error.
+pub fn load_or_generate_params(k: u32, reader: Option<impl Read>) -> io::Result<ParamsKZG<Bn256>> {
+    let Some(mut reader) = reader else {
+        return Ok(ParamsKZG::<Bn256>::setup(k, OsRng));
+    };
+
+    let mut params = ParamsKZG::<Bn256>::read(&mut reader)?;
+    if params.k() < k {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Params for k = {} are too small for k = {}", params.k(), k),
+        ));
+    }
+    if params.k() > k {
+        params.downsize(k);
+    }
+
+    Ok(params)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// Load KZG params from `ptau/kzg_bn254_{k}.srs` if available,
+/// otherwise run an insecure setup for testing.
+pub fn kzg_params(k: u32) -> ParamsKZG<Bn256> {
+    let path = format!("ptau/kzg_bn254_{}.srs", k);
+    load_or_generate_params(k, File::open(path).ok()).expect("Failed to read params")
+}
+
+/// IPA params. No trusted setup is needed.
+pub fn ipa_params(k: u32) -> ParamsIPA<G1Affine> {
+    ParamsIPA::<G1Affine>::new(k)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// `prove` with KZG and SHPLONK multiopen.
+pub fn prove_kzg<ConcreteCircuit: Circuit<Fp>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: ConcreteCircuit,
+    instances: &[&[Fp]],
+) -> Result<Vec<u8>, Error> {
+    prove_kzg_with_rng(params, pk, circuit, instances, OsRng)
+}
+
+/// `prove_kzg` with the given RNG. With a seeded RNG, the proof is the same on every run
+/// for the same params, circuit and instances, e.g. to compare proofs in tests.
+/// NOTE: The blinding factors are then predictable, which breaks zero knowledge.
+pub fn prove_kzg_with_rng<ConcreteCircuit: Circuit<Fp>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: ConcreteCircuit,
+    instances: &[&[Fp]],
+    rng: impl RngCore,
+) -> Result<Vec<u8>, Error> {
+    prove_with_rng::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _>(
+        params, pk, circuit, instances, rng,
+    )
+}
+
+/// `verify` with KZG and SHPLONK multiopen.
+pub fn verify_kzg(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    instances: &[&[Fp]],
+) -> Result<(), Error> {
+    verify::<KZGCommitmentScheme<Bn256>, VerifierSHPLONK<'_, Bn256>, KzgSingleStrategy<'_, Bn256>>(
+        params.verifier_params(),
+        vk,
+        proof,
+        instances,
+    )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// `prove` with IPA.
+pub fn prove_ipa<ConcreteCircuit: Circuit<Fp>>(
+    params: &ParamsIPA<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: ConcreteCircuit,
+    instances: &[&[Fp]],
+) -> Result<Vec<u8>, Error> {
+    prove::<IPACommitmentScheme<G1Affine>, ProverIPA<'_, G1Affine>, _>(
+        params, pk, circuit, instances,
+    )
+}
+
+/// `verify` with IPA.
+pub fn verify_ipa(
+    params: &ParamsIPA<G1Affine>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    instances: &[&[Fp]],
+) -> Result<(), Error> {
+    verify::<
+        IPACommitmentScheme<G1Affine>,
+        VerifierIPA<'_, G1Affine>,
+        IpaSingleStrategy<'_, G1Affine>,
+    >(params.verifier_params(), vk, proof, instances)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{
+        halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner, Value},
+            plonk::{Advice, Column, ConstraintSystem, Instance},
+        },
+        EncryptCursor, FixedAes128Config,
+    };
+
+    const K: u32 = 17;
+
+    // FIPS-197 Appendix C.1
+    const KEY: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const PLAINTEXT: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+    const CIPHERTEXT: [u8; 16] = [
+        0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5,
+        0x5a,
+    ];
+
+    #[derive(Clone)]
+    struct TestCircuit;
+
+    impl Circuit<Fp> for TestCircuit {
+        type Config = FixedAes128Config<K, 1>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            FixedAes128Config::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            config.load_tables(&mut layouter)?;
+            let aes = config.schedule_key(&mut layouter, KEY)?;
+            let mut cursor = EncryptCursor::default();
+            let ciphertext = aes.encrypt(&mut cursor, &mut layouter, PLAINTEXT)?;
+            aes.expose_ciphertext(&mut cursor, &mut layouter, &ciphertext)?;
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            Self
+        }
+    }
+
+    fn instance(ciphertext: [u8; 16]) -> Vec<Fp> {
+        ciphertext.iter().map(|&b| Fp::from(b as u64)).collect()
+    }
+
+    fn params_bytes(params: &ParamsKZG<Bn256>) -> Vec<u8> {
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_load_or_generate_params() {
+        // Small k, the setup is fast
+        let k = 4;
+        let generated = load_or_generate_params(k, None::<&[u8]>).unwrap();
+        assert_eq!(generated.k(), k);
+
+        let bytes = params_bytes(&generated);
+        let loaded = load_or_generate_params(k, Some(&bytes[..])).unwrap();
+        assert_eq!(params_bytes(&loaded), bytes);
+
+        // Params for a larger k are downsized
+        let downsized = load_or_generate_params(k - 1, Some(&bytes[..])).unwrap();
+        assert_eq!(downsized.k(), k - 1);
+
+        let err = load_or_generate_params(k + 1, Some(&bytes[..])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        assert!(load_or_generate_params(k, Some(&bytes[..8])).is_err());
+    }
+
+    // Exposes a witness, small enough to prove in a unit test
+    #[derive(Clone)]
+    struct TestSmallCircuit(u64);
+
+    impl Circuit<Fp> for TestSmallCircuit {
+        type Config = (Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(advice);
+            meta.enable_equality(instance);
+            (advice, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (advice, instance): Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let cell = layouter.assign_region(
+                || "assign value",
+                |mut region| {
+                    region.assign_advice(|| "value", advice, 0, || Value::known(Fp::from(self.0)))
+                },
+            )?;
+            layouter.constrain_instance(cell.cell(), instance, 0)
+        }
+
+        fn without_witnesses(&self) -> Self {
+            Self(0)
+        }
+    }
+
+    #[test]
+    fn test_prove_kzg_with_seeded_rng() {
+        let k = 4;
+        let params = ParamsKZG::<Bn256>::setup(k, StdRng::seed_from_u64(0));
+        let circuit = TestSmallCircuit(42);
+        let (pk, vk) = keygen(&params, &circuit).unwrap();
+        let instances: &[&[Fp]] = &[&[Fp::from(42)]];
+
+        let prove_seeded = |seed| {
+            prove_kzg_with_rng(
+                &params,
+                &pk,
+                circuit.clone(),
+                instances,
+                StdRng::seed_from_u64(seed),
+            )
+            .unwrap()
+        };
+
+        // Same seed, same proof
+        let proof = prove_seeded(1);
+        assert_eq!(prove_seeded(1), proof);
+        assert!(verify_kzg(&params, &vk, &proof, instances).is_ok());
+
+        // The blinding factors come from the RNG
+        assert_ne!(prove_seeded(2), proof);
+    }
+
+    // Generating real proofs is slow. Run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_prove_kzg() {
+        let params = kzg_params(K);
+        let (pk, vk) = keygen(&params, &TestCircuit).unwrap();
+        let proof = prove_kzg(&params, &pk, TestCircuit, &[&instance(CIPHERTEXT)]).unwrap();
+
+        assert!(verify_kzg(&params, &vk, &proof, &[&instance(CIPHERTEXT)]).is_ok());
+        assert!(verify_kzg(&params, &vk, &proof, &[&instance([0u8; 16])]).is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_prove_ipa() {
+        let params = ipa_params(K);
+        let (pk, vk) = keygen(&params, &TestCircuit).unwrap();
+        let proof = prove_ipa(&params, &pk, TestCircuit, &[&instance(CIPHERTEXT)]).unwrap();
+
+        assert!(verify_ipa(&params, &vk, &proof, &[&instance(CIPHERTEXT)]).is_ok());
+        assert!(verify_ipa(&params, &vk, &proof, &[&instance([0u8; 16])]).is_err());
+    }
+}
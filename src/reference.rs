@@ -0,0 +1,363 @@
+//! AES-128 computed out of the circuit, following the exact steps of `FixedAes128Config`.
+//!
+//! The functions share `State` and `MIX_COLUMNS` with the circuit and
+//! combine the bytes in the same order, so that a transcription difference
+//! between the circuit and the reference can't go unnoticed the way it could
+//! when comparing against an independent implementation like the `aes` crate.
+
+use std::convert::Infallible;
+
+use crate::{
+    constant::{
+        INV_MIX_COLUMNS, KEY_WRAP_IV, MIX_COLUMNS, MUL_BY_11, MUL_BY_13, MUL_BY_14, MUL_BY_2,
+        MUL_BY_3, MUL_BY_9, ROUND_CONSTANT, S_BOX,
+    },
+    state::State,
+};
+
+/// Expand the key into 11 round keys, as `Aes128KeyScheduleConfig::schedule_keys`.
+pub fn key_schedule_reference(key: [u8; 16]) -> [[u8; 16]; 11] {
+    let mut words = key.chunks(4).map(|word| word.to_vec()).collect::<Vec<_>>();
+
+    for i in 4..44 {
+        let prev_word = &words[i - 1];
+        let temp = if i % 4 == 0 {
+            // RotWord, SubWord and XOR with the round constant word (rc, 0, 0, 0)
+            [1usize, 2, 3, 0]
+                .iter()
+                .map(|&v| S_BOX[prev_word[v] as usize])
+                .zip([ROUND_CONSTANT[i / 4 - 1], 0, 0, 0])
+                .map(|(s, r)| s ^ r)
+                .collect::<Vec<_>>()
+        } else {
+            prev_word.clone()
+        };
+
+        let word = words[i - 4].iter().zip(temp).map(|(f, t)| f ^ t).collect();
+        words.push(word);
+    }
+
+    std::array::from_fn(|round| words[round * 4..round * 4 + 4].concat().try_into().unwrap())
+}
+
+/// Round keys of the equivalent inverse cipher (FIPS-197 5.3.5) in the order of decryption:
+/// the last round key, InvMixColumns of the round keys 9 down to 1, and the key.
+/// Reference only: `decrypt` runs the straightforward inverse cipher under the round keys
+/// of the encryption, so the circuit has no inverse key schedule to compare it with.
+pub fn dec_key_schedule_reference(round_keys: &[[u8; 16]; 11]) -> [[u8; 16]; 11] {
+    std::array::from_fn(|round| match round {
+        0 | 10 => round_keys[10 - round],
+        _ => inv_mix_columns(&round_keys[10 - round]),
+    })
+}
+
+fn inv_mix_columns(block: &[u8; 16]) -> [u8; 16] {
+    State::from_bytes_column_major(block)
+        .try_map_columns(|word| {
+            Ok::<_, Infallible>(INV_MIX_COLUMNS.iter().map(|col| lcon(word, col)).collect())
+        })
+        .unwrap()
+        .to_flat()
+        .try_into()
+        .unwrap()
+}
+
+/// Outputs of all the chips of one `encrypt` in the order they are assigned,
+/// to assign them as they are with `Aes128Scheduled::encrypt_with_trace`:
+/// the initial AddRoundKey, and for each round SubBytes, the products and XORs of
+/// MixColumns by output byte, and AddRoundKey.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessTrace(pub(crate) Vec<u8>);
+
+impl WitnessTrace {
+    /// Number of outputs of one encryption.
+    pub const LEN: usize = 16 + 9 * (16 + 16 * (2 + 3) + 16) + (16 + 16);
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Witness trace of encrypting the block under the round keys, see `WitnessTrace`.
+pub fn witness_trace_reference(round_keys: &[[u8; 16]; 11], plaintext: [u8; 16]) -> WitnessTrace {
+    let mut trace = vec![];
+
+    let mut state: [u8; 16] = std::array::from_fn(|i| plaintext[i] ^ round_keys[0][i]);
+    trace.extend(state);
+    for (round, round_key) in round_keys.iter().enumerate().skip(1) {
+        let subbed = sub_bytes(&state);
+        trace.extend(subbed.to_flat());
+
+        let shifted = subbed.shift_rows();
+        let mixed = if round < 10 {
+            shifted
+                .try_map_columns(|word| {
+                    Ok::<_, Infallible>(
+                        MIX_COLUMNS
+                            .iter()
+                            .map(|col| lcon_traced(word, col, &mut trace))
+                            .collect(),
+                    )
+                })
+                .unwrap()
+        } else {
+            shifted
+        };
+
+        state = add_round_key(&mixed, round_key);
+        trace.extend(state);
+    }
+
+    WitnessTrace(trace)
+}
+
+/// Encrypt the block under the round keys.
+pub fn encrypt_block_reference(round_keys: &[[u8; 16]; 11], plaintext: [u8; 16]) -> [u8; 16] {
+    encrypt_trace_reference(round_keys, plaintext)[10]
+}
+
+/// AES Key Wrap of RFC 3394 of the key data under the round keys of the KEK,
+/// as `Aes128Scheduled::key_wrap`.
+/// The key data should be at least 2 blocks of 8 bytes.
+pub fn key_wrap_reference(round_keys: &[[u8; 16]; 11], key_data: &[u8]) -> Vec<u8> {
+    assert!(
+        key_data.len() % 8 == 0 && key_data.len() >= 16,
+        "key data should be at least 2 blocks of 8 bytes"
+    );
+
+    let n = key_data.len() / 8;
+    let mut a = KEY_WRAP_IV;
+    let mut r = key_data.chunks(8).map(|c| c.to_vec()).collect::<Vec<_>>();
+    for j in 0..6 {
+        for (i, r_i) in r.iter_mut().enumerate() {
+            let block = std::array::from_fn(|k| if k < 8 { a[k] } else { r_i[k - 8] });
+            let b = encrypt_block_reference(round_keys, block);
+            let t = ((n * j + i + 1) as u64).to_be_bytes();
+            a = std::array::from_fn(|k| b[k] ^ t[k]);
+            *r_i = b[8..].to_vec();
+        }
+    }
+
+    a.into_iter().chain(r.into_iter().flatten()).collect()
+}
+
+/// States after the initial AddRoundKey and after each of the 10 rounds,
+/// i.e. the i-th state is the input of the round i + 1 and the last one is the ciphertext.
+pub fn encrypt_trace_reference(round_keys: &[[u8; 16]; 11], plaintext: [u8; 16]) -> [[u8; 16]; 11] {
+    let mut trace = [[0u8; 16]; 11];
+
+    trace[0] = std::array::from_fn(|i| plaintext[i] ^ round_keys[0][i]);
+    for round in 1..10 {
+        trace[round] = middle_round(&trace[round - 1], &round_keys[round]);
+    }
+    trace[10] = last_round(&trace[9], &round_keys[10]);
+
+    trace
+}
+
+fn sub_bytes(state: &[u8; 16]) -> State<u8> {
+    State::from_bytes_column_major(state)
+        .try_map(|&byte| Ok::<_, Infallible>(S_BOX[byte as usize]))
+        .unwrap()
+}
+
+fn middle_round(state: &[u8; 16], round_key: &[u8; 16]) -> [u8; 16] {
+    let mixed = sub_bytes(state)
+        .shift_rows()
+        .try_map_columns(|word| {
+            Ok::<_, Infallible>(MIX_COLUMNS.iter().map(|col| lcon(word, col)).collect())
+        })
+        .unwrap();
+
+    add_round_key(&mixed, round_key)
+}
+
+fn last_round(state: &[u8; 16], round_key: &[u8; 16]) -> [u8; 16] {
+    add_round_key(&sub_bytes(state).shift_rows(), round_key)
+}
+
+fn add_round_key(state: &State<u8>, round_key: &[u8; 16]) -> [u8; 16] {
+    state
+        .try_zip_map(&State::from_bytes_column_major(round_key), |x, k| {
+            Ok::<_, Infallible>(x ^ k)
+        })
+        .unwrap()
+        .to_flat()
+        .try_into()
+        .unwrap()
+}
+
+// Linear combination of the word and the coefficients, as `FixedAes128Config::lcon`.
+// The coefficients 9, 11, 13 and 14 are those of InvMixColumns.
+fn lcon(word: &[u8], coeffs: &[u32]) -> u8 {
+    let tmp = word
+        .iter()
+        .zip(coeffs)
+        .map(|(&byte, &col)| mul_by(byte, col))
+        .collect::<Vec<_>>();
+
+    (tmp[0] ^ tmp[1]) ^ (tmp[2] ^ tmp[3])
+}
+
+// `lcon` recording the products and the XORs in the order the circuit assigns them.
+// The products by 1 are copies, not outputs.
+fn lcon_traced(word: &[u8], coeffs: &[u32], trace: &mut Vec<u8>) -> u8 {
+    let tmp = word
+        .iter()
+        .zip(coeffs)
+        .map(|(&byte, &col)| {
+            let product = mul_by(byte, col);
+            if col != 1 {
+                trace.push(product);
+            }
+            product
+        })
+        .collect::<Vec<_>>();
+
+    let (left, right) = (tmp[0] ^ tmp[1], tmp[2] ^ tmp[3]);
+    trace.extend([left, right, left ^ right]);
+    left ^ right
+}
+
+fn mul_by(byte: u8, col: u32) -> u8 {
+    match col {
+        1 => byte,
+        2 => MUL_BY_2[byte as usize],
+        3 => MUL_BY_3[byte as usize],
+        9 => MUL_BY_9[byte as usize],
+        11 => MUL_BY_11[byte as usize],
+        13 => MUL_BY_13[byte as usize],
+        14 => MUL_BY_14[byte as usize],
+        _ => panic!("col should be 1, 2, 3, 9, 11, 13 or 14."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_to_block(hex: &str) -> [u8; 16] {
+        std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap())
+    }
+
+    // FIPS-197 Appendix C.1
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f";
+    const PLAINTEXT: &str = "00112233445566778899aabbccddeeff";
+    const ROUND_STARTS: [&str; 10] = [
+        "00102030405060708090a0b0c0d0e0f0",
+        "89d810e8855ace682d1843d8cb128fe4",
+        "4915598f55e5d7a0daca94fa1f0a63f7",
+        "fa636a2825b339c940668a3157244d17",
+        "247240236966b3fa6ed2753288425b6c",
+        "c81677bc9b7ac93b25027992b0261996",
+        "c62fe109f75eedc3cc79395d84f9cf5d",
+        "d1876c0f79c4300ab45594add66ff41f",
+        "fde3bad205e5d0d73547964ef1fe37f1",
+        "bd6e7c3df2b5779e0b61216e8b10b689",
+    ];
+    const CIPHERTEXT: &str = "69c4e0d86a7b0430d8cdb78070b4c55a";
+
+    #[test]
+    fn test_key_schedule_reference() {
+        let round_keys = key_schedule_reference(hex_to_block(KEY));
+
+        assert_eq!(round_keys[0], hex_to_block(KEY));
+        assert_eq!(
+            round_keys[1],
+            hex_to_block("d6aa74fdd2af72fadaa678f1d6ab76fe")
+        );
+        assert_eq!(
+            round_keys[10],
+            hex_to_block("13111d7fe3944a17f307a78b4d2b30c5")
+        );
+    }
+
+    // FIPS-197 Appendix C.1, round[r].ik_sch of the equivalent inverse cipher
+    const DEC_ROUND_KEYS: [&str; 11] = [
+        "13111d7fe3944a17f307a78b4d2b30c5",
+        "13aa29be9c8faff6f770f58000f7bf03",
+        "1362a4638f2586486bff5a76f7874a83",
+        "8d82fc749c47222be4dadc3e9c7810f5",
+        "72e3098d11c5de5f789dfe1578a2cccb",
+        "2ec410276326d7d26958204a003f32de",
+        "a8a2f5044de2c7f50a7ef79869671294",
+        "c7c6e391e54032f1479c306d6319e50c",
+        "a0db02992286d160a2dc029c2485d561",
+        "8c56dff0825dd3f9805ad3fc8659d7fd",
+        "000102030405060708090a0b0c0d0e0f",
+    ];
+
+    // InvMixColumns applied to the round keys is easy to get wrong, e.g. by the order
+    // of the rows of the matrix or by applying it to the first or last round key.
+    #[test]
+    fn test_dec_key_schedule_reference() {
+        let round_keys = key_schedule_reference(hex_to_block(KEY));
+        let dec_round_keys = dec_key_schedule_reference(&round_keys);
+
+        for (round_key, expected) in dec_round_keys.iter().zip(DEC_ROUND_KEYS) {
+            assert_eq!(*round_key, hex_to_block(expected));
+        }
+
+        // InvMixColumns undoes MixColumns
+        let block = hex_to_block(ROUND_STARTS[1]);
+        let mixed: [u8; 16] = State::from_bytes_column_major(&block)
+            .try_map_columns(|word| {
+                Ok::<_, Infallible>(MIX_COLUMNS.iter().map(|col| lcon(word, col)).collect())
+            })
+            .unwrap()
+            .to_flat()
+            .try_into()
+            .unwrap();
+        assert_eq!(inv_mix_columns(&mixed), block);
+    }
+
+    #[test]
+    fn test_witness_trace_reference() {
+        let round_keys = key_schedule_reference(hex_to_block(KEY));
+        let trace = witness_trace_reference(&round_keys, hex_to_block(PLAINTEXT));
+
+        assert_eq!(trace.bytes().len(), WitnessTrace::LEN);
+        // Starts with the state after the initial AddRoundKey, ends with the ciphertext
+        assert_eq!(trace.bytes()[..16], hex_to_block(ROUND_STARTS[0]));
+        assert_eq!(
+            trace.bytes()[WitnessTrace::LEN - 16..],
+            hex_to_block(CIPHERTEXT)
+        );
+        // Each middle round ends with the start of the next round
+        let round_len = 16 + 16 * 5 + 16;
+        for (round, expected) in ROUND_STARTS.iter().enumerate().skip(1) {
+            let end = 16 + round * round_len;
+            assert_eq!(trace.bytes()[end - 16..end], hex_to_block(expected));
+        }
+    }
+
+    #[test]
+    fn test_encrypt_trace_reference() {
+        let round_keys = key_schedule_reference(hex_to_block(KEY));
+        let trace = encrypt_trace_reference(&round_keys, hex_to_block(PLAINTEXT));
+
+        for (state, expected) in trace.iter().zip(ROUND_STARTS) {
+            assert_eq!(*state, hex_to_block(expected));
+        }
+        assert_eq!(trace[10], hex_to_block(CIPHERTEXT));
+        assert_eq!(
+            encrypt_block_reference(&round_keys, hex_to_block(PLAINTEXT)),
+            hex_to_block(CIPHERTEXT)
+        );
+    }
+
+    // RFC 3394 Section 4.1, 128 bits of key data with a 128-bit KEK
+    const KEY_WRAP_KEK: &str = "000102030405060708090a0b0c0d0e0f";
+    const KEY_WRAP_KEY_DATA: &str = "00112233445566778899aabbccddeeff";
+    const KEY_WRAP_CIPHERTEXT: [u8; 24] = [
+        0x1f, 0xa6, 0x8b, 0x0a, 0x81, 0x12, 0xb4, 0x47, 0xae, 0xf3, 0x4b, 0xd8, 0xfb, 0x5a, 0x7b,
+        0x82, 0x9d, 0x3e, 0x86, 0x23, 0x71, 0xd2, 0xcf, 0xe5,
+    ];
+
+    #[test]
+    fn test_key_wrap_reference() {
+        let round_keys = key_schedule_reference(hex_to_block(KEY_WRAP_KEK));
+        let wrapped = key_wrap_reference(&round_keys, &hex_to_block(KEY_WRAP_KEY_DATA));
+        assert_eq!(wrapped, KEY_WRAP_CIPHERTEXT);
+    }
+}
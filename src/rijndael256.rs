@@ -0,0 +1,350 @@
+//! Rijndael with 256 bit block and 256 bit key.
+//!
+//! NOTE: This is the non-standard Rijndael, NOT the AES of FIPS-197 which fixes the block to 128 bits.
+//! The state has 8 words, the rows are shifted by 0, 1, 3 and 4 bytes and the cipher runs 14 rounds.
+//! It reuses the chips of `FixedAes128Config` in a single column group.
+
+use std::ops::Deref;
+
+use crate::{
+    aes128::{EncryptCursor, FixedAes128Config},
+    chips::{sbox_chip::SboxChip, u8_range_check_chip::U8RangeCheckChip, u8_xor_chip::U8XorChip},
+    constant::{
+        INITIAL_ROUND_ROWS, KEY_SCHEDULE_ROWS as AES_KEY_SCHEDULE_ROWS, LAST_ROUND_ROWS,
+        MIX_COLUMNS, ROUND_ROWS,
+    },
+    error::AesError,
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::{ConstraintSystem, Error},
+    },
+    table::TableColumns,
+};
+
+/// Number of words in the block
+const NB: usize = 8;
+/// Number of words in the key
+const NK: usize = 8;
+/// Number of rounds, max(NB, NK) + 6
+const ROUNDS: usize = 14;
+/// Offsets of shift rows for each row of the state
+const SHIFT_OFFSETS: [usize; 4] = [0, 1, 3, 4];
+
+// Rows of the key schedule: the key (32), 14 words with RotWord, SubWord and the round
+// constant (4 + 4 + 4 + 4), 14 words with SubWord (4), the XOR (4) of each of the
+// 112 derived words and the range check of their 448 bytes.
+const KEY_SCHEDULE_ROWS: u64 = 32 + 14 * 16 + 14 * 4 + 112 * 4 + 448;
+// Rows of a block, twice the rows of an AES-128 round for the state of 32 bytes:
+// the plaintext and the initial AddRoundKey, 13 rounds with MixColumns and the last one
+const BLOCK_ROWS: u64 =
+    2 * (INITIAL_ROUND_ROWS + (ROUNDS as u64 - 1) * ROUND_ROWS + LAST_ROUND_ROWS);
+
+/// Shift rows of the state given as 8 words.
+/// j-th byte of i-th word is taken from the word (i + SHIFT_OFFSETS[j]) % 8.
+pub(crate) fn shift_rows<T: Clone>(words: &[Vec<T>]) -> Vec<Vec<T>> {
+    (0..NB)
+        .map(|i| {
+            (0..4)
+                .map(|j| words[(i + SHIFT_OFFSETS[j]) % NB][j].clone())
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct Rijndael256Config<const K: u32> {
+    aes: FixedAes128Config<K, 1>,
+
+    pub tables: TableColumns,
+}
+
+impl<const K: u32> Rijndael256Config<K> {
+    pub fn configure(meta: &mut ConstraintSystem<Fp>) -> Self {
+        let aes = FixedAes128Config::configure(meta);
+        let tables = aes.tables;
+
+        Self { aes, tables }
+    }
+
+    /// Load the lookup tables, which must be done before encrypting.
+    /// Encryption fails with `AesError::TablesNotLoaded` otherwise.
+    pub fn load_tables(&mut self, layouter: &mut impl Layouter<Fp>) -> Result<(), Error> {
+        self.aes.load_tables(layouter)
+    }
+
+    /// Expand 256 bit key to 15 round keys of 32 bytes, returning the config which can
+    /// encrypt under the key. The rows of the key schedule beyond the ones of AES-128
+    /// are accounted in the cursor, so it must be called before encrypting.
+    pub fn schedule_key(
+        self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        key: [u8; 32],
+    ) -> Result<Rijndael256Scheduled<K>, Error> {
+        self.aes
+            .add_key_schedule(cursor, KEY_SCHEDULE_ROWS - AES_KEY_SCHEDULE_ROWS)?;
+
+        let words = self
+            .aes
+            .key_schedule_config
+            .expand_key(layouter, &key, NB * (ROUNDS + 1))?;
+        let keys = words.chunks(NB * 4).map(|round| round.to_vec()).collect();
+
+        Ok(Rijndael256Scheduled { config: self, keys })
+    }
+}
+
+/// `Rijndael256Config` with the scheduled key, returned by `schedule_key`.
+/// Encryption is only available on this type, so it can't be called before scheduling the key.
+#[derive(Clone, Debug)]
+pub struct Rijndael256Scheduled<const K: u32> {
+    config: Rijndael256Config<K>,
+    keys: Vec<Vec<AssignedCell<Fp, Fp>>>,
+}
+
+impl<const K: u32> Deref for Rijndael256Scheduled<K> {
+    type Target = Rijndael256Config<K>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.config
+    }
+}
+
+impl<const K: u32> Rijndael256Scheduled<K> {
+    /// Drop the scheduled round keys and return the config.
+    pub fn into_config(self) -> Rijndael256Config<K> {
+        self.config
+    }
+
+    /// Encrypt the 32 bytes plaintext block under the scheduled key.
+    /// Fails with `AesError::TablesNotLoaded` before `load_tables`,
+    /// and with `AesError::CapacityExceeded` if the block doesn't fit in the rows left.
+    pub fn encrypt(
+        &self,
+        cursor: &mut EncryptCursor,
+        layouter: &mut impl Layouter<Fp>,
+        plaintext: [u8; 32],
+    ) -> Result<Vec<AssignedCell<Fp, Fp>>, AesError> {
+        let aes = &self.config.aes;
+        aes.check_tables_loaded()?;
+        aes.add_extra_rows(cursor, BLOCK_ROWS)?;
+
+        let xor_chip = U8XorChip::construct(aes.xor_config(cursor));
+        let sbox_chip = SboxChip::construct(aes.sbox_config(cursor));
+        let range_chip = U8RangeCheckChip::construct(aes.range_config(cursor));
+
+        let assigned_plaintext = layouter.assign_region(
+            || "Assign plaintext",
+            |mut region| {
+                plaintext
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &p)| range_chip.assign_byte(&mut region, i, Value::known(p as u64)))
+                    .collect::<Result<Vec<_>, Error>>()
+            },
+        )?;
+
+        let mut prev_round = assigned_plaintext
+            .iter()
+            .zip(self.keys[0].iter())
+            .map(|(p, k)| xor_chip.xor(layouter, p, k))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for no_round in 1..=ROUNDS {
+            let subbed = prev_round
+                .iter()
+                .map(|byte| sbox_chip.substitute(layouter, byte))
+                .collect::<Result<Vec<_>, Error>>()?
+                .chunks(4)
+                .map(|word| word.to_vec())
+                .collect::<Vec<_>>();
+
+            let shifted = shift_rows(&subbed);
+
+            let mixed = if no_round == ROUNDS {
+                shifted
+            } else {
+                shifted
+                    .iter()
+                    .map(|word| {
+                        // Same MixColumns matrix as AES
+                        MIX_COLUMNS
+                            .iter()
+                            .map(|col| aes.lcon(cursor, layouter, word, col))
+                            .collect::<Result<Vec<_>, Error>>()
+                    })
+                    .collect::<Result<Vec<Vec<_>>, Error>>()?
+            };
+
+            prev_round = mixed
+                .concat()
+                .iter()
+                .zip(self.keys[no_round].iter())
+                .map(|(m, k)| xor_chip.xor(layouter, m, k))
+                .collect::<Result<Vec<_>, Error>>()?;
+        }
+
+        Ok(prev_round)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        constant::{MUL_BY_2, MUL_BY_3, ROUND_CONSTANT, S_BOX},
+        halo2_proofs::{
+            circuit::{Layouter, SimpleFloorPlanner},
+            dev::MockProver,
+            halo2curves::bn256::Fr as Fp,
+            plonk::{Circuit, ConstraintSystem, Error},
+        },
+        utils::cells_to_bytes,
+    };
+
+    // Software Rijndael with 256 bit key and nb words block
+    fn reference_encrypt(key: &[u8; 32], plaintext: &[u8], shift_offsets: [usize; 4]) -> Vec<u8> {
+        let nb = plaintext.len() / 4;
+
+        // Key expansion
+        let mut w = key.chunks(4).map(|word| word.to_vec()).collect::<Vec<_>>();
+        for i in NK..nb * (ROUNDS + 1) {
+            let mut temp = w[i - 1].clone();
+            if i % NK == 0 {
+                temp = vec![
+                    S_BOX[temp[1] as usize] ^ ROUND_CONSTANT[i / NK - 1],
+                    S_BOX[temp[2] as usize],
+                    S_BOX[temp[3] as usize],
+                    S_BOX[temp[0] as usize],
+                ];
+            } else if i % NK == 4 {
+                temp = temp.iter().map(|&b| S_BOX[b as usize]).collect();
+            }
+            let word = w[i - NK].iter().zip(temp).map(|(a, b)| a ^ b).collect();
+            w.push(word);
+        }
+        let round_keys = w.chunks(nb).map(|words| words.concat()).collect::<Vec<_>>();
+
+        let mut state = plaintext
+            .iter()
+            .zip(round_keys[0].iter())
+            .map(|(p, k)| p ^ k)
+            .collect::<Vec<_>>();
+        for round in 1..=ROUNDS {
+            let subbed = state.iter().map(|&b| S_BOX[b as usize]).collect::<Vec<_>>();
+
+            let mut shifted = vec![0u8; nb * 4];
+            for i in 0..nb {
+                for j in 0..4 {
+                    shifted[i * 4 + j] = subbed[((i + shift_offsets[j]) % nb) * 4 + j];
+                }
+            }
+
+            let mut mixed = shifted.clone();
+            if round != ROUNDS {
+                for i in 0..nb {
+                    let a = &shifted[i * 4..i * 4 + 4];
+                    for j in 0..4 {
+                        mixed[i * 4 + j] = MUL_BY_2[a[j] as usize]
+                            ^ MUL_BY_3[a[(j + 1) % 4] as usize]
+                            ^ a[(j + 2) % 4]
+                            ^ a[(j + 3) % 4];
+                    }
+                }
+            }
+
+            state = mixed
+                .iter()
+                .zip(round_keys[round].iter())
+                .map(|(m, k)| m ^ k)
+                .collect();
+        }
+
+        state
+    }
+
+    fn sequence<const L: usize>(start: u8, step: u8) -> [u8; L] {
+        std::array::from_fn(|i| start.wrapping_add(step.wrapping_mul(i as u8)))
+    }
+
+    #[test]
+    fn test_reference_matches_aes256() {
+        // FIPS-197 Appendix C.3. With 4 words block the reference is AES-256.
+        let key = sequence::<32>(0, 1);
+        let plaintext = sequence::<16>(0, 0x11);
+        let expected = [
+            0x8e, 0xa2, 0xb7, 0xca, 0x51, 0x67, 0x45, 0xbf, 0xea, 0xfc, 0x49, 0x90, 0x4b, 0x49,
+            0x60, 0x89,
+        ];
+
+        assert_eq!(reference_encrypt(&key, &plaintext, [0, 1, 2, 3]), expected);
+    }
+
+    #[test]
+    fn test_shift_rows() {
+        let state = (0..32u8)
+            .collect::<Vec<_>>()
+            .chunks(4)
+            .map(|word| word.to_vec())
+            .collect::<Vec<_>>();
+
+        // rows 1, 2, 3 are rotated left by 1, 3, 4 bytes
+        let shifted = shift_rows(&state).concat();
+        assert_eq!(
+            shifted,
+            vec![
+                0, 5, 14, 19, 4, 9, 18, 23, 8, 13, 22, 27, 12, 17, 26, 31, 16, 21, 30, 3, 20, 25,
+                2, 7, 24, 29, 6, 11, 28, 1, 10, 15
+            ]
+        );
+    }
+
+    #[derive(Clone)]
+    struct TestRijndael256Circuit {
+        key: [u8; 32],
+        plaintext: [u8; 32],
+    }
+
+    impl Circuit<Fp> for TestRijndael256Circuit {
+        type Config = Rijndael256Config<17>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            Rijndael256Config::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            config.load_tables(&mut layouter)?;
+            let mut cursor = EncryptCursor::default();
+            let rijndael = config.schedule_key(&mut cursor, &mut layouter, self.key)?;
+            let ciphertext = rijndael.encrypt(&mut cursor, &mut layouter, self.plaintext)?;
+
+            let expected = reference_encrypt(&self.key, &self.plaintext, SHIFT_OFFSETS);
+            cells_to_bytes(&ciphertext).assert_if_known(|bytes| *bytes == expected);
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_rijndael256_encryption() {
+        let circuit = TestRijndael256Circuit {
+            key: sequence(0, 1),
+            plaintext: sequence(0, 0x11),
+        };
+
+        let mock = MockProver::run(17, &circuit, vec![vec![]]).unwrap();
+        mock.assert_satisfied();
+    }
+}
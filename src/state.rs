@@ -0,0 +1,136 @@
+//! The 4x4 state of AES, FIPS-197 Section 3.4.
+//!
+//! The bytes of a block fill the state column by column, i.e. the byte r + 4c
+//! of the block is in the row r of the column c. A column is a word of the state.
+//! The transformations keep this column-major order, which is also the order
+//! the bytes are laid out in the circuit.
+
+/// The state given as its 4 columns, generic over the byte so that the circuit
+/// (`AssignedCell`) and the reference (`u8`) share the transformations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State<T>([[T; 4]; 4]);
+
+impl<T> State<T> {
+    // State from the 16 bytes of the block in order
+    fn from_vec(bytes: Vec<T>) -> Self {
+        assert_eq!(bytes.len(), 16, "State should be 16 bytes");
+        let mut bytes = bytes.into_iter();
+        Self(std::array::from_fn(|_| {
+            std::array::from_fn(|_| bytes.next().unwrap())
+        }))
+    }
+
+    /// The 4 columns of the state.
+    pub fn columns(&self) -> &[[T; 4]; 4] {
+        &self.0
+    }
+
+    /// Apply `f` to every byte in the column-major order.
+    pub fn try_map<U, E>(&self, mut f: impl FnMut(&T) -> Result<U, E>) -> Result<State<U>, E> {
+        let bytes = self
+            .0
+            .iter()
+            .flatten()
+            .map(&mut f)
+            .collect::<Result<_, E>>()?;
+        Ok(State::from_vec(bytes))
+    }
+
+    /// Replace every column by the 4 bytes returned by `f`, e.g. MixColumns.
+    pub fn try_map_columns<U, E>(
+        &self,
+        f: impl FnMut(&[T; 4]) -> Result<Vec<U>, E>,
+    ) -> Result<State<U>, E> {
+        let columns = self.0.iter().map(f).collect::<Result<Vec<_>, E>>()?;
+        assert!(
+            columns.iter().all(|column| column.len() == 4),
+            "Column should be 4 bytes"
+        );
+        Ok(State::from_vec(columns.into_iter().flatten().collect()))
+    }
+
+    /// Combine the bytes at the same position of the two states, e.g. AddRoundKey.
+    pub fn try_zip_map<U, V, E>(
+        &self,
+        other: &State<U>,
+        mut f: impl FnMut(&T, &U) -> Result<V, E>,
+    ) -> Result<State<V>, E> {
+        let bytes = self
+            .0
+            .iter()
+            .flatten()
+            .zip(other.0.iter().flatten())
+            .map(|(x, y)| f(x, y))
+            .collect::<Result<_, E>>()?;
+        Ok(State::from_vec(bytes))
+    }
+}
+
+impl<T: Clone> State<T> {
+    /// State of the 16 bytes of the block, filling the columns in order.
+    pub fn from_bytes_column_major(bytes: &[T]) -> Self {
+        Self::from_vec(bytes.to_vec())
+    }
+
+    /// The 16 bytes of the block in order.
+    pub fn to_flat(&self) -> Vec<T> {
+        self.0.iter().flatten().cloned().collect()
+    }
+
+    /// ShiftRows, FIPS-197 Section 5.1.2: the row r is rotated left by r bytes,
+    /// i.e. the row r of the column c is taken from the column (c + r) % 4.
+    pub fn shift_rows(&self) -> Self {
+        Self(std::array::from_fn(|c| {
+            std::array::from_fn(|r| self.0[(c + r) % 4][r].clone())
+        }))
+    }
+
+    /// InvShiftRows, FIPS-197 Section 5.3.1: the row r is rotated right by r bytes,
+    /// undoing `shift_rows`.
+    pub fn inv_shift_rows(&self) -> Self {
+        Self(std::array::from_fn(|c| {
+            std::array::from_fn(|r| self.0[(c + 4 - r) % 4][r].clone())
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shift_rows() {
+        let state = State::from_bytes_column_major(&(0..16u8).collect::<Vec<_>>());
+
+        // FIPS-197 5.1.2: row r is rotated left by r bytes
+        assert_eq!(
+            state.shift_rows().to_flat(),
+            vec![0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12, 1, 6, 11]
+        );
+        assert_eq!(
+            state.inv_shift_rows().to_flat(),
+            vec![0, 13, 10, 7, 4, 1, 14, 11, 8, 5, 2, 15, 12, 9, 6, 3]
+        );
+        assert_eq!(state.shift_rows().inv_shift_rows(), state);
+    }
+
+    #[test]
+    fn test_column_major() {
+        let bytes = (0..16u8).collect::<Vec<_>>();
+        let state = State::from_bytes_column_major(&bytes);
+
+        assert_eq!(state.columns()[1], [4, 5, 6, 7]);
+        assert_eq!(state.to_flat(), bytes);
+
+        let doubled = state
+            .try_map_columns(|column| Ok::<_, ()>(column.iter().map(|b| b * 2).collect()))
+            .unwrap();
+        let sum = state
+            .try_zip_map(&doubled, |x, y| Ok::<_, ()>(x + y))
+            .unwrap();
+        assert_eq!(
+            sum.to_flat(),
+            bytes.iter().map(|b| b * 3).collect::<Vec<_>>()
+        );
+    }
+}
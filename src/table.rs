@@ -1,192 +1,1087 @@
 use crate::{
-    constant::{MUL_BY_2, MUL_BY_3, S_BOX},
+    constant::{
+        GF_LOG_ZERO, INV_S_BOX, MUL_BY_11, MUL_BY_13, MUL_BY_14, MUL_BY_2, MUL_BY_3, MUL_BY_9,
+        S_BOX,
+    },
+    error::AesError,
     halo2_proofs::{
-        circuit::{Layouter, Value},
+        circuit::{Layouter, Table, Value},
         halo2curves::bn256::Fr as Fp,
-        plonk::{Error, TableColumn},
+        plonk::{ConstraintSystem, Error, TableColumn},
     },
+    utils::{gf_exp, gf_log},
 };
 
+// Tags of the sub-tables. Must be non-zero, see `zero_row`.
+// All of them are shifted by the tag offset of the table, see `TableColumns::tag_offset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum Tag {
     U8 = 1,
     Xor = 2,
     Sbox = 3,
     GfMul2 = 4,
     GfMul3 = 5,
+    GfLog = 6,
+    GfExp = 7,
+    InvSbox = 8,
+    GfMul9 = 9,
+    GfMul11 = 10,
+    GfMul13 = 11,
+    GfMul14 = 12,
+}
+
+/// Number of rows assigned by `load_enc_full_table`:
+/// u8, sbox, xor, mul2, mul3, gf log, gf exp, the inverse cipher maps and the zero row.
+/// The circuit needs these rows plus the rows reserved for the blinding factors.
+pub const fn full_table_rows() -> usize {
+    256 + 256 + 65536 + 256 + 256 + 256 + GF_EXP_ROWS + INV_ROWS + 1
+}
+
+/// Number of rows assigned by `load_dec_full_table`:
+/// u8, xor, inverse sbox, mul9, mul11, mul13, mul14 and the zero row.
+pub const fn dec_full_table_rows() -> usize {
+    256 + 65536 + INV_ROWS + 1
+}
+
+impl Tag {
+    // Value of the tag in the table shifted by the tag offset
+    pub(crate) fn value(self, tag_offset: u64) -> u64 {
+        self as u64 + tag_offset
+    }
+}
+
+// Sums of two logs are at most 2 * GF_LOG_ZERO
+const GF_EXP_ROWS: usize = 2 * GF_LOG_ZERO as usize + 1;
+
+// Inverse sbox, mul9, mul11, mul13 and mul14, see `inv_rows`
+const INV_ROWS: usize = 5 * 256;
+
+// Rows reserved by halo2 at the end of the circuit for the blinding factors
+pub(crate) const BLINDING_ROWS: usize = 10;
+
+// Smallest k such that the table loaded by `load_enc_full_table` fits in 2^k rows.
+pub(crate) const fn min_table_k() -> u32 {
+    min_k_for_rows(full_table_rows())
+}
+
+// Smallest k such that a table of `rows` rows fits in 2^k rows besides the blinding rows.
+const fn min_k_for_rows(rows: usize) -> u32 {
+    let mut needed = 0;
+    while (1usize << needed) < rows + BLINDING_ROWS {
+        needed += 1;
+    }
+    needed
+}
+
+// Check if a table of `rows` rows fits in the circuit of 2^k rows.
+fn check_table_rows(rows: usize, k: u32) -> Result<(), AesError> {
+    let needed = min_k_for_rows(rows);
+
+    if k < needed {
+        Err(AesError::TableTooLargeForK { needed, k })
+    } else {
+        Ok(())
+    }
+}
+
+/// Name the table columns of `load_enc_full_table`,
+/// shown in the MockProver errors and the dev-graph layout.
+#[cfg(feature = "halo2-pse")]
+pub fn annotate_table_columns(meta: &mut ConstraintSystem<Fp>, tables: [TableColumn; 4]) {
+    for (table, name) in tables
+        .into_iter()
+        .zip(["table_tag", "table_x", "table_y", "table_z"])
+    {
+        meta.annotate_lookup_column(table, || name);
+    }
+}
+
+/// Layout of the lookup tables of `FixedAes128Config`, chosen at configure time.
+///
+/// Both layouts need the same K, since the XOR table alone takes 2^16 rows,
+/// and have the same number of lookup arguments.
+/// Combined adds only 4 fixed columns, while Separate adds 18 of them,
+/// each committed in the verifying key and opened in every proof.
+/// So the combined layout is expected to be cheaper at any block count,
+/// compare them with `cargo bench --bench aes128`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TableLayout {
+    /// All the tables in 4 shared columns, distinguished by the tag column.
+    /// Loaded by `load_enc_full_table`.
+    #[default]
+    Combined,
+    /// Each operation looks up its own table columns.
+    /// Loaded by `load_u8_table`, `load_xor_table`, `load_sbox_table`,
+    /// `load_mul2_table`, `load_mul3_table` and `load_inv_table`.
+    Separate,
+}
+
+/// Table columns looked up by each operation of `FixedAes128Config`.
+/// The first column of each is the tag column.
+/// In the combined layout, all of them are the prefixes of the same 4 columns.
+#[derive(Clone, Copy, Debug)]
+pub struct TableColumns {
+    pub layout: TableLayout,
+    /// Added to all the tags, e.g. to load the combined table into the columns of
+    /// another tagged table without the tags colliding. 0 by default.
+    pub tag_offset: u64,
+    pub u8: [TableColumn; 2],
+    pub xor: [TableColumn; 4],
+    pub sbox: [TableColumn; 3],
+    pub mul2: [TableColumn; 3],
+    pub mul3: [TableColumn; 3],
+    /// Inverse sbox and mul by 9, 11, 13 and 14 of the inverse cipher.
+    pub inv: [TableColumn; 3],
+}
+
+impl TableColumns {
+    pub fn configure(meta: &mut ConstraintSystem<Fp>, layout: TableLayout) -> Self {
+        Self::configure_with_tag_offset(meta, layout, 0)
+    }
+
+    /// Same as `configure` with all the tags shifted by `tag_offset`.
+    /// Only the combined layout can be shifted: the separate tables have their own columns,
+    /// so there is nothing for their tags to collide with.
+    pub fn configure_with_tag_offset(
+        meta: &mut ConstraintSystem<Fp>,
+        layout: TableLayout,
+        tag_offset: u64,
+    ) -> Self {
+        assert!(
+            tag_offset == 0 || layout == TableLayout::Combined,
+            "Only the combined table can shift its tags"
+        );
+
+        match layout {
+            TableLayout::Combined => {
+                let tables = std::array::from_fn(|_| meta.lookup_table_column());
+                Self {
+                    layout,
+                    tag_offset,
+                    u8: [tables[0], tables[1]],
+                    xor: tables,
+                    sbox: [tables[0], tables[1], tables[2]],
+                    mul2: [tables[0], tables[1], tables[2]],
+                    mul3: [tables[0], tables[1], tables[2]],
+                    inv: [tables[0], tables[1], tables[2]],
+                }
+            }
+            TableLayout::Separate => Self {
+                layout,
+                tag_offset,
+                u8: std::array::from_fn(|_| meta.lookup_table_column()),
+                xor: std::array::from_fn(|_| meta.lookup_table_column()),
+                sbox: std::array::from_fn(|_| meta.lookup_table_column()),
+                mul2: std::array::from_fn(|_| meta.lookup_table_column()),
+                mul3: std::array::from_fn(|_| meta.lookup_table_column()),
+                inv: std::array::from_fn(|_| meta.lookup_table_column()),
+            },
+        }
+    }
+
+    /// Load the tables of the layout in the circuit of 2^k rows.
+    /// Both layouts fail with `AesError::TableTooLargeForK` if k is too small.
+    pub fn load(&self, layouter: &mut impl Layouter<Fp>, k: u32) -> Result<(), AesError> {
+        match self.layout {
+            TableLayout::Combined => {
+                load_enc_full_table_with_tag_offset(layouter, self.xor, k, self.tag_offset)
+            }
+            TableLayout::Separate => {
+                assert_table_fits(k)?;
+
+                load_u8_table(layouter, self.u8[0], self.u8[1])?;
+                let [tag, x, y, z] = self.xor;
+                load_xor_table(layouter, tag, x, y, z)?;
+                let [tag, x, y] = self.sbox;
+                load_sbox_table(layouter, tag, x, y)?;
+                let [tag, x, y] = self.mul2;
+                load_mul2_table(layouter, tag, x, y)?;
+                let [tag, x, y] = self.mul3;
+                load_mul3_table(layouter, tag, x, y)?;
+                let [tag, x, y] = self.inv;
+                load_inv_table(layouter, tag, x, y)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Load only the tables of the inverse cipher in the circuit of 2^k rows,
+    /// see `load_dec_full_table`. The key schedule and the encryption look up the
+    /// tables left out, so only `Aes128Scheduled::decrypt` under precomputed round keys works.
+    pub fn load_dec(&self, layouter: &mut impl Layouter<Fp>, k: u32) -> Result<(), AesError> {
+        match self.layout {
+            TableLayout::Combined => {
+                load_dec_full_table_with_tag_offset(layouter, self.xor, k, self.tag_offset)
+            }
+            TableLayout::Separate => {
+                check_table_rows(dec_full_table_rows(), k)?;
+
+                load_u8_table(layouter, self.u8[0], self.u8[1])?;
+                let [tag, x, y, z] = self.xor;
+                load_xor_table(layouter, tag, x, y, z)?;
+                let [tag, x, y] = self.inv;
+                load_inv_table(layouter, tag, x, y)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Name the table columns, see `annotate_table_columns`.
+    #[cfg(feature = "halo2-pse")]
+    pub fn annotate(&self, meta: &mut ConstraintSystem<Fp>) {
+        match self.layout {
+            TableLayout::Combined => annotate_table_columns(meta, self.xor),
+            TableLayout::Separate => {
+                let names = ["tag", "x", "y", "z"];
+                let tables = [
+                    ("u8", &self.u8[..]),
+                    ("xor", &self.xor[..]),
+                    ("sbox", &self.sbox[..]),
+                    ("mul2", &self.mul2[..]),
+                    ("mul3", &self.mul3[..]),
+                    ("inv", &self.inv[..]),
+                ];
+                for (op, columns) in tables {
+                    for (&column, name) in columns.iter().zip(names) {
+                        meta.annotate_lookup_column(column, || format!("{}_table_{}", op, name));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Check if the table loaded by `load_enc_full_table` fits in the circuit of 2^k rows.
+pub fn assert_table_fits(k: u32) -> Result<(), AesError> {
+    check_table_rows(full_table_rows(), k)
 }
 
+/// Load the tables used by the cipher: u8, sbox, xor, mul2, mul3 and gf mul,
+/// and the inverse sbox and mul by 9, 11, 13 and 14 of the inverse cipher.
+/// Fails with `AesError::TableTooLargeForK` before assigning any row
+/// if the table doesn't fit in the circuit of 2^k rows.
 pub fn load_enc_full_table(
     layouter: &mut impl Layouter<Fp>,
     tables: [TableColumn; 4],
-) -> Result<(), Error> {
+    k: u32,
+) -> Result<(), AesError> {
+    load_enc_full_table_with_tag_offset(layouter, tables, k, 0)
+}
+
+/// Same as `load_enc_full_table` with all the tags shifted by `tag_offset`,
+/// matching the chips configured with the same offset,
+/// see `TableColumns::configure_with_tag_offset`.
+pub fn load_enc_full_table_with_tag_offset(
+    layouter: &mut impl Layouter<Fp>,
+    tables: [TableColumn; 4],
+    k: u32,
+    tag_offset: u64,
+) -> Result<(), AesError> {
+    assert_table_fits(k)?;
+
     layouter.assign_table(
         || "Assign full table",
+        |mut table| {
+            let rows = assign_full_table(&mut table, &tables, tag_offset)?;
+            debug_assert_eq!(rows, full_table_rows());
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+// Assign the rows of the full table, returning the number of the assigned rows.
+fn assign_full_table(
+    table: &mut Table<'_, Fp>,
+    tables: &[TableColumn; 4],
+    tag_offset: u64,
+) -> Result<usize, Error> {
+    assign_rows(table, tables, 0, enc_full_table_row_values(tag_offset))
+}
+
+// Rows of the full table in the order they are assigned, ending with the empty row.
+// Shared with the tests replaying the loader.
+fn enc_full_table_row_values(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    u8_rows(tag_offset)
+        .chain(sbox_rows(tag_offset))
+        .chain(xor_rows(tag_offset))
+        .chain(mul2_rows(tag_offset))
+        .chain(mul3_rows(tag_offset))
+        .chain(gf_log_rows(tag_offset))
+        .chain(gf_exp_rows(tag_offset))
+        .chain(inv_rows(tag_offset))
+        .chain(zero_row())
+}
+
+/// Load the tables used by the inverse cipher: u8, xor, inverse sbox and mul by 9, 11, 13, 14.
+/// Unlike `load_enc_full_table`, the forward sbox, mul2 and mul3 tables aren't loaded,
+/// so a decryption-only circuit doesn't pay for them.
+/// Fails with `AesError::TableTooLargeForK` if the table doesn't fit in 2^k rows.
+pub fn load_dec_full_table(
+    layouter: &mut impl Layouter<Fp>,
+    tables: [TableColumn; 4],
+    k: u32,
+) -> Result<(), AesError> {
+    load_dec_full_table_with_tag_offset(layouter, tables, k, 0)
+}
+
+/// Same as `load_dec_full_table` with all the tags shifted by `tag_offset`,
+/// see `load_enc_full_table_with_tag_offset`.
+pub fn load_dec_full_table_with_tag_offset(
+    layouter: &mut impl Layouter<Fp>,
+    tables: [TableColumn; 4],
+    k: u32,
+    tag_offset: u64,
+) -> Result<(), AesError> {
+    check_table_rows(dec_full_table_rows(), k)?;
+
+    layouter.assign_table(
+        || "Assign full decryption table",
         |mut table| {
             let mut offset = 0;
-            // Assign for u8 range check
-            for i in 0..=u8::MAX {
-                let pos = (i as usize) + offset;
-                table.assign_cell(
-                    || "",
-                    tables[0],
-                    pos,
-                    || Value::known(Fp::from(Tag::U8 as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign cell for u8 range_check",
-                    tables[1],
-                    pos,
-                    || Value::known(Fp::from(i as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign empty",
-                    tables[2],
-                    pos,
-                    || Value::known(Fp::from(0)),
-                )?;
-                table.assign_cell(
-                    || "assign empty",
-                    tables[3],
-                    pos,
-                    || Value::known(Fp::from(0)),
-                )?;
+            offset = assign_rows(&mut table, &tables, offset, u8_rows(tag_offset))?;
+            offset = assign_rows(&mut table, &tables, offset, xor_rows(tag_offset))?;
+            offset = assign_rows(&mut table, &tables, offset, inv_rows(tag_offset))?;
+
+            // Add empty row
+            let rows = assign_rows(&mut table, &tables, offset, zero_row())?;
+            debug_assert_eq!(rows, dec_full_table_rows());
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+/// Load only the u8 range check table.
+pub fn load_u8_table(
+    layouter: &mut impl Layouter<Fp>,
+    tag_col: TableColumn,
+    value_col: TableColumn,
+) -> Result<(), Error> {
+    load_single_table(
+        layouter,
+        || "Assign u8 table",
+        &[tag_col, value_col],
+        u8_rows(0),
+    )
+}
+
+/// Load only the sbox table.
+pub fn load_sbox_table(
+    layouter: &mut impl Layouter<Fp>,
+    tag_col: TableColumn,
+    in_col: TableColumn,
+    out_col: TableColumn,
+) -> Result<(), Error> {
+    load_single_table(
+        layouter,
+        || "Assign sbox table",
+        &[tag_col, in_col, out_col],
+        sbox_rows(0),
+    )
+}
+
+/// Load only the u8 XOR table.
+pub fn load_xor_table(
+    layouter: &mut impl Layouter<Fp>,
+    tag_col: TableColumn,
+    x_col: TableColumn,
+    y_col: TableColumn,
+    z_col: TableColumn,
+) -> Result<(), Error> {
+    load_single_table(
+        layouter,
+        || "Assign xor table",
+        &[tag_col, x_col, y_col, z_col],
+        xor_rows(0),
+    )
+}
+
+/// Load only the gf mul by 2 table.
+pub fn load_mul2_table(
+    layouter: &mut impl Layouter<Fp>,
+    tag_col: TableColumn,
+    in_col: TableColumn,
+    out_col: TableColumn,
+) -> Result<(), Error> {
+    load_single_table(
+        layouter,
+        || "Assign mul2 table",
+        &[tag_col, in_col, out_col],
+        mul2_rows(0),
+    )
+}
+
+/// Load only the gf mul by 3 table.
+pub fn load_mul3_table(
+    layouter: &mut impl Layouter<Fp>,
+    tag_col: TableColumn,
+    in_col: TableColumn,
+    out_col: TableColumn,
+) -> Result<(), Error> {
+    load_single_table(
+        layouter,
+        || "Assign mul3 table",
+        &[tag_col, in_col, out_col],
+        mul3_rows(0),
+    )
+}
+
+/// Load only the inverse sbox and mul by 9, 11, 13 and 14 tables of the inverse cipher.
+pub fn load_inv_table(
+    layouter: &mut impl Layouter<Fp>,
+    tag_col: TableColumn,
+    in_col: TableColumn,
+    out_col: TableColumn,
+) -> Result<(), Error> {
+    load_single_table(
+        layouter,
+        || "Assign inverse cipher table",
+        &[tag_col, in_col, out_col],
+        inv_rows(0),
+    )
+}
+
+/// Load only the gf log and exp tables used by the multiplication of two bytes.
+pub fn load_gf_mul_table(
+    layouter: &mut impl Layouter<Fp>,
+    tag_col: TableColumn,
+    in_col: TableColumn,
+    out_col: TableColumn,
+) -> Result<(), Error> {
+    load_single_table(
+        layouter,
+        || "Assign gf mul table",
+        &[tag_col, in_col, out_col],
+        gf_log_rows(0).chain(gf_exp_rows(0)),
+    )
+}
+
+fn load_single_table(
+    layouter: &mut impl Layouter<Fp>,
+    name: impl Fn() -> &'static str,
+    columns: &[TableColumn],
+    rows: impl Iterator<Item = [u64; 4]> + Clone,
+) -> Result<(), Error> {
+    layouter.assign_table(name, |mut table| {
+        let offset = assign_rows(&mut table, columns, 0, rows.clone())?;
+        assign_rows(&mut table, columns, offset, zero_row())?;
+        Ok(())
+    })
+}
+
+/// Assign rows of (tag, input, input or output, output) from the offset.
+/// Only the first `columns.len()` values of each row are assigned.
+/// Returns the offset next to the last assigned row.
+fn assign_rows(
+    table: &mut Table<'_, Fp>,
+    columns: &[TableColumn],
+    mut offset: usize,
+    rows: impl Iterator<Item = [u64; 4]>,
+) -> Result<usize, Error> {
+    for row in rows {
+        for (&column, &value) in columns.iter().zip(row.iter()) {
+            table.assign_cell(
+                || "assign table cell",
+                column,
+                offset,
+                || Value::known(Fp::from(value)),
+            )?;
+        }
+        offset += 1;
+    }
+    Ok(offset)
+}
+
+fn u8_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    let tag = Tag::U8.value(tag_offset);
+    (0..256).map(move |i| [tag, i, 0, 0])
+}
+
+fn sbox_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    byte_map_rows(Tag::Sbox, tag_offset, S_BOX)
+}
+
+fn xor_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    let tag = Tag::Xor.value(tag_offset);
+    (0..256).flat_map(move |i| (0..256).map(move |j| [tag, i, j, i ^ j]))
+}
+
+fn mul2_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    byte_map_rows(Tag::GfMul2, tag_offset, MUL_BY_2)
+}
+
+fn mul3_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    byte_map_rows(Tag::GfMul3, tag_offset, MUL_BY_3)
+}
+
+// Rows of the maps looked up by `InvCipherChip`
+fn inv_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    byte_map_rows(Tag::InvSbox, tag_offset, INV_S_BOX)
+        .chain(byte_map_rows(Tag::GfMul9, tag_offset, MUL_BY_9))
+        .chain(byte_map_rows(Tag::GfMul11, tag_offset, MUL_BY_11))
+        .chain(byte_map_rows(Tag::GfMul13, tag_offset, MUL_BY_13))
+        .chain(byte_map_rows(Tag::GfMul14, tag_offset, MUL_BY_14))
+}
+
+// Rows of (tag, x, map[x]) for every byte x
+fn byte_map_rows(
+    tag: Tag,
+    tag_offset: u64,
+    map: [u8; 256],
+) -> impl Iterator<Item = [u64; 4]> + Clone {
+    let tag = tag.value(tag_offset);
+    (0..256).map(move |i| [tag, i, map[i as usize] as u64, 0])
+}
+
+fn gf_log_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    let tag = Tag::GfLog.value(tag_offset);
+    (0..256).map(move |i| [tag, i, gf_log(i as u8), 0])
+}
+
+fn gf_exp_rows(tag_offset: u64) -> impl Iterator<Item = [u64; 4]> + Clone {
+    let tag = Tag::GfExp.value(tag_offset);
+    (0..GF_EXP_ROWS as u64).map(move |e| [tag, e, gf_exp(e) as u64, 0])
+}
+
+// Lookups with the disabled selector query all zeros, so the zero row has to be in the table:
+// the layouter pads the rest of the table columns with the first row, a u8 row,
+// so nothing else matches them. It can't be matched by an enabled lookup,
+// since the queried tag is then one of `Tag`, all of which are at least 1 plus the tag offset.
+fn zero_row() -> impl Iterator<Item = [u64; 4]> + Clone {
+    std::iter::once([0; 4])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        aes128::{EncryptCursor, FixedAes128Config},
+        chips::{
+            sbox_chip::{SboxChip, SboxConfig},
+            u8_range_check_chip::{U8RangeCheckChip, U8RangeCheckConfig},
+        },
+        halo2_proofs::{
+            circuit::SimpleFloorPlanner,
+            dev::MockProver,
+            plonk::{Advice, Circuit, Column, ConstraintSystem},
+        },
+        reference::key_schedule_reference,
+        utils::{cells_to_bytes, gf_mul},
+    };
+
+    #[test]
+    fn test_assert_table_fits() {
+        assert!(matches!(
+            assert_table_fits(16),
+            Err(AesError::TableTooLargeForK { needed: 17, k: 16 })
+        ));
+        assert!(assert_table_fits(17).is_ok());
+        assert!(assert_table_fits(20).is_ok());
+    }
+
+    #[derive(Clone)]
+    struct TestFullTableRowsCircuit;
+
+    impl Circuit<Fp> for TestFullTableRowsCircuit {
+        type Config = [TableColumn; 4];
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ]
+        }
+
+        fn synthesize(
+            &self,
+            tables: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "Assign full table",
+                |mut table| {
+                    let rows = assign_full_table(&mut table, &tables, 0)?;
+                    assert_eq!(rows, full_table_rows());
+                    Ok(())
+                },
+            )
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    fn test_full_table_rows() {
+        assert_eq!(full_table_rows(), 69118);
+
+        let mock = MockProver::run(17, &TestFullTableRowsCircuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
+
+    #[derive(Clone)]
+    struct TestLoadFullTableCircuit {
+        k: u32,
+    }
+
+    impl Circuit<Fp> for TestLoadFullTableCircuit {
+        type Config = [TableColumn; 4];
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ]
+        }
+
+        fn synthesize(
+            &self,
+            tables: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            load_enc_full_table(&mut layouter, tables, self.k)?;
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    // Rows of the sub-table with the tag, in the order they are loaded.
+    // The table columns should be the first fixed columns.
+    #[cfg(feature = "halo2-pse")]
+    fn sub_table(mock: &MockProver<Fp>, tag: Tag) -> Vec<(Fp, Fp)> {
+        use crate::halo2_proofs::dev::CellValue;
+
+        let fixed = mock.fixed();
+        let tag = Fp::from(tag as u64);
+        (0..fixed[0].len())
+            .filter_map(
+                |row| match (&fixed[0][row], &fixed[1][row], &fixed[2][row]) {
+                    (CellValue::Assigned(t), CellValue::Assigned(x), CellValue::Assigned(y))
+                        if *t == tag =>
+                    {
+                        Some((*x, *y))
+                    }
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    #[cfg(feature = "halo2-pse")]
+    fn assert_sub_table(mock: &MockProver<Fp>, tag: Tag, expected: [u8; 256]) {
+        let rows = sub_table(mock, tag);
+        assert_eq!(rows.len(), 256);
+        for (i, (x, y)) in rows.into_iter().enumerate() {
+            assert_eq!(x, Fp::from(i as u64));
+            assert_eq!(y, Fp::from(expected[i] as u64));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_loaded_sub_tables() {
+        let mock = MockProver::run(17, &TestLoadFullTableCircuit { k: 17 }, vec![]).unwrap();
+
+        for (tag, expected) in [
+            (Tag::Sbox, S_BOX),
+            (Tag::GfMul2, MUL_BY_2),
+            (Tag::GfMul3, MUL_BY_3),
+            (Tag::InvSbox, INV_S_BOX),
+            (Tag::GfMul9, MUL_BY_9),
+            (Tag::GfMul11, MUL_BY_11),
+            (Tag::GfMul13, MUL_BY_13),
+            (Tag::GfMul14, MUL_BY_14),
+        ] {
+            assert_sub_table(&mock, tag, expected);
+        }
+    }
+
+    #[test]
+    fn test_load_table_too_large_for_k() {
+        // Fails before assigning the rows beyond 2^16
+        let result = MockProver::run(16, &TestLoadFullTableCircuit { k: 16 }, vec![]);
+        assert!(matches!(
+            result,
+            Err(Error::NotEnoughRowsAvailable { current_k: 16 })
+        ));
+    }
+
+    // FIPS-197 Appendix C.1
+    const FIPS_KEY: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    const FIPS_PLAINTEXT: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff,
+    ];
+    const FIPS_CIPHERTEXT: [u8; 16] = [
+        0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5,
+        0x5a,
+    ];
+
+    // Decrypt with only the decryption table loaded. The round keys are precomputed,
+    // since the key schedule looks up the forward sbox left out of the table.
+    #[derive(Clone)]
+    struct TestDecTableCircuit;
+
+    impl Circuit<Fp> for TestDecTableCircuit {
+        type Config = FixedAes128Config<17, 1>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            FixedAes128Config::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            mut config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            config.load_dec_tables(&mut layouter)?;
+            let aes =
+                config.schedule_key_precomputed(&mut layouter, key_schedule_reference(FIPS_KEY))?;
+            let mut cursor = EncryptCursor::default();
+
+            let plaintext = aes.decrypt(&mut cursor, &mut layouter, FIPS_CIPHERTEXT)?;
+            cells_to_bytes(&plaintext).assert_if_known(|bytes| bytes[..] == FIPS_PLAINTEXT);
+            aes.expose_ciphertext(&mut cursor, &mut layouter, &plaintext)?;
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    fn fips_plaintext_instance() -> Vec<Vec<Fp>> {
+        vec![FIPS_PLAINTEXT.iter().map(|&b| Fp::from(b as u64)).collect()]
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_dec_full_table() {
+        assert_eq!(dec_full_table_rows(), 67073);
+
+        let mock = MockProver::run(17, &TestDecTableCircuit, fips_plaintext_instance()).unwrap();
+        mock.assert_satisfied();
+
+        // Only the inverse tables are loaded besides u8 and xor
+        for tag in [Tag::Sbox, Tag::GfMul2, Tag::GfMul3, Tag::GfLog, Tag::GfExp] {
+            assert!(sub_table(&mock, tag).is_empty());
+        }
+        for (tag, expected) in [
+            (Tag::InvSbox, INV_S_BOX),
+            (Tag::GfMul9, MUL_BY_9),
+            (Tag::GfMul11, MUL_BY_11),
+            (Tag::GfMul13, MUL_BY_13),
+            (Tag::GfMul14, MUL_BY_14),
+        ] {
+            assert_sub_table(&mock, tag, expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_dec_full_table_wrong_inverse_fails() {
+        use crate::{
+            fault::{with_fault, FaultSite},
+            halo2_proofs::dev::VerifyFailure,
+        };
+
+        // A wrong inverse sbox, then a wrong InvMixColumns product, fails its lookup
+        for index in [0, 16] {
+            let mock = with_fault(FaultSite::InvCipher, index, || {
+                MockProver::run(17, &TestDecTableCircuit, fips_plaintext_instance()).unwrap()
+            });
+            let failures = mock.verify().unwrap_err();
+            assert!(failures
+                .iter()
+                .any(|failure| matches!(failure, VerifyFailure::Lookup { .. })));
+        }
+    }
+
+    // Full table replayed out of the circuit, with the rows assigned by the loader
+    fn replay_full_table(tag_offset: u64) -> Vec<(Fp, Fp, Fp, Fp)> {
+        enc_full_table_row_values(tag_offset)
+            .map(|[t, x, y, z]| (Fp::from(t), Fp::from(x), Fp::from(y), Fp::from(z)))
+            .collect()
+    }
+
+    // Rows the full table should have, computed independently of the row functions
+    fn expected_full_table(tag_offset: u64) -> Vec<[u64; 4]> {
+        let tag = |tag: Tag| tag.value(tag_offset);
+        let mut rows = vec![[0; 4]];
+        for x in 0..=255u8 {
+            let x64 = x as u64;
+            rows.push([tag(Tag::U8), x64, 0, 0]);
+            rows.push([tag(Tag::Sbox), x64, S_BOX[x as usize] as u64, 0]);
+            rows.push([tag(Tag::GfMul2), x64, gf_mul(x, 2) as u64, 0]);
+            rows.push([tag(Tag::GfMul3), x64, gf_mul(x, 3) as u64, 0]);
+            rows.push([tag(Tag::GfLog), x64, gf_log(x), 0]);
+            rows.push([tag(Tag::InvSbox), x64, INV_S_BOX[x as usize] as u64, 0]);
+            for (t, coeff) in [
+                (Tag::GfMul9, 9),
+                (Tag::GfMul11, 11),
+                (Tag::GfMul13, 13),
+                (Tag::GfMul14, 14),
+            ] {
+                rows.push([tag(t), x64, gf_mul(x, coeff) as u64, 0]);
             }
-            offset += 256;
-
-            // Assign sbox
-            for i in 0..256 {
-                let pos = offset + i;
-                table.assign_cell(
-                    || "assign tag for sbox",
-                    tables[0],
-                    pos,
-                    || Value::known(Fp::from(Tag::Sbox as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign cell for sbox input",
-                    tables[1],
-                    pos,
-                    || Value::known(Fp::from(i as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign cell for sbox output",
-                    tables[2],
-                    pos,
-                    || Value::known(Fp::from(S_BOX[i] as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign empty",
-                    tables[3],
-                    pos,
-                    || Value::known(Fp::from(0)),
-                )?;
+            for y in 0..=255u8 {
+                rows.push([tag(Tag::Xor), x64, y as u64, (x ^ y) as u64]);
             }
-            offset += 256;
-
-            // Assign XOR
-            let mut l = offset;
-            for i in 0..=u8::MAX {
-                for j in 0..=u8::MAX {
-                    table.assign_cell(
-                        || "assign tag for xor",
-                        tables[0],
-                        l,
-                        || Value::known(Fp::from(Tag::Xor as u64)),
-                    )?;
-                    table.assign_cell(
-                        || "assign cell for left input of XOR table",
-                        tables[1],
-                        l,
-                        || Value::known(Fp::from(i as u64)),
-                    )?;
-                    table.assign_cell(
-                        || "assign cell for right input of XOR table",
-                        tables[2],
-                        l,
-                        || Value::known(Fp::from(j as u64)),
-                    )?;
-                    table.assign_cell(
-                        || "assign cell for output of XOR table",
-                        tables[3],
-                        l,
-                        || Value::known(Fp::from((i ^ j) as u64)),
-                    )?;
-                    l += 1;
+        }
+        for e in 0..=2 * GF_LOG_ZERO {
+            rows.push([tag(Tag::GfExp), e, gf_exp(e) as u64, 0]);
+        }
+        rows
+    }
+
+    fn row_key(row: &(Fp, Fp, Fp, Fp)) -> [[u8; 32]; 4] {
+        [
+            row.0.to_bytes(),
+            row.1.to_bytes(),
+            row.2.to_bytes(),
+            row.3.to_bytes(),
+        ]
+    }
+
+    #[test]
+    fn test_replayed_full_table() {
+        use std::collections::HashMap;
+
+        for tag_offset in [0, 12] {
+            let table = replay_full_table(tag_offset);
+            assert_eq!(table.len(), full_table_rows());
+
+            // No two rows of a sub-table share the inputs: both bytes for xor, the byte otherwise
+            let mut inputs = HashMap::new();
+            for (i, row) in table.iter().enumerate() {
+                let [t, x, y, _] = row_key(row);
+                let y = (row.0 == Fp::from(Tag::Xor.value(tag_offset))).then_some(y);
+                if let Some(prev) = inputs.insert((t, x, y), i) {
+                    panic!("rows {} and {} have the same tag and inputs", prev, i);
                 }
             }
-            offset += 65536;
-
-            // Assign mul2
-            for i in 0..256 {
-                table.assign_cell(
-                    || "assign tag for mul",
-                    tables[0],
-                    offset + i,
-                    || Value::known(Fp::from(Tag::GfMul2 as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign cell for mul input byte",
-                    tables[1],
-                    offset + i,
-                    || Value::known(Fp::from(i as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign cell for mul output byte",
-                    tables[2],
-                    offset + i,
-                    || Value::known(Fp::from(MUL_BY_2[i] as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign empty",
-                    tables[3],
-                    offset + i,
-                    || Value::known(Fp::from(0)),
-                )?;
+
+            // Every expected row is there exactly once, and nothing else
+            let mut counts = HashMap::new();
+            for row in table.iter() {
+                *counts.entry(row_key(row)).or_insert(0) += 1;
             }
-            offset += 256;
-
-            // Assign mul3
-            for i in 0..256 {
-                table.assign_cell(
-                    || "assign tag for mul",
-                    tables[0],
-                    offset + i,
-                    || Value::known(Fp::from(Tag::GfMul3 as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign cell for mul input byte",
-                    tables[1],
-                    offset + i,
-                    || Value::known(Fp::from(i as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign cell for mul output byte",
-                    tables[2],
-                    offset + i,
-                    || Value::known(Fp::from(MUL_BY_3[i] as u64)),
-                )?;
-                table.assign_cell(
-                    || "assign empty",
-                    tables[3],
-                    offset + i,
-                    || Value::known(Fp::from(0)),
-                )?;
+            let expected = expected_full_table(tag_offset);
+            assert_eq!(expected.len(), table.len());
+            for [t, x, y, z] in expected {
+                let key = row_key(&(Fp::from(t), Fp::from(x), Fp::from(y), Fp::from(z)));
+                assert_eq!(
+                    counts.get(&key),
+                    Some(&1),
+                    "row {:?} should be loaded once",
+                    [t, x, y, z]
+                );
             }
-            offset += 256;
+        }
+    }
 
-            // Add empty row
-            tables.iter().for_each(|&col| {
-                table
-                    .assign_cell(
-                        || "assign zero row",
-                        col,
-                        offset,
-                        || Value::known(Fp::from(0)),
+    #[test]
+    fn test_tags_are_not_zero() {
+        for tag in [
+            Tag::U8,
+            Tag::Xor,
+            Tag::Sbox,
+            Tag::GfMul2,
+            Tag::GfMul3,
+            Tag::GfLog,
+            Tag::GfExp,
+            Tag::InvSbox,
+            Tag::GfMul9,
+            Tag::GfMul11,
+            Tag::GfMul13,
+            Tag::GfMul14,
+        ] {
+            assert_ne!(tag as u64, 0);
+        }
+    }
+
+    #[derive(Clone)]
+    struct TestZeroRowCircuit {
+        byte: u64,
+        with_zero_row: bool,
+    }
+
+    impl Circuit<Fp> for TestZeroRowCircuit {
+        type Config = (U8RangeCheckConfig, Column<Advice>, [TableColumn; 2]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advice = meta.advice_column();
+            let tables = [meta.lookup_table_column(), meta.lookup_table_column()];
+            meta.enable_equality(advice);
+
+            let q_u8_range_check = meta.complex_selector();
+            let range_config =
+                U8RangeCheckChip::configure(meta, advice, q_u8_range_check, tables[0], tables[1]);
+
+            (range_config, advice, tables)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let (range_config, advice, tables) = config;
+            layouter.assign_table(
+                || "Assign u8 table",
+                |mut table| {
+                    let offset = assign_rows(&mut table, &tables, 0, u8_rows(0))?;
+                    if self.with_zero_row {
+                        assign_rows(&mut table, &tables, offset, zero_row())?;
+                    }
+                    Ok(())
+                },
+            )?;
+
+            // The lookup is enabled only on the row of the byte,
+            // the selector is disabled on all the other rows.
+            let byte = layouter.assign_region(
+                || "assign byte",
+                |mut region| {
+                    region.assign_advice(
+                        || "assign byte",
+                        advice,
+                        0,
+                        || Value::known(Fp::from(self.byte)),
                     )
-                    .expect("Should success to assign cell");
-            });
+                },
+            )?;
+            U8RangeCheckChip::construct(range_config).range_check(&mut layouter, &byte)?;
 
             Ok(())
-        },
-    )
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_zero_row() {
+        let mock = MockProver::run(
+            9,
+            &TestZeroRowCircuit {
+                byte: 0xab,
+                with_zero_row: true,
+            },
+            vec![],
+        )
+        .unwrap();
+        mock.assert_satisfied();
+
+        // The zero row doesn't let an enabled lookup through
+        let mock = MockProver::run(
+            9,
+            &TestZeroRowCircuit {
+                byte: 256,
+                with_zero_row: true,
+            },
+            vec![],
+        )
+        .unwrap();
+        assert!(mock.verify().is_err());
+
+        // Without the zero row, the disabled rows query (0, 0), which isn't in the table
+        let mock = MockProver::run(
+            9,
+            &TestZeroRowCircuit {
+                byte: 0xab,
+                with_zero_row: false,
+            },
+            vec![],
+        )
+        .unwrap();
+        assert!(mock.verify().is_err());
+    }
+
+    #[derive(Clone)]
+    struct TestSboxOnlyCircuit {
+        input: u8,
+    }
+
+    impl Circuit<Fp> for TestSboxOnlyCircuit {
+        type Config = (SboxConfig, Column<Advice>, [TableColumn; 3]);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column()];
+            let tables = [
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+                meta.lookup_table_column(),
+            ];
+            advices
+                .iter()
+                .for_each(|&advice| meta.enable_equality(advice));
+
+            let q_sbox = meta.complex_selector();
+            let sbox_config = SboxChip::configure(
+                meta, advices[0], advices[1], q_sbox, tables[0], tables[1], tables[2],
+            );
+
+            (sbox_config, advices[0], tables)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let [tag_col, in_col, out_col] = config.2;
+            load_sbox_table(&mut layouter, tag_col, in_col, out_col)?;
+
+            let input = layouter.assign_region(
+                || "assign input",
+                |mut region| {
+                    region.assign_advice(
+                        || "assign input",
+                        config.1,
+                        0,
+                        || Value::known(Fp::from(self.input as u64)),
+                    )
+                },
+            )?;
+            SboxChip::construct(config.0).substitute(&mut layouter, &input)?;
+
+            Ok(())
+        }
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "halo2-pse")]
+    fn test_sbox_only_table() {
+        // 257 rows of sbox table fit in 2^9 rows, the full table needs k = 17
+        let circuit = TestSboxOnlyCircuit { input: 0x53 };
+
+        let mock = MockProver::run(9, &circuit, vec![]).unwrap();
+        mock.assert_satisfied();
+    }
 }
@@ -1,6 +1,12 @@
 use crate::{
-    constant::S_BOX,
-    halo2_proofs::{circuit::Value, halo2curves::bn256::Fr as Fp},
+    chips::byte_pack_chip::MAX_PACKED_BYTES,
+    constant::{GF_LOG_ZERO, ROUND_CONSTANT, S_BOX},
+    error::AesError,
+    halo2_proofs::{
+        circuit::{AssignedCell, Layouter, Value},
+        halo2curves::bn256::Fr as Fp,
+        plonk::Error,
+    },
 };
 
 /// Calculate xor of given two bytes.
@@ -23,13 +29,122 @@ pub(crate) fn sub_byte(x: &Value<Fp>) -> Value<Fp> {
     x.map(|v| Fp::from(S_BOX[*v.to_bytes().first().unwrap() as usize] as u64))
 }
 
-/// See here for the detailed explanation of the constant.
-/// https://en.wikipedia.org/wiki/AES_key_schedule
-const ROUND_CONSTANT: [u64; 10] = [1, 2, 4, 8, 16, 32, 64, 128, 27, 54];
+/// Get the round constant of the given round, starting from 0.
+pub(crate) fn get_round_constant(round: u32) -> Result<Value<Fp>, AesError> {
+    ROUND_CONSTANT
+        .get(round as usize)
+        .map(|&rc| Value::known(Fp::from(rc as u64)))
+        .ok_or(AesError::RoundConstantOutOfRange { round })
+}
+
+/// Multiply two bytes in GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1.
+pub fn gf_mul(mut x: u8, mut y: u8) -> u8 {
+    let mut res = 0;
+    while y != 0 {
+        if y & 1 == 1 {
+            res ^= x;
+        }
+        let carry = x & 0x80;
+        x <<= 1;
+        if carry != 0 {
+            x ^= 0x1b;
+        }
+        y >>= 1;
+    }
+    res
+}
+
+/// Logarithm to the base 3 (a generator of GF(2^8)*).
+/// Log of 0 is `GF_LOG_ZERO` so that any sum with it is out of the range of the logs of non zero bytes.
+pub(crate) fn gf_log(x: u8) -> u64 {
+    if x == 0 {
+        return GF_LOG_ZERO;
+    }
+    let mut e = 0;
+    let mut v = 1;
+    while v != x {
+        v = gf_mul(v, 3);
+        e += 1;
+    }
+    e
+}
+
+/// Inverse of `gf_log` extended to sums of two logs, 3^e for e < GF_LOG_ZERO and 0 otherwise.
+pub(crate) fn gf_exp(e: u64) -> u8 {
+    if e >= GF_LOG_ZERO {
+        return 0;
+    }
+    (0..e % 255).fold(1, |v, _| gf_mul(v, 3))
+}
+
+/// Extract byte values of the given cells.
+/// Returns unknown value if any of the cells are not assigned with a known value.
+pub fn cells_to_bytes(cells: &[AssignedCell<Fp, Fp>]) -> Value<Vec<u8>> {
+    cells.iter().fold(Value::known(vec![]), |acc, cell| {
+        acc.zip(cell.value()).map(|(mut bytes, v)| {
+            bytes.push(v.to_bytes()[0]);
+            bytes
+        })
+    })
+}
+
+/// PKCS#7 padding to a multiple of 16 bytes. 1 to 16 bytes of the padding length are
+/// appended, so a block aligned input gets a whole block of padding.
+pub fn pkcs7_pad(data: &[u8]) -> Vec<u8> {
+    let pad = 16 - data.len() % 16;
+    let mut padded = data.to_vec();
+    padded.resize(data.len() + pad, pad as u8);
+    padded
+}
+
+/// Pack a block into 2 field elements of 8 little-endian bytes each,
+/// as taken by `Aes128Scheduled::encrypt_packed`.
+pub fn pack_block(block: &[u8; 16]) -> [Fp; 2] {
+    std::array::from_fn(|i| {
+        Fp::from(u64::from_le_bytes(
+            block[8 * i..8 * i + 8].try_into().unwrap(),
+        ))
+    })
+}
 
-/// Get round constant value from
-pub(crate) fn get_round_constant(round: u32) -> Value<Fp> {
-    Value::known(Fp::from(ROUND_CONSTANT[round as usize]))
+/// Pack the bytes into field elements of up to 31 little-endian bytes each,
+/// as exposed by `Aes128Scheduled::expose_ciphertext_packed`.
+/// The bytes of an element are the first ones of its `to_bytes`.
+pub fn pack_bytes(bytes: &[u8]) -> Vec<Fp> {
+    bytes
+        .chunks(MAX_PACKED_BYTES)
+        .map(|chunk| {
+            chunk.iter().rev().fold(Fp::from(0), |acc, &b| {
+                acc * Fp::from(256) + Fp::from(b as u64)
+            })
+        })
+        .collect()
+}
+
+/// Constrain the cell to equal the constant byte.
+/// NOTE: A constant column must be enabled in the constraint system,
+/// which `FixedAes128Config` does for the key schedule.
+pub fn assert_byte_equals(
+    layouter: &mut impl Layouter<Fp>,
+    cell: &AssignedCell<Fp, Fp>,
+    value: u8,
+) -> Result<(), Error> {
+    layouter.assign_region(
+        || "Assert byte equals",
+        |mut region| region.constrain_constant(cell.cell(), Fp::from(value as u64)),
+    )
+}
+
+/// Constrain the two cells to be equal. Both columns should have equality enabled.
+pub fn assert_cells_equal(
+    layouter: &mut impl Layouter<Fp>,
+    a: &AssignedCell<Fp, Fp>,
+    b: &AssignedCell<Fp, Fp>,
+) -> Result<(), Error> {
+    layouter.assign_region(
+        || "Assert cells equal",
+        |mut region| region.constrain_equal(a.cell(), b.cell()),
+    )
 }
 
 #[cfg(test)]
@@ -45,4 +160,144 @@ mod tests {
 
         z.assert_if_known(|v| v.eq(&Fp::from(9)));
     }
+
+    #[test]
+    fn test_get_round_constant() {
+        get_round_constant(0)
+            .unwrap()
+            .assert_if_known(|rc| *rc == Fp::from(1));
+        get_round_constant(9)
+            .unwrap()
+            .assert_if_known(|rc| *rc == Fp::from(0x36));
+
+        let round = ROUND_CONSTANT.len() as u32;
+        assert!(matches!(
+            get_round_constant(round),
+            Err(AesError::RoundConstantOutOfRange { round: r }) if r == round
+        ));
+    }
+
+    #[test]
+    fn test_gf_mul() {
+        // FIPS-197 4.2
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+    }
+
+    #[test]
+    fn test_pkcs7_pad() {
+        assert_eq!(pkcs7_pad(&[]), vec![16u8; 16]);
+        assert_eq!(
+            pkcs7_pad(&[7u8; 13]),
+            [vec![7u8; 13], vec![3u8; 3]].concat()
+        );
+        assert_eq!(
+            pkcs7_pad(&[7u8; 16]),
+            [vec![7u8; 16], vec![16u8; 16]].concat()
+        );
+    }
+
+    #[test]
+    fn test_gf_log_exp() {
+        for x in 0..=255u8 {
+            for y in 0..=255u8 {
+                assert_eq!(gf_exp(gf_log(x) + gf_log(y)), gf_mul(x, y));
+            }
+        }
+    }
+
+    #[cfg(feature = "halo2-pse")]
+    mod equality {
+        use super::*;
+        use crate::halo2_proofs::{
+            circuit::SimpleFloorPlanner,
+            dev::MockProver,
+            plonk::{Advice, Circuit, Column, ConstraintSystem},
+        };
+
+        #[derive(Clone)]
+        struct TestEqualityCircuit {
+            a: u8,
+            b: u8,
+            expected: u8,
+        }
+
+        impl Circuit<Fp> for TestEqualityCircuit {
+            type Config = Column<Advice>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let advice = meta.advice_column();
+                meta.enable_equality(advice);
+                let constants = meta.fixed_column();
+                meta.enable_constant(constants);
+
+                advice
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                let (a, b) = layouter.assign_region(
+                    || "assign bytes",
+                    |mut region| {
+                        let a = region.assign_advice(
+                            || "a",
+                            config,
+                            0,
+                            || Value::known(Fp::from(self.a as u64)),
+                        )?;
+                        let b = region.assign_advice(
+                            || "b",
+                            config,
+                            1,
+                            || Value::known(Fp::from(self.b as u64)),
+                        )?;
+                        Ok((a, b))
+                    },
+                )?;
+
+                assert_byte_equals(&mut layouter, &a, self.expected)?;
+                assert_cells_equal(&mut layouter, &a, &b)
+            }
+
+            fn without_witnesses(&self) -> Self {
+                unimplemented!()
+            }
+        }
+
+        #[test]
+        fn test_equal_bytes() {
+            let circuit = TestEqualityCircuit {
+                a: 0x2a,
+                b: 0x2a,
+                expected: 0x2a,
+            };
+            let mock = MockProver::run(4, &circuit, vec![]).unwrap();
+            mock.assert_satisfied();
+        }
+
+        #[test]
+        fn test_unequal_bytes_fail() {
+            // Different from the constant
+            let circuit = TestEqualityCircuit {
+                a: 0x2a,
+                b: 0x2a,
+                expected: 0x2b,
+            };
+            let mock = MockProver::run(4, &circuit, vec![]).unwrap();
+            assert!(mock.verify().is_err());
+
+            // Different cells
+            let circuit = TestEqualityCircuit {
+                a: 0x2a,
+                b: 0x2b,
+                expected: 0x2a,
+            };
+            let mock = MockProver::run(4, &circuit, vec![]).unwrap();
+            assert!(mock.verify().is_err());
+        }
+    }
 }